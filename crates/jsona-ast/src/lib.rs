@@ -5,7 +5,7 @@ use serde::{Deserialize, Serialize};
 use serde_json::{Number as JsonNumber, Value};
 use std::{str::FromStr, string::String as StdString};
 
-use jsona::dom::{self, DomNode, Node};
+use jsona::dom::{self, DomNode, Node, NonFinite, NumberRepr};
 use jsona::error::ErrorObject;
 
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
@@ -35,6 +35,11 @@ pub struct Bool {
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct Number {
     pub value: JsonNumber,
+    #[serde(default)]
+    pub repr: NumberRepr,
+    /// Set for NaN and infinities, `value` is `0` then.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub non_finite: Option<NonFinite>,
     pub annotations: Vec<Annotation>,
     pub range: Option<Range>,
 }
@@ -106,8 +111,15 @@ impl From<Ast> for Node {
                 value, annotations, ..
             }) => dom::Bool::new(value, from_annotations(annotations)).into(),
             Ast::Number(Number {
-                value, annotations, ..
-            }) => dom::Number::new(value, from_annotations(annotations)).into(),
+                value,
+                repr,
+                non_finite,
+                annotations,
+                ..
+            }) => match non_finite {
+                Some(v) => dom::Number::from_f64(v.as_f64(), from_annotations(annotations)).into(),
+                None => dom::Number::with_repr(value, repr, from_annotations(annotations)).into(),
+            },
             Ast::String(String {
                 value, annotations, ..
             }) => dom::String::new(value, from_annotations(annotations)).into(),
@@ -166,6 +178,8 @@ fn node_to_ast(value: &Node, mapper: &Mapper) -> Ast {
         }),
         Node::Number(v) => Ast::Number(Number {
             value: v.value().clone(),
+            repr: v.repr(),
+            non_finite: v.non_finite(),
             annotations,
             range,
         }),
@@ -0,0 +1,130 @@
+use crate::App;
+
+use anyhow::anyhow;
+use clap::{Args, ValueEnum};
+use codespan_reporting::files::SimpleFile;
+use jsona::{
+    dom::{ArrayMatch, Change, DiffOptions, Node},
+    parser,
+};
+use jsona_util::environment::Environment;
+use serde_json::{json, Value};
+use tokio::io::AsyncWriteExt;
+
+impl<E: Environment> App<E> {
+    pub async fn execute_diff(&self, cmd: DiffCommand) -> Result<(), anyhow::Error> {
+        let old = self.load_node(&cmd.old).await?;
+        let new = self.load_node(&cmd.new).await?;
+
+        let options = DiffOptions {
+            array_match: match cmd.array_key {
+                Some(key) => ArrayMatch::Key(key),
+                None => ArrayMatch::Index,
+            },
+        };
+        let changes = old.diff(&new, &options);
+
+        let output = match cmd.output {
+            DiffOutput::Human => {
+                let mut output = String::new();
+                for change in &changes {
+                    output.push_str(&human_change(change));
+                    output.push('\n');
+                }
+                output
+            }
+            DiffOutput::Json => {
+                let changes: Vec<Value> = changes.iter().map(json_change).collect();
+                let mut output = serde_json::to_string_pretty(&changes)?;
+                output.push('\n');
+                output
+            }
+        };
+
+        let mut stdout = self.env.stdout();
+        stdout.write_all(output.as_bytes()).await?;
+        stdout.flush().await?;
+        Ok(())
+    }
+
+    async fn load_node(&self, path: &str) -> Result<Node, anyhow::Error> {
+        let (_, source) = self
+            .load_file(path)
+            .await
+            .map_err(|err| anyhow!("failed to read {path}, {err}"))?;
+
+        let parse = parser::parse(&source);
+
+        self.print_parse_errors(&SimpleFile::new(path, &source), &parse.errors, 0.into())
+            .await?;
+
+        if !parse.errors.is_empty() {
+            return Err(anyhow!("syntax errors found in {path}"));
+        }
+
+        let node = parse.into_dom();
+
+        if let Err(errors) = node.validate() {
+            self.print_semantic_errors(&SimpleFile::new(path, &source), errors, 0.into())
+                .await?;
+
+            return Err(anyhow!("semantic errors found in {path}"));
+        }
+
+        Ok(node)
+    }
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct DiffCommand {
+    /// The output format.
+    #[clap(long, value_enum, default_value = "human")]
+    pub output: DiffOutput,
+
+    /// Pair the objects in arrays by the value of this property instead of by position.
+    #[clap(long)]
+    pub array_key: Option<String>,
+
+    /// Path to the old JSONA document.
+    pub old: String,
+
+    /// Path to the new JSONA document.
+    pub new: String,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum DiffOutput {
+    /// One line per change, prefixed with `+`, `-` or `~`.
+    Human,
+    /// An array of changes with their kind, path and values.
+    Json,
+}
+
+fn human_change(change: &Change) -> String {
+    match change {
+        Change::Added { new, .. } => format!("{change}: {}", compact_json(new)),
+        Change::Removed { old, .. } => format!("{change}: {}", compact_json(old)),
+        Change::Changed { old, new, .. } => {
+            format!("{change}: {} -> {}", compact_json(old), compact_json(new))
+        }
+    }
+}
+
+fn json_change(change: &Change) -> Value {
+    let mut value = json!({
+        "kind": change.kind(),
+        "path": change.keys().to_string(),
+        "pointer": change.keys().to_pointer_ext(),
+    });
+    if let Some(old) = change.old_value() {
+        value["old"] = old.to_plain_json();
+    }
+    if let Some(new) = change.new_value() {
+        value["new"] = new.to_plain_json();
+    }
+    value
+}
+
+fn compact_json(node: &Node) -> String {
+    node.to_plain_json().to_string()
+}
@@ -1,9 +1,13 @@
-use crate::{App, GeneralArgs};
+use crate::{is_jsona_lines, App, GeneralArgs};
 
 use anyhow::anyhow;
 use clap::Args;
 use codespan_reporting::files::SimpleFile;
-use jsona::{formatter, parser};
+use jsona::{
+    dom::{DomNode, Node},
+    formatter, lines,
+    parser::{self, Dialect, ParseOptions},
+};
 use jsona_util::environment::Environment;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
@@ -23,11 +27,25 @@ impl<E: Environment> App<E> {
 
         let display_path = "-";
 
-        let p = parser::parse(&source);
+        if cmd.canonical {
+            let canonical = self.format_canonical(display_path, &source, &cmd).await?;
+            return self.write_stdout_or_check(&cmd, &source, &canonical).await;
+        }
+
+        let p = parser::parse_with_options(
+            &source,
+            &ParseOptions {
+                dialect: cmd.dialect,
+            },
+        );
 
         if !p.errors.is_empty() {
-            self.print_parse_errors(&SimpleFile::new(display_path, source.as_str()), &p.errors)
-                .await?;
+            self.print_parse_errors(
+                &SimpleFile::new(display_path, source.as_str()),
+                &p.errors,
+                0.into(),
+            )
+            .await?;
 
             if !cmd.force {
                 return Err(anyhow!("no formatting was done due to syntax errors"));
@@ -37,6 +55,15 @@ impl<E: Environment> App<E> {
 
         let formatted = formatter::format_syntax(p.into_syntax(), format_opts);
 
+        self.write_stdout_or_check(&cmd, &source, &formatted).await
+    }
+
+    async fn write_stdout_or_check(
+        &self,
+        cmd: &FormatCommand,
+        source: &str,
+        formatted: &str,
+    ) -> Result<(), anyhow::Error> {
         if cmd.check {
             if source != formatted {
                 return Err(anyhow!("the input was not properly formatted"));
@@ -52,6 +79,12 @@ impl<E: Environment> App<E> {
 
     #[tracing::instrument(skip_all)]
     async fn format_files(&mut self, cmd: FormatCommand) -> Result<(), anyhow::Error> {
+        if cmd.canonical && !cmd.check && cmd.files.len() > 1 {
+            return Err(anyhow!(
+                "the canonical form is written to the standard output, one file at a time"
+            ));
+        }
+
         let mut result = Ok(());
 
         let format_opts = self.format_options(&cmd)?;
@@ -62,27 +95,58 @@ impl<E: Environment> App<E> {
                 .await
                 .map_err(|err| anyhow!("failed to read {path}, {err}"))?;
 
-            let p = parser::parse(&source);
+            let formatted = if cmd.canonical {
+                match self.format_canonical(path, &source, &cmd).await {
+                    Ok(formatted) => formatted,
+                    Err(error) => {
+                        result = Err(error);
+                        continue;
+                    }
+                }
+            } else if is_jsona_lines(path) {
+                match self.format_lines(path, &source, &cmd).await {
+                    Ok(formatted) => formatted,
+                    Err(error) => {
+                        result = Err(error);
+                        continue;
+                    }
+                }
+            } else {
+                let p = parser::parse_with_options(
+                    &source,
+                    &ParseOptions {
+                        dialect: cmd.dialect,
+                    },
+                );
 
-            if !p.errors.is_empty() {
-                self.print_parse_errors(&SimpleFile::new(path, source.as_str()), &p.errors)
+                if !p.errors.is_empty() {
+                    self.print_parse_errors(
+                        &SimpleFile::new(path, source.as_str()),
+                        &p.errors,
+                        0.into(),
+                    )
                     .await?;
 
-                if !cmd.force {
-                    result = Err(anyhow!(
-                        "some files were not formatted due to syntax errors"
-                    ));
-                    continue;
+                    if !cmd.force {
+                        result = Err(anyhow!(
+                            "some files were not formatted due to syntax errors"
+                        ));
+                        continue;
+                    }
                 }
-            }
 
-            let formatted = formatter::format_syntax(p.into_syntax(), format_opts.clone());
+                formatter::format_syntax(p.into_syntax(), format_opts.clone())
+            };
 
             if cmd.check {
                 if source != formatted {
                     tracing::error!(?path, "the file is not properly formatted");
                     result = Err(anyhow!("some files were not properly formatted"));
                 }
+            } else if cmd.canonical {
+                let mut stdout = self.env.stdout();
+                stdout.write_all(formatted.as_bytes()).await?;
+                stdout.flush().await?;
             } else if source != formatted {
                 self.env.write_file(&url, formatted.as_bytes()).await?;
             }
@@ -91,6 +155,101 @@ impl<E: Environment> App<E> {
         result
     }
 
+    /// Write every record of a JSONA Lines input in compact form on its own line.
+    ///
+    /// Records with comments are kept as they are, since the compact form has no room for them.
+    async fn format_lines(
+        &self,
+        path: &str,
+        source: &str,
+        cmd: &FormatCommand,
+    ) -> Result<String, anyhow::Error> {
+        let file = SimpleFile::new(path, source);
+        let options = ParseOptions {
+            dialect: cmd.dialect,
+        };
+        let mut writer = lines::Writer::new(vec![]);
+        let mut valid = true;
+
+        for record in lines::Reader::with_options(source, options) {
+            let text = &source[record.range];
+            if !record.errors.is_empty() {
+                self.print_parse_errors(&file, &record.errors, record.range.start())
+                    .await?;
+                valid = false;
+            }
+            if !record.errors.is_empty() || has_comments(&record.node) {
+                let inner = writer.get_mut();
+                inner.extend_from_slice(text.trim().as_bytes());
+                inner.push(b'\n');
+            } else {
+                writer.write(&record.node)?;
+            }
+        }
+
+        if !valid && !cmd.force {
+            return Err(anyhow!("no formatting was done due to syntax errors"));
+        }
+
+        Ok(String::from_utf8(writer.into_inner())?)
+    }
+
+    /// Write the canonical form of the input, see [Node::to_canonical_string].
+    ///
+    /// There is no trailing line break, so the output can be hashed as is,
+    /// JSONA Lines inputs are written with one canonical record per line instead.
+    /// Inputs with errors are never written, since their canonical form would be ambiguous.
+    async fn format_canonical(
+        &self,
+        path: &str,
+        source: &str,
+        cmd: &FormatCommand,
+    ) -> Result<String, anyhow::Error> {
+        let file = SimpleFile::new(path, source);
+        let options = ParseOptions {
+            dialect: cmd.dialect,
+        };
+
+        if !is_jsona_lines(path) {
+            let p = parser::parse_with_options(source, &options);
+            if !p.errors.is_empty() {
+                self.print_parse_errors(&file, &p.errors, 0.into()).await?;
+                return Err(anyhow!("syntax errors found in {path}"));
+            }
+            let node = p.into_dom();
+            if let Err(errors) = node.validate() {
+                self.print_semantic_errors(&file, errors, 0.into()).await?;
+                return Err(anyhow!("semantic errors found in {path}"));
+            }
+            return Ok(node.to_canonical_string());
+        }
+
+        let mut output = String::new();
+        let mut valid = true;
+        for record in lines::Reader::with_options(source, options) {
+            let offset = record.range.start();
+            if !record.errors.is_empty() {
+                self.print_parse_errors(&file, &record.errors, offset)
+                    .await?;
+                valid = false;
+                continue;
+            }
+            if let Err(errors) = record.node.validate() {
+                self.print_semantic_errors(&file, errors, offset).await?;
+                valid = false;
+                continue;
+            }
+            output.push_str(&record.node.to_canonical_string());
+            output.push('\n');
+        }
+
+        if !valid {
+            return Err(anyhow!("errors found in {path}"));
+        }
+
+        Ok(output)
+    }
+
     fn format_options(&self, cmd: &FormatCommand) -> Result<formatter::Options, anyhow::Error> {
         let mut format_opts = formatter::Options::default();
         format_opts.update_from_str(cmd.options.iter().filter_map(|s| {
@@ -106,7 +265,7 @@ impl<E: Environment> App<E> {
             }
         }))?;
 
-        Ok(format_opts)
+        Ok(format_opts.with_dialect(cmd.dialect))
     }
 }
 
@@ -127,8 +286,34 @@ pub struct FormatCommand {
     #[clap(long)]
     pub check: bool,
 
+    /// Write the canonical form of the documents instead, for hashing and signing.
+    ///
+    /// Comments and layout are dropped, properties and annotations are sorted and
+    /// numbers are normalized. Documents with errors are never written.
+    ///
+    /// The canonical form is written to the standard output and never replaces a file,
+    /// so only one file can be given unless with `--check`.
+    #[clap(long)]
+    pub canonical: bool,
+
+    /// The grammar to accept, one of jsona, json, jsonc or json5.
+    #[clap(long, default_value = "jsona")]
+    pub dialect: Dialect,
+
     /// JSONA files to format.
     ///
+    /// Files with the `.jsonal` extension are read as JSONA Lines, one document per line.
+    ///
     /// If the only argument is "-", the standard input will be used.
     pub files: Vec<String>,
 }
+
+fn has_comments(node: &Node) -> bool {
+    node.syntax()
+        .and_then(|v| v.ancestors().last())
+        .map(|root| {
+            root.descendants_with_tokens()
+                .any(|v| v.kind().is_comment())
+        })
+        .unwrap_or_default()
+}
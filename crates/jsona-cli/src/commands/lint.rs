@@ -1,15 +1,22 @@
-use crate::{App, GeneralArgs};
+use crate::{is_jsona_lines, App, GeneralArgs};
 
 use anyhow::{anyhow, Context};
 use clap::Args;
 use codespan_reporting::files::SimpleFile;
-use jsona::parser;
+use jsona::{
+    dom::Node,
+    lines,
+    parser::{self, Dialect, ParseOptions},
+    rowan::{TextRange, TextSize},
+};
 use jsona_util::{
     environment::Environment,
+    include,
     schema::associations::{AssociationRule, SchemaAssociation},
 };
 use serde_json::json;
 use tokio::io::AsyncReadExt;
+use url::Url;
 
 impl<E: Environment> App<E> {
     pub async fn execute_lint(&mut self, cmd: LintCommand) -> Result<(), anyhow::Error> {
@@ -55,8 +62,8 @@ impl<E: Environment> App<E> {
     }
 
     #[tracing::instrument(skip_all)]
-    async fn lint_stdin(&self, _cmd: LintCommand) -> Result<(), anyhow::Error> {
-        self.lint_file("-", true).await
+    async fn lint_stdin(&self, cmd: LintCommand) -> Result<(), anyhow::Error> {
+        self.lint_file("-", true, &cmd).await
     }
 
     #[tracing::instrument(skip_all)]
@@ -64,7 +71,7 @@ impl<E: Environment> App<E> {
         let mut result = Ok(());
 
         for file in &cmd.files {
-            if let Err(error) = self.lint_file(file, false).await {
+            if let Err(error) = self.lint_file(file, false, &cmd).await {
                 tracing::error!(%error, path = ?file, "invalid file");
                 result = Err(anyhow!("some files were not valid"));
             }
@@ -74,7 +81,12 @@ impl<E: Environment> App<E> {
     }
 
     #[tracing::instrument(skip_all, fields(%file_path))]
-    async fn lint_file(&self, file_path: &str, stdin: bool) -> Result<(), anyhow::Error> {
+    async fn lint_file(
+        &self,
+        file_path: &str,
+        stdin: bool,
+        cmd: &LintCommand,
+    ) -> Result<(), anyhow::Error> {
         let (file_uri, source) = if stdin {
             let mut source = String::new();
             self.env
@@ -88,8 +100,18 @@ impl<E: Environment> App<E> {
                 .await
                 .map_err(|err| anyhow!("failed to read {file_path}, {err}"))?
         };
-        let parse = parser::parse(&source);
-        self.print_parse_errors(&SimpleFile::new(file_path, &source), &parse.errors)
+        if is_jsona_lines(file_path) {
+            return self.lint_lines(file_path, &file_uri, &source, cmd).await;
+        }
+
+        let parse = parser::parse_with_options(
+            &source,
+            &ParseOptions {
+                dialect: cmd.dialect,
+            },
+        );
+        let file = SimpleFile::new(file_path, source.as_str());
+        self.print_parse_errors(&file, &parse.errors, 0.into())
             .await?;
 
         if !parse.errors.is_empty() {
@@ -97,19 +119,85 @@ impl<E: Environment> App<E> {
         }
 
         let dom = parse.into_dom();
+        let range = TextRange::up_to(TextSize::of(source.as_str()));
+        self.lint_dom(&file, &file_uri, &dom, range, cmd, true)
+            .await
+    }
 
+    /// Lint every record of a JSONA Lines file, reporting errors of all records.
+    ///
+    /// The schema of the file is associated from its first valid record.
+    async fn lint_lines(
+        &self,
+        file_path: &str,
+        file_uri: &Url,
+        source: &str,
+        cmd: &LintCommand,
+    ) -> Result<(), anyhow::Error> {
+        let file = SimpleFile::new(file_path, source);
+        let options = ParseOptions {
+            dialect: cmd.dialect,
+        };
+        let mut result = Ok(());
+
+        let records: Vec<_> = lines::Reader::with_options(source, options).collect();
+        if let Some(record) = records.iter().find(|v| v.errors.is_empty()) {
+            self.schemas
+                .associations()
+                .add_from_document(file_uri, &record.node);
+        }
+
+        for record in records {
+            let offset = record.range.start();
+            if !record.errors.is_empty() {
+                self.print_parse_errors(&file, &record.errors, offset)
+                    .await?;
+                result = Err(anyhow!("syntax errors found"));
+                continue;
+            }
+            if let Err(error) = self
+                .lint_dom(&file, file_uri, &record.node, record.range, cmd, false)
+                .await
+            {
+                result = Err(error);
+            }
+        }
+
+        result
+    }
+
+    /// Lint the document in `range` of the file, a whole file or a record of JSONA Lines.
+    ///
+    /// Schemas are associated from the document once it is valid if `associate` is set.
+    async fn lint_dom(
+        &self,
+        file: &SimpleFile<&str, &str>,
+        file_uri: &Url,
+        dom: &Node,
+        range: TextRange,
+        cmd: &LintCommand,
+        associate: bool,
+    ) -> Result<(), anyhow::Error> {
+        let offset = range.start();
         if let Err(errors) = dom.validate() {
-            self.print_semantic_errors(&SimpleFile::new(file_path, &source), errors)
-                .await?;
+            self.print_semantic_errors(file, errors, offset).await?;
 
             return Err(anyhow!("semantic errors found"));
         }
 
-        self.schemas
-            .associations()
-            .add_from_document(&file_uri, &dom);
+        if associate {
+            self.schemas.associations().add_from_document(file_uri, dom);
+        }
+
+        let source = &file.source()[range];
+        let composed = include::compose(&self.env, file_uri, source, dom).await;
+        if !composed.errors.is_empty() {
+            self.print_include_errors(file, &composed, offset).await?;
 
-        if let Some(schema_association) = self.schemas.associations().query_for(&file_uri) {
+            return Err(anyhow!("include errors found"));
+        }
+
+        if let Some(schema_association) = self.schemas.associations().query_for(file_uri) {
             tracing::debug!(
                 schema.url = %schema_association.url,
                 schema.name = schema_association.meta["name"].as_str().unwrap_or(""),
@@ -117,10 +205,13 @@ impl<E: Environment> App<E> {
                 "using schema"
             );
 
-            let errors = self.schemas.validate(&schema_association.url, &dom).await?;
+            let errors = self
+                .schemas
+                .validate(&schema_association.url, &composed.node)
+                .await?;
 
             if !errors.is_empty() {
-                self.print_schema_errors(&SimpleFile::new(file_path, &source), &dom, &errors)
+                self.print_schema_errors(file, &composed, &errors, cmd.pointer, offset)
                     .await?;
 
                 return Err(anyhow!("schema validation failed"));
@@ -150,6 +241,16 @@ pub struct LintCommand {
     #[clap(short = 'S', long = "default-schemastore")]
     pub default_schemastore: bool,
 
+    /// Note the JSON Pointer of schema errors, annotations are addressed by `@` segments.
+    #[clap(long)]
+    pub pointer: bool,
+
+    /// The grammar to accept, one of jsona, json, jsonc or json5.
+    #[clap(long, default_value = "jsona")]
+    pub dialect: Dialect,
+
     /// Paths or glob patterns to JSONA documents.
+    ///
+    /// Files with the `.jsonal` extension are read as JSONA Lines, one document per line.
     pub files: Vec<String>,
 }
@@ -1,8 +1,9 @@
 #[cfg(feature = "lsp")]
 use self::lsp::LspCommand;
-use self::{format::FormatCommand, lint::LintCommand, queries::GetCommand};
+use self::{diff::DiffCommand, format::FormatCommand, lint::LintCommand, queries::GetCommand};
 use crate::App;
 
+mod diff;
 mod format;
 mod lint;
 #[cfg(feature = "lsp")]
@@ -36,6 +37,7 @@ impl<E: Environment> App<E> {
             }
             JsonaCommand::Lint(cmd) => self.execute_lint(cmd).await,
             JsonaCommand::Get(cmd) => self.execute_get(cmd).await,
+            JsonaCommand::Diff(cmd) => self.execute_diff(cmd).await,
         }
     }
 }
@@ -88,4 +90,8 @@ pub enum JsonaCommand {
     },
     /// Extract a value from the given JSONA document.
     Get(GetCommand),
+    /// Show the structural differences between two JSONA documents.
+    ///
+    /// Layout, comments and representations of strings and numbers are ignored.
+    Diff(DiffCommand),
 }
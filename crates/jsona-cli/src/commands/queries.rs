@@ -1,11 +1,11 @@
-use crate::App;
+use crate::{is_jsona_lines, App};
 
 use anyhow::{anyhow, bail};
-use clap::Args;
+use clap::{Args, ValueEnum};
 use codespan_reporting::files::SimpleFile;
 use jsona::{
-    dom::{DomNode, Node, QueryKeys},
-    parser,
+    dom::{DomNode, ExportError, Node, NonFiniteMode, QueryKeys},
+    lines, parser,
 };
 use jsona_util::environment::Environment;
 use serde_json::{json, Value};
@@ -28,12 +28,20 @@ impl<E: Environment> App<E> {
             }
         };
 
-        let parse = parser::parse(&source);
-
         let file_path = cmd.file_path.as_deref().unwrap_or("-");
 
-        self.print_parse_errors(&SimpleFile::new(file_path, &source), &parse.errors)
-            .await?;
+        if is_jsona_lines(file_path) {
+            return self.get_lines(file_path, &source, &cmd).await;
+        }
+
+        let parse = parser::parse(&source);
+
+        self.print_parse_errors(
+            &SimpleFile::new(file_path, &source),
+            &parse.errors,
+            0.into(),
+        )
+        .await?;
 
         if !parse.errors.is_empty() {
             return Err(anyhow!("syntax errors found"));
@@ -42,40 +50,14 @@ impl<E: Environment> App<E> {
         let node = parse.into_dom();
 
         if let Err(errors) = node.validate() {
-            self.print_semantic_errors(&SimpleFile::new(file_path, &source), errors)
+            self.print_semantic_errors(&SimpleFile::new(file_path, &source), errors, 0.into())
                 .await?;
 
             return Err(anyhow!("semantic errors found"));
         }
 
-        let nodes = match cmd.pattern {
-            Some(p) => {
-                let p = p.trim_start_matches('.');
-
-                let keys = p.parse::<QueryKeys>().map_err(|errors| {
-                    anyhow!(
-                        "invalid pattern: {}",
-                        errors
-                            .into_iter()
-                            .map(|v| v.to_string())
-                            .collect::<Vec<String>>()
-                            .join(",")
-                    )
-                })?;
-
-                node.matches_all(keys, false)
-                    .map_err(|err| anyhow!("invalid pattern: {err}"))?
-                    .map(|(_, v)| v)
-                    .collect()
-            }
-            None => vec![node],
-        };
+        let items = to_values(&select(node, &cmd.pattern)?, &cmd)?;
         let buf = {
-            let items: Vec<Value> = if cmd.annotation {
-                nodes.iter().map(to_json).collect()
-            } else {
-                nodes.iter().map(|v| v.to_plain_json()).collect()
-            };
             let value = match items.len() {
                 0 => {
                     bail!("no found");
@@ -93,6 +75,86 @@ impl<E: Environment> App<E> {
         stdout.flush().await?;
         Ok(())
     }
+
+    /// Query every record of a JSONA Lines file, printing one JSON value per line.
+    async fn get_lines(
+        &self,
+        file_path: &str,
+        source: &str,
+        cmd: &GetCommand,
+    ) -> Result<(), anyhow::Error> {
+        let file = SimpleFile::new(file_path, source);
+        let mut buf = vec![];
+
+        for record in lines::Reader::new(source) {
+            let offset = record.range.start();
+            if !record.errors.is_empty() {
+                self.print_parse_errors(&file, &record.errors, offset)
+                    .await?;
+                return Err(anyhow!("syntax errors found"));
+            }
+            if let Err(errors) = record.node.validate() {
+                self.print_semantic_errors(&file, errors, offset).await?;
+                return Err(anyhow!("semantic errors found"));
+            }
+            for value in to_values(&select(record.node, &cmd.pattern)?, cmd)? {
+                serde_json::to_writer(&mut buf, &value)?;
+                buf.push(b'\n');
+            }
+        }
+
+        if buf.is_empty() {
+            bail!("no found");
+        }
+        let mut stdout = self.env.stdout();
+        stdout.write_all(&buf).await?;
+        stdout.flush().await?;
+        Ok(())
+    }
+}
+
+/// Find the nodes matching the pattern, or the node itself without a pattern.
+fn select(node: Node, pattern: &Option<String>) -> Result<Vec<Node>, anyhow::Error> {
+    let nodes = match pattern {
+        Some(p) if p.starts_with('/') => node.pointer_ext(p).into_iter().collect(),
+        Some(p) => {
+            let p = p.trim_start_matches('.');
+
+            let keys = p.parse::<QueryKeys>().map_err(|errors| {
+                anyhow!(
+                    "invalid pattern: {}",
+                    errors
+                        .into_iter()
+                        .map(|v| v.to_string())
+                        .collect::<Vec<String>>()
+                        .join(",")
+                )
+            })?;
+
+            node.matches_all(keys, false)
+                .map_err(|err| anyhow!("invalid pattern: {err}"))?
+                .map(|(_, v)| v)
+                .collect()
+        }
+        None => vec![node],
+    };
+    Ok(nodes)
+}
+
+fn to_values(nodes: &[Node], cmd: &GetCommand) -> Result<Vec<Value>, anyhow::Error> {
+    let mode = cmd.non_finite.into();
+    let items = if cmd.annotation {
+        nodes
+            .iter()
+            .map(|v| to_json(v, mode))
+            .collect::<Result<_, _>>()
+    } else {
+        nodes
+            .iter()
+            .map(|v| v.to_json(mode))
+            .collect::<Result<_, _>>()
+    };
+    items.map_err(|err| anyhow!("{err}"))
 }
 
 #[derive(Debug, Clone, Args)]
@@ -102,6 +164,9 @@ pub struct GetCommand {
     pub annotation: bool,
 
     /// Path to the JSONA document, if omitted the standard input will be used.
+    ///
+    /// Files with the `.jsonal` extension are read as JSONA Lines, and the values
+    /// found in every record are printed as JSON Lines.
     #[clap(short, long)]
     pub file_path: Option<String>,
 
@@ -120,21 +185,56 @@ pub struct GetCommand {
     /// - table.array[*].foo
     /// - table.array.*.foo
     /// - dependencies.tokio-*.version
+    /// - table.array[-1].foo
+    /// - table.array[1:5].foo
+    /// - table.array[0,2].foo
+    /// - dependencies[/^tokio-/].version
+    /// - table.array[?(@.enabled == true)].foo
+    /// - **[?(@@mock)]
+    ///
+    /// A pattern starting with `/` is read as a JSON Pointer instead, which can
+    /// address keys containing dots or brackets, e.g. `/dependencies/serde.json`.
+    /// Segments starting with `@` address annotations, `~2` escapes a leading `@` of a key.
     ///
     pub pattern: Option<String>,
+
+    /// How NaN and infinities are printed.
+    #[clap(long, value_enum, default_value = "null")]
+    pub non_finite: NonFinite,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum NonFinite {
+    /// Print as `null`.
+    Null,
+    /// Print as `"NaN"`, `"Infinity"` or `"-Infinity"`.
+    String,
+    /// Fail the operation.
+    Error,
 }
 
-pub fn to_json(node: &Node) -> Value {
-    let annotations = node.annotations().map(|a| {
-        Value::Object(
+impl From<NonFinite> for NonFiniteMode {
+    fn from(v: NonFinite) -> Self {
+        match v {
+            NonFinite::Null => NonFiniteMode::Null,
+            NonFinite::String => NonFiniteMode::String,
+            NonFinite::Error => NonFiniteMode::Error,
+        }
+    }
+}
+
+pub fn to_json(node: &Node, mode: NonFiniteMode) -> Result<Value, ExportError> {
+    let annotations = match node.annotations() {
+        Some(a) => Some(Value::Object(
             a.value()
                 .read()
                 .iter()
-                .map(|(k, v)| (k.to_string(), v.to_plain_json()))
-                .collect(),
-        )
-    });
-    match node {
+                .map(|(k, v)| Ok((k.to_string(), v.to_json(mode)?)))
+                .collect::<Result<_, ExportError>>()?,
+        )),
+        None => None,
+    };
+    let value = match node {
         Node::Null(_) => match annotations {
             Some(annotations) => {
                 json!({
@@ -161,16 +261,16 @@ pub fn to_json(node: &Node) -> Value {
                 })
             }
         },
-        Node::Number(v) => match annotations {
+        Node::Number(_) => match annotations {
             Some(annotations) => {
                 json!({
-                    "value": v.value(),
+                    "value": node.to_json(mode)?,
                     "annotations": annotations
                 })
             }
             None => {
                 json!({
-                    "value": v.value(),
+                    "value": node.to_json(mode)?,
                 })
             }
         },
@@ -188,7 +288,13 @@ pub fn to_json(node: &Node) -> Value {
             }
         },
         Node::Array(v) => {
-            let value = Value::Array(v.value().read().iter().map(to_json).collect());
+            let value = Value::Array(
+                v.value()
+                    .read()
+                    .iter()
+                    .map(|v| to_json(v, mode))
+                    .collect::<Result<_, _>>()?,
+            );
             match annotations {
                 Some(annotations) => {
                     json!({
@@ -208,8 +314,8 @@ pub fn to_json(node: &Node) -> Value {
                 v.value()
                     .read()
                     .iter()
-                    .map(|(k, v)| (k.to_string(), to_json(v)))
-                    .collect(),
+                    .map(|(k, v)| Ok((k.to_string(), to_json(v, mode)?)))
+                    .collect::<Result<_, ExportError>>()?,
             );
             match annotations {
                 Some(annotations) => {
@@ -225,5 +331,6 @@ pub fn to_json(node: &Node) -> Value {
                 }
             }
         }
-    }
+    };
+    Ok(value)
 }
@@ -31,3 +31,8 @@ impl<E: Environment> App<E> {
         Ok((url, content.to_string()))
     }
 }
+
+/// Whether the file is read as JSONA Lines, one document per line.
+pub(crate) fn is_jsona_lines(path: &str) -> bool {
+    path.ends_with(".jsonal")
+}
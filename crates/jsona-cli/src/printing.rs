@@ -9,11 +9,15 @@ use codespan_reporting::{
 };
 use itertools::Itertools;
 use jsona::{
-    dom::{self, Node},
-    parser,
-    rowan::TextRange,
+    dom, parser,
+    rowan::{TextRange, TextSize},
+};
+use jsona_util::{
+    environment::Environment,
+    include::{Composed, IncludeErrorKind, Location},
+    schema::JSONASchemaValidationError,
+    util::url::to_file_path,
 };
-use jsona_util::{environment::Environment, schema::JSONASchemaValidationError};
 use std::ops::Range;
 use tokio::io::AsyncWriteExt;
 
@@ -22,6 +26,7 @@ impl<E: Environment> App<E> {
         &self,
         file: &SimpleFile<&str, &str>,
         errors: &[parser::Error],
+        offset: TextSize,
     ) -> Result<(), anyhow::Error> {
         let mut out_diag = Vec::<u8>::new();
 
@@ -30,9 +35,11 @@ impl<E: Environment> App<E> {
         for error in errors.iter().unique_by(|e| e.range) {
             let diag = Diagnostic::error()
                 .with_message("invalid JSONA")
-                .with_labels(Vec::from([
-                    Label::primary((), std_range(error.range)).with_message(&error.message)
-                ]));
+                .with_labels(Vec::from([Label::primary(
+                    (),
+                    std_range(error.range + offset),
+                )
+                .with_message(&error.message)]));
 
             if self.colors {
                 term::emit(&mut Ansi::new(&mut out_diag), &config, file, &diag)?;
@@ -53,6 +60,7 @@ impl<E: Environment> App<E> {
         &self,
         file: &SimpleFile<&str, &str>,
         errors: impl Iterator<Item = dom::DomError>,
+        offset: TextSize,
     ) -> Result<(), anyhow::Error> {
         let mut out_diag = Vec::<u8>::new();
 
@@ -63,9 +71,17 @@ impl<E: Environment> App<E> {
                 dom::DomError::ConflictingKeys { key, other_key } => Diagnostic::error()
                     .with_message(error.to_string())
                     .with_labels(Vec::from([
-                        Label::primary((), std_range(key.text_range().unwrap()))
+                        Label::primary((), std_range(key.text_range().unwrap() + offset))
+                            .with_message("duplicate key"),
+                        Label::secondary((), std_range(other_key.text_range().unwrap() + offset))
+                            .with_message("duplicate found here"),
+                    ])),
+                dom::DomError::DuplicateKeys { key, other_key } => Diagnostic::warning()
+                    .with_message(error.to_string())
+                    .with_labels(Vec::from([
+                        Label::primary((), std_range(key.text_range().unwrap() + offset))
                             .with_message("duplicate key"),
-                        Label::secondary((), std_range(other_key.text_range().unwrap()))
+                        Label::secondary((), std_range(other_key.text_range().unwrap() + offset))
                             .with_message("duplicate found here"),
                     ])),
                 dom::DomError::InvalidNode { syntax }
@@ -74,7 +90,7 @@ impl<E: Environment> App<E> {
                     .with_message(error.to_string())
                     .with_labels(Vec::from([Label::primary(
                         (),
-                        std_range(syntax.text_range()),
+                        std_range(syntax.text_range() + offset),
                     )
                     .with_message(error.to_string())])),
             };
@@ -91,32 +107,78 @@ impl<E: Environment> App<E> {
         Ok(())
     }
 
+    pub(crate) async fn print_include_errors(
+        &self,
+        file: &SimpleFile<&str, &str>,
+        composed: &Composed,
+        offset: TextSize,
+    ) -> Result<(), anyhow::Error> {
+        let config = codespan_reporting::term::Config::default();
+
+        let mut out_diag = Vec::<u8>::new();
+        for err in &composed.errors {
+            let message = match err.kind {
+                IncludeErrorKind::Alias(_) => "invalid alias",
+                _ => "invalid include",
+            };
+            let diag = Diagnostic::error()
+                .with_message(message)
+                .with_labels(Vec::from([Label::primary(
+                    (),
+                    std_range(err.location.range.unwrap_or_default()),
+                )
+                .with_message(err.kind.to_string())]));
+
+            self.emit_located(
+                &mut out_diag,
+                &config,
+                file,
+                composed,
+                &err.location,
+                offset,
+                diag,
+            )?;
+        }
+        let mut stderr = self.env.stderr();
+        stderr.write_all(&out_diag).await?;
+        stderr.flush().await?;
+
+        Ok(())
+    }
+
     pub(crate) async fn print_schema_errors(
         &self,
         file: &SimpleFile<&str, &str>,
-        node: &Node,
+        composed: &Composed,
         errors: &[JSONASchemaValidationError],
+        pointer: bool,
+        offset: TextSize,
     ) -> Result<(), anyhow::Error> {
         let config = codespan_reporting::term::Config::default();
 
         let mut out_diag = Vec::<u8>::new();
         for err in errors {
-            let text_range = node
-                .path(&err.keys)
-                .and_then(|v| v.text_range())
-                .or_else(|| err.keys.last_text_range())
-                .unwrap_or_default();
-            let diag = Diagnostic::error()
-                .with_message(&err.kind.to_string())
-                .with_labels(Vec::from([
-                    Label::primary((), std_range(text_range)).with_message(&err.kind.to_string())
-                ]));
+            let location = composed.locate(&err.keys);
+            let mut diag = Diagnostic::error()
+                .with_message(err.kind.to_string())
+                .with_labels(Vec::from([Label::primary(
+                    (),
+                    std_range(location.range.unwrap_or_default()),
+                )
+                .with_message(err.kind.to_string())]));
+            if pointer {
+                diag = diag.with_notes(Vec::from([format!("at `{}`", err.pointer_ext())]));
+            }
 
-            if self.colors {
-                term::emit(&mut Ansi::new(&mut out_diag), &config, file, &diag)?;
-            } else {
-                term::emit(&mut NoColor::new(&mut out_diag), &config, file, &diag)?;
-            };
+            self.emit_located(
+                &mut out_diag,
+                &config,
+                file,
+                composed,
+                &location,
+                offset,
+                diag,
+            )?;
         }
         let mut stderr = self.env.stderr();
         stderr.write_all(&out_diag).await?;
@@ -124,6 +186,49 @@ impl<E: Environment> App<E> {
 
         Ok(())
     }
+
+    /// Emit a diagnostic whose labels refer to the file of the location, ranges of the
+    /// root file are moved by the offset.
+    #[allow(clippy::too_many_arguments)]
+    fn emit_located(
+        &self,
+        out_diag: &mut Vec<u8>,
+        config: &term::Config,
+        file: &SimpleFile<&str, &str>,
+        composed: &Composed,
+        location: &Location,
+        offset: TextSize,
+        mut diag: Diagnostic<()>,
+    ) -> Result<(), anyhow::Error> {
+        let included = match composed.graph.file(&location.file) {
+            Some(included) if location.include_range.is_some() => included,
+            _ => {
+                for label in &mut diag.labels {
+                    label.range = label.range.start + usize::from(offset)
+                        ..label.range.end + usize::from(offset);
+                }
+                return self.emit(out_diag, config, file, &diag);
+            }
+        };
+        let name = to_file_path(&included.url).unwrap_or_else(|| included.url.to_string());
+        let included_file = SimpleFile::new(name.as_str(), included.source.as_str());
+        self.emit(out_diag, config, &included_file, &diag)
+    }
+
+    fn emit(
+        &self,
+        out_diag: &mut Vec<u8>,
+        config: &term::Config,
+        file: &SimpleFile<&str, &str>,
+        diag: &Diagnostic<()>,
+    ) -> Result<(), anyhow::Error> {
+        if self.colors {
+            term::emit(&mut Ansi::new(out_diag), config, file, diag)?;
+        } else {
+            term::emit(&mut NoColor::new(out_diag), config, file, diag)?;
+        }
+        Ok(())
+    }
 }
 
 fn std_range(range: TextRange) -> Range<usize> {
@@ -1,14 +1,20 @@
 use crate::{
+    util::LspExt,
     world::{DocumentState, WorkspaceState, World},
     NAME,
 };
-use jsona::dom::Node;
-use jsona_util::environment::Environment;
-use lsp_async_stub::{util::LspExt, Context, RequestWriter};
+use jsona::{dom::Node, parser::Parse, util::mapper::Mapper};
+use jsona_util::{
+    environment::Environment,
+    include::{self, Composed, Location as IncludeLocation},
+    HashMap,
+};
+use lsp_async_stub::{Context, RequestWriter};
 use lsp_types::{
     notification, Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, Location,
     PublishDiagnosticsParams, Url,
 };
+use std::rc::Rc;
 
 #[tracing::instrument(skip_all)]
 pub(crate) async fn publish_diagnostics<E: Environment>(
@@ -60,7 +66,7 @@ pub(crate) async fn publish_diagnostics<E: Environment>(
         None => return,
     };
 
-    let dom = doc.dom.clone();
+    let dom = doc.dom().clone();
 
     collect_dom_errors(doc, &dom, &document_uri, &mut diags);
     drop(workspaces);
@@ -91,7 +97,68 @@ pub(crate) async fn publish_diagnostics<E: Environment>(
         None => return,
     };
 
-    collect_schema_errors(ws, doc, &dom, &document_uri, &mut diags).await;
+    // Includes are composed without holding the lock, since included files are read from disk.
+    let cached = match ws.composed.get(&document_uri) {
+        Some(composed) => Ok(composed.clone()),
+        None => {
+            let source = doc.parse.clone().into_syntax().to_string();
+            // Included documents that are open are used with their unsaved changes.
+            let parses: HashMap<Url, Parse> = ws
+                .documents
+                .iter()
+                .map(|(url, doc)| (url.clone(), doc.parse.clone()))
+                .collect();
+            Err((ws.composed_revision, source, parses))
+        }
+    };
+    drop(workspaces);
+
+    let composed = match cached {
+        Ok(composed) => composed,
+        Err((revision, source, parses)) => {
+            let sources = |url: &Url| parses.get(url).map(|v| v.clone().into_syntax().to_string());
+            let composed = Rc::new(
+                include::compose_with_sources(&context.env, &document_uri, &source, &dom, &sources)
+                    .await,
+            );
+            let included: Vec<Url> = composed
+                .graph
+                .files()
+                .skip(1)
+                .map(|v| v.url.clone())
+                .collect();
+            let mut workspaces = context.workspaces.write().await;
+            if let Some(ws) = workspaces.get_mut(&ws_uri) {
+                if ws.documents.contains_key(&document_uri) {
+                    // Not cached if a file changed while composing, it may have been read before.
+                    if ws.composed_revision == revision {
+                        ws.composed.insert(document_uri.clone(), composed.clone());
+                    }
+                    ws.includes.insert(document_uri.clone(), included);
+                }
+            }
+            drop(workspaces);
+            composed
+        }
+    };
+
+    let workspaces = context.workspaces.read().await;
+    let ws = match workspaces.get(&ws_uri) {
+        Some(d) => d,
+        None => {
+            tracing::warn!(%document_uri, "workspace not found");
+            return;
+        }
+    };
+    let doc = match ws.documents.get(&document_uri) {
+        Some(doc) => doc,
+        None => return,
+    };
+
+    collect_include_errors(doc, &composed, &mut diags);
+    if diags.is_empty() {
+        collect_schema_errors(ws, doc, &composed, &document_uri, &mut diags).await;
+    }
     drop(workspaces);
 
     context
@@ -104,6 +171,30 @@ pub(crate) async fn publish_diagnostics<E: Environment>(
         .unwrap_or_else(|err| tracing::error!("{err}"));
 }
 
+/// Publish the diagnostics of the open documents that include the given document.
+#[tracing::instrument(skip_all)]
+pub(crate) async fn publish_dependents<E: Environment>(
+    context: Context<World<E>>,
+    ws_uri: Url,
+    document_uri: Url,
+) {
+    let workspaces = context.workspaces.read().await;
+    let dependents: Vec<Url> = match workspaces.get(&ws_uri) {
+        Some(ws) => ws
+            .includes
+            .iter()
+            .filter(|(url, included)| *url != &document_uri && included.contains(&document_uri))
+            .map(|(url, _)| url.clone())
+            .collect(),
+        None => return,
+    };
+    drop(workspaces);
+
+    for url in dependents {
+        publish_diagnostics(context.clone(), ws_uri.clone(), url).await;
+    }
+}
+
 #[tracing::instrument(skip_all)]
 pub(crate) async fn clear_diagnostics<E: Environment>(
     mut context: Context<World<E>>,
@@ -144,82 +235,92 @@ fn collect_dom_errors(
     document_uri: &Url,
     diags: &mut Vec<Diagnostic>,
 ) {
-    if let Err(errors) = dom.validate() {
-        for error in errors {
-            match &error {
-                jsona::dom::DomError::ConflictingKeys { key, other_key } => {
-                    let range = doc
-                        .mapper
-                        .range(key.text_range().unwrap())
-                        .unwrap()
-                        .into_lsp();
+    let errors = dom.validate().err().into_iter().flatten();
+    for error in errors.chain(dom.warnings()) {
+        match &error {
+            jsona::dom::DomError::ConflictingKeys { key, other_key }
+            | jsona::dom::DomError::DuplicateKeys { key, other_key } => {
+                let range = doc
+                    .mapper
+                    .range(key.text_range().unwrap())
+                    .unwrap()
+                    .into_lsp();
 
-                    let other_range = doc
-                        .mapper
-                        .range(other_key.text_range().unwrap())
-                        .unwrap()
-                        .into_lsp();
+                let other_range = doc
+                    .mapper
+                    .range(other_key.text_range().unwrap())
+                    .unwrap()
+                    .into_lsp();
 
-                    diags.push(Diagnostic {
-                        range,
-                        severity: Some(DiagnosticSeverity::WARNING),
-                        source: Some(NAME.into()),
-                        message: error.to_string(),
-                        related_information: Some(Vec::from([DiagnosticRelatedInformation {
-                            location: Location {
-                                uri: document_uri.clone(),
-                                range: other_range,
-                            },
-                            message: "other key defined here".into(),
-                        }])),
-                        ..Default::default()
-                    });
+                diags.push(Diagnostic {
+                    range,
+                    severity: Some(DiagnosticSeverity::WARNING),
+                    source: Some(NAME.into()),
+                    message: error.to_string(),
+                    related_information: Some(Vec::from([DiagnosticRelatedInformation {
+                        location: Location {
+                            uri: document_uri.clone(),
+                            range: other_range,
+                        },
+                        message: "other key defined here".into(),
+                    }])),
+                    ..Default::default()
+                });
 
-                    diags.push(Diagnostic {
-                        range: other_range,
-                        severity: Some(DiagnosticSeverity::WARNING),
-                        source: Some(NAME.into()),
-                        message: error.to_string(),
-                        related_information: Some(Vec::from([DiagnosticRelatedInformation {
-                            location: Location {
-                                uri: document_uri.clone(),
-                                range,
-                            },
-                            message: "other key defined here".into(),
-                        }])),
-                        ..Default::default()
-                    });
-                }
-                jsona::dom::DomError::InvalidNode { syntax } => {
-                    let range = doc
-                        .mapper
-                        .range(syntax.text_range())
-                        .unwrap_or_default()
-                        .into_lsp();
-                    diags.push(Diagnostic {
-                        range,
-                        severity: Some(DiagnosticSeverity::ERROR),
-                        code: None,
-                        code_description: None,
-                        source: Some(NAME.into()),
-                        message: error.to_string(),
-                        related_information: None,
-                        tags: None,
-                        data: None,
-                    });
-                }
-                jsona::dom::DomError::InvalidString { syntax: _ }
-                | jsona::dom::DomError::InvalidNumber { syntax: _ } => {}
+                diags.push(Diagnostic {
+                    range: other_range,
+                    severity: Some(DiagnosticSeverity::WARNING),
+                    source: Some(NAME.into()),
+                    message: error.to_string(),
+                    related_information: Some(Vec::from([DiagnosticRelatedInformation {
+                        location: Location {
+                            uri: document_uri.clone(),
+                            range,
+                        },
+                        message: "other key defined here".into(),
+                    }])),
+                    ..Default::default()
+                });
+            }
+            jsona::dom::DomError::InvalidNode { syntax } => {
+                let range = doc
+                    .mapper
+                    .range(syntax.text_range())
+                    .unwrap_or_default()
+                    .into_lsp();
+                diags.push(Diagnostic {
+                    range,
+                    severity: Some(DiagnosticSeverity::ERROR),
+                    code: None,
+                    code_description: None,
+                    source: Some(NAME.into()),
+                    message: error.to_string(),
+                    related_information: None,
+                    tags: None,
+                    data: None,
+                });
             }
+            jsona::dom::DomError::InvalidString { syntax: _ }
+            | jsona::dom::DomError::InvalidNumber { syntax: _ } => {}
         }
     }
 }
 
+#[tracing::instrument(skip_all)]
+fn collect_include_errors(doc: &DocumentState, composed: &Composed, diags: &mut Vec<Diagnostic>) {
+    diags.extend(
+        composed
+            .errors
+            .iter()
+            .map(|err| located_diagnostic(doc, composed, &err.location, err.kind.to_string())),
+    );
+}
+
 #[tracing::instrument(skip_all, fields(%document_uri))]
 async fn collect_schema_errors<E: Environment>(
     ws: &WorkspaceState<E>,
     doc: &DocumentState,
-    dom: &Node,
+    composed: &Composed,
     document_uri: &Url,
     diags: &mut Vec<Diagnostic>,
 ) {
@@ -235,25 +336,14 @@ async fn collect_schema_errors<E: Environment>(
             "using schema"
         );
 
-        match ws.schemas.validate(&schema_association.url, dom).await {
+        match ws
+            .schemas
+            .validate(&schema_association.url, &composed.node)
+            .await
+        {
             Ok(errors) => diags.extend(errors.into_iter().map(|err| {
-                let text_range = dom
-                    .path(&err.keys)
-                    .and_then(|v| v.text_range())
-                    .or_else(|| err.keys.last_text_range())
-                    .unwrap_or_default();
-                let range = doc.mapper.range(text_range).unwrap_or_default().into_lsp();
-                Diagnostic {
-                    range,
-                    severity: Some(DiagnosticSeverity::ERROR),
-                    code: None,
-                    code_description: None,
-                    source: Some(NAME.into()),
-                    message: err.kind.to_string(),
-                    related_information: None,
-                    tags: None,
-                    data: None,
-                }
+                let location = composed.locate(&err.keys);
+                located_diagnostic(doc, composed, &location, err.kind.to_string())
             })),
             Err(error) => {
                 tracing::error!(?error, "schema validation failed");
@@ -261,3 +351,43 @@ async fn collect_schema_errors<E: Environment>(
         }
     }
 }
+
+/// An error at a location of the composed document, errors in included files are shown
+/// at the include of the document and point to the included file.
+fn located_diagnostic(
+    doc: &DocumentState,
+    composed: &Composed,
+    location: &IncludeLocation,
+    message: String,
+) -> Diagnostic {
+    let (text_range, related_information) = match location.include_range {
+        None => (location.range, None),
+        Some(include_range) => {
+            let related = composed.graph.file(&location.file).and_then(|file| {
+                let mapper = Mapper::new(&file.source, doc.mapper.encoding(), false);
+                let range = mapper.range(location.range.unwrap_or_default())?;
+                Some(Vec::from([DiagnosticRelatedInformation {
+                    location: Location {
+                        uri: location.file.clone(),
+                        range: range.into_lsp(),
+                    },
+                    message: message.clone(),
+                }]))
+            });
+            (Some(include_range), related)
+        }
+    };
+    let range = doc
+        .mapper
+        .range(text_range.unwrap_or_default())
+        .unwrap_or_default()
+        .into_lsp();
+    Diagnostic {
+        range,
+        severity: Some(DiagnosticSeverity::ERROR),
+        source: Some(NAME.into()),
+        message,
+        related_information,
+        ..Default::default()
+    }
+}
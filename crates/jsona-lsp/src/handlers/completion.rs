@@ -3,18 +3,14 @@ use std::collections::HashSet;
 use indexmap::{IndexMap, IndexSet};
 use jsona::{
     dom::{visit_annotations, DomNode, Key, KeyOrIndex, Keys, Node, VisitControl, Visitor},
-    util::quote,
+    util::{mapper::Position, quote},
 };
 use jsona_schema::{Schema, SchemaType};
 use jsona_util::{
     environment::Environment,
     schema::associations::{SCHEMA_REF_KEY, SCHEMA_REF_SCHEMA},
 };
-use lsp_async_stub::{
-    rpc::Error,
-    util::{LspExt, Position},
-    Context, Params,
-};
+use lsp_async_stub::{rpc::Error, Context, Params};
 use lsp_types::{
     Command, CompletionItem, CompletionItemKind, CompletionParams, CompletionResponse,
     CompletionTextEdit, Documentation, InsertTextFormat, MarkupContent, TextEdit,
@@ -24,6 +20,7 @@ use serde_json::Value;
 use crate::World;
 use crate::{
     query::{Query, ScopeKind},
+    util::LspExt,
     world::DocumentState,
 };
 
@@ -49,12 +46,12 @@ pub async fn completion<E: Environment>(
         }
     };
 
-    let query = Query::at(&doc.dom, offset, true);
+    let query = Query::at(doc.dom(), offset, true);
     if query.scope == ScopeKind::Unknown {
         return Ok(None);
     }
 
-    let (keys, node) = match Query::node_at(&doc.dom, query.node_at_offset) {
+    let (keys, node) = match Query::node_at(doc.dom(), query.node_at_offset) {
         Some(v) => v,
         None => return Ok(None),
     };
@@ -171,7 +168,7 @@ fn complete_annotations_schemaless(
     exist_props: &[String],
 ) -> Option<CompletionResponse> {
     let mut comps = CompletionMap::default();
-    for (anno_keys, anno_value) in visit_annotations(&doc.dom) {
+    for (anno_keys, anno_value) in visit_annotations(doc.dom()) {
         if let Some(anno_key) = anno_keys.last_annotation_key() {
             let anno_key = anno_key.value().to_string();
             if &anno_key == "@" || exist_props.contains(&anno_key) {
@@ -192,11 +189,8 @@ fn complete_properties_schemaless(
     keys: &Keys,
 ) -> Option<CompletionResponse> {
     let mut comps = CompletionMap::default();
-    let parent_key = match keys.last_property_key() {
-        None => return None,
-        Some(v) => v,
-    };
-    let visitor = Visitor::new(&doc.dom, parent_key, |keys, node, parent_key| {
+    let parent_key = keys.last_property_key()?;
+    let visitor = Visitor::new(doc.dom(), parent_key, |keys, node, parent_key| {
         match keys.last() {
             Some(key) => match key.as_property_key() {
                 Some(key) => {
@@ -235,11 +229,8 @@ fn complete_value_schemaless(
     is_array: bool,
 ) -> Option<CompletionResponse> {
     let mut comps = CompletionMap::default();
-    let parent_key = match keys.last_property_key() {
-        None => return None,
-        Some(v) => v,
-    };
-    let visitor = Visitor::new(&doc.dom, parent_key, |keys, _, parent_key| {
+    let parent_key = keys.last_property_key()?;
+    let visitor = Visitor::new(doc.dom(), parent_key, |keys, _, parent_key| {
         match keys.last() {
             Some(key) => match key.as_property_key() {
                 Some(key) => {
@@ -0,0 +1,37 @@
+use crate::{util::LspExt, world::World};
+use jsona::{anchors, util::mapper::Position};
+use jsona_util::environment::Environment;
+use lsp_async_stub::{rpc::Error, Context, Params};
+use lsp_types::{GotoDefinitionParams, GotoDefinitionResponse, Location};
+
+#[tracing::instrument(skip_all)]
+pub(crate) async fn goto_definition<E: Environment>(
+    context: Context<World<E>>,
+    params: Params<GotoDefinitionParams>,
+) -> Result<Option<GotoDefinitionResponse>, Error> {
+    let p = params.required()?;
+
+    let workspaces = context.workspaces.read().await;
+    let document_uri = &p.text_document_position_params.text_document.uri;
+    let (_, doc) = workspaces.try_get_document(document_uri)?;
+
+    let position = p.text_document_position_params.position;
+    let offset = match doc.mapper.offset(Position::from_lsp(position)) {
+        Some(ofs) => ofs,
+        None => {
+            tracing::error!(?position, "document position not found");
+            return Ok(None);
+        }
+    };
+
+    let range = anchors::definition_at(doc.dom(), offset)
+        .and_then(|anchor| anchor.range)
+        .and_then(|range| doc.mapper.range(range));
+
+    Ok(range.map(|range| {
+        GotoDefinitionResponse::Scalar(Location {
+            uri: document_uri.clone(),
+            range: range.into_lsp(),
+        })
+    }))
+}
@@ -1,14 +1,14 @@
-use crate::world::{DocumentState, World};
+use crate::{
+    util::LspExt,
+    world::{DocumentState, World},
+};
 use jsona::{
     dom::{self, DomNode, Node},
     rowan::TextRange,
+    util::mapper::Mapper,
 };
 use jsona_util::environment::Environment;
-use lsp_async_stub::{
-    rpc::Error,
-    util::{LspExt, Mapper},
-    Context, Params,
-};
+use lsp_async_stub::{rpc::Error, Context, Params};
 use lsp_types::{DocumentSymbol, DocumentSymbolParams, DocumentSymbolResponse, SymbolKind};
 
 #[tracing::instrument(skip_all)]
@@ -32,7 +32,7 @@ pub(crate) fn create_symbols(doc: &DocumentState) -> Vec<DocumentSymbol> {
     let mapper = &doc.mapper;
     let mut symbols: Vec<DocumentSymbol> = Vec::new();
 
-    let dom = doc.dom.clone();
+    let dom = doc.dom().clone();
     symbols_for_annotations(&dom, mapper, &mut symbols);
     match dom {
         Node::Object(obj) => {
@@ -174,3 +174,43 @@ fn symbols_for_object(obj: &dom::Object, mapper: &Mapper) -> Vec<DocumentSymbol>
     }
     symbols
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::negotiate_position_encoding;
+    use jsona::parser;
+    use lsp_types::{ClientCapabilities, GeneralClientCapabilities, PositionEncodingKind};
+
+    fn symbol_start(encodings: Vec<PositionEncodingKind>, text: &str, name: &str) -> u32 {
+        let caps = ClientCapabilities {
+            general: Some(GeneralClientCapabilities {
+                position_encodings: Some(encodings),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mapper = Mapper::new(text, negotiate_position_encoding(&caps), false);
+        let doc = DocumentState::new(parser::parse(text), mapper);
+        let symbols = create_symbols(&doc);
+        let symbol = symbols.iter().find(|v| v.name == name).unwrap();
+        symbol.selection_range.start.character
+    }
+
+    #[test]
+    fn test_symbols_columns() {
+        let text = r#"{ "é😀": 1, b: 2 }"#;
+        assert_eq!(
+            symbol_start(vec![PositionEncodingKind::UTF8], text, "b"),
+            15
+        );
+        assert_eq!(
+            symbol_start(vec![PositionEncodingKind::UTF16], text, "b"),
+            12
+        );
+        assert_eq!(
+            symbol_start(vec![PositionEncodingKind::UTF32], text, "b"),
+            11
+        );
+    }
+}
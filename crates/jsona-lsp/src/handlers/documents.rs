@@ -1,5 +1,7 @@
+use jsona::parser::{self, TextEdit};
+use jsona::util::mapper::{Mapper, Range};
 use jsona_util::environment::Environment;
-use lsp_async_stub::{util::Mapper, Context, Params, RequestWriter};
+use lsp_async_stub::{Context, Params, RequestWriter};
 use lsp_types::{
     notification, Diagnostic, DiagnosticSeverity, DidChangeTextDocumentParams,
     DidCloseTextDocumentParams, DidOpenTextDocumentParams, DidSaveTextDocumentParams,
@@ -9,6 +11,7 @@ use url::Url;
 
 use crate::{
     diagnostics,
+    util::LspExt,
     world::{DocumentState, World},
     NAME,
 };
@@ -23,26 +26,7 @@ pub(crate) async fn document_open<E: Environment>(
         Some(p) => p,
     };
 
-    let parse = jsona::parser::parse(&p.text_document.text);
-    let mapper = Mapper::new_utf16(&p.text_document.text, false);
-
-    let mut workspaces = context.workspaces.write().await;
-    let document_uri = &p.text_document.uri;
-    let ws = workspaces.by_document_mut(document_uri);
-    let dom = parse.clone().into_dom();
-
-    if ws.lsp_config.schema.enabled {
-        ws.schemas
-            .associations()
-            .add_from_document(document_uri, &dom);
-    }
-
-    ws.documents
-        .insert(document_uri.clone(), DocumentState { parse, dom, mapper });
-
-    let ws_root = ws.root.clone();
-    drop(workspaces);
-    diagnostics::publish_diagnostics(context.clone(), ws_root, document_uri.clone()).await;
+    open_document(context, p.text_document.uri, &p.text_document.text).await;
 }
 
 #[tracing::instrument(skip_all)]
@@ -50,39 +34,79 @@ pub(crate) async fn document_change<E: Environment>(
     context: Context<World<E>>,
     params: Params<DidChangeTextDocumentParams>,
 ) {
-    let mut p = match params.optional() {
+    let p = match params.optional() {
         None => return,
         Some(p) => p,
     };
 
-    // We expect one full change
-    let change = match p.content_changes.pop() {
-        None => return,
-        Some(c) => c,
-    };
-
-    let parse = jsona::parser::parse(&change.text);
-    let mapper = Mapper::new_utf16(&change.text, false);
-
+    let encoding = **context.position_encoding.load();
     let mut workspaces = context.workspaces.write().await;
     let document_uri = &p.text_document.uri;
     let ws = workspaces.by_document_mut(document_uri);
-    let dom = parse.clone().into_dom();
+    let doc = match ws.documents.get_mut(document_uri) {
+        Some(doc) => doc,
+        None => {
+            tracing::debug!(%document_uri, "not found document in workspace");
+            return;
+        }
+    };
 
-    ws.documents
-        .insert(document_uri.clone(), DocumentState { parse, dom, mapper });
+    for change in p.content_changes {
+        match change.range {
+            Some(range) => {
+                if apply_change(doc, Range::from_lsp(range), &change.text).is_none() {
+                    // The document is synced again once it is saved with its full text.
+                    tracing::error!(?range, %document_uri, "document range not found, keeping the previous text");
+                    break;
+                }
+            }
+            None => {
+                *doc = DocumentState::new(
+                    parser::parse(&change.text),
+                    Mapper::new(&change.text, encoding, false),
+                );
+            }
+        }
+    }
 
+    ws.invalidate_composed(document_uri);
     let ws_root = ws.root.clone();
     drop(workspaces);
-    diagnostics::publish_diagnostics(context.clone(), ws_root, document_uri.clone()).await;
+    diagnostics::publish_diagnostics(context.clone(), ws_root.clone(), document_uri.clone()).await;
+    diagnostics::publish_dependents(context.clone(), ws_root, document_uri.clone()).await;
 }
 
+/// Documents that fell out of sync after a change that could not be applied
+/// are synced again from the saved text.
 #[tracing::instrument(skip_all)]
 pub(crate) async fn document_save<E: Environment>(
-    _context: Context<World<E>>,
-    _params: Params<DidSaveTextDocumentParams>,
+    context: Context<World<E>>,
+    params: Params<DidSaveTextDocumentParams>,
 ) {
-    // stub to silence warnings
+    let p = match params.optional() {
+        None => return,
+        Some(p) => p,
+    };
+
+    let text = match p.text {
+        Some(text) => text,
+        None => return,
+    };
+    let document_uri = p.text_document.uri;
+    let workspaces = context.workspaces.read().await;
+    if let Some(doc) = workspaces
+        .by_document(&document_uri)
+        .documents
+        .get(&document_uri)
+    {
+        if doc.parse.clone().into_syntax().to_string() == text {
+            return;
+        }
+    }
+    drop(workspaces);
+
+    tracing::debug!(%document_uri, "resync document from saved text");
+    open_document(context, document_uri, &text).await;
 }
 
 #[tracing::instrument(skip_all)]
@@ -100,12 +124,17 @@ pub(crate) async fn document_close<E: Environment>(
     let ws = workspaces.by_document_mut(document_uri);
 
     ws.documents.remove(document_uri);
+    ws.invalidate_composed(document_uri);
+    ws.includes.remove(document_uri);
+    let ws_root = ws.root.clone();
     drop(workspaces);
 
     context.env.spawn_local(diagnostics::clear_diagnostics(
         context.clone(),
         document_uri.clone(),
     ));
+    // Documents including it read it from disk again.
+    diagnostics::publish_dependents(context.clone(), ws_root, document_uri.clone()).await;
 }
 
 #[tracing::instrument(skip_all, fields(%file))]
@@ -129,3 +158,74 @@ async fn hint_excluded<E: Environment>(mut context: Context<World<E>>, file: &Ur
         .await
         .unwrap_or_else(|err| tracing::error!("{err}"));
 }
+
+async fn open_document<E: Environment>(context: Context<World<E>>, document_uri: Url, text: &str) {
+    let parse = parser::parse(text);
+    let encoding = **context.position_encoding.load();
+    let mapper = Mapper::new(text, encoding, false);
+
+    let mut workspaces = context.workspaces.write().await;
+    let ws = workspaces.by_document_mut(&document_uri);
+    let doc = DocumentState::new(parse, mapper);
+
+    if ws.lsp_config.schema.enabled {
+        ws.schemas
+            .associations()
+            .add_from_document(&document_uri, doc.dom());
+    }
+
+    ws.documents.insert(document_uri.clone(), doc);
+    ws.invalidate_composed(&document_uri);
+
+    let ws_root = ws.root.clone();
+    drop(workspaces);
+    diagnostics::publish_diagnostics(context.clone(), ws_root.clone(), document_uri.clone()).await;
+    diagnostics::publish_dependents(context.clone(), ws_root, document_uri).await;
+}
+
+/// Apply a ranged change, only the touched part of the tree is reparsed.
+///
+/// The document is left unchanged if the range is not in it.
+fn apply_change(doc: &mut DocumentState, range: Range, text: &str) -> Option<()> {
+    let range = doc.mapper.text_range(range)?;
+    doc.mapper.apply_edit(range, text)?;
+    let parse = parser::reparse(&doc.parse, &TextEdit::new(range, text));
+    doc.set_parse(parse);
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsona::util::mapper::{Position, PositionEncoding};
+
+    fn document(text: &str) -> DocumentState {
+        DocumentState::new(
+            parser::parse(text),
+            Mapper::new(text, PositionEncoding::Utf16, false),
+        )
+    }
+
+    fn text(doc: &DocumentState) -> String {
+        doc.parse.clone().into_syntax().to_string()
+    }
+
+    #[test]
+    fn test_apply_change() {
+        let range = |start: (u64, u64), end: (u64, u64)| Range {
+            start: Position::new(0, start.0, start.1),
+            end: Position::new(0, end.0, end.1),
+        };
+        let mut doc = document("{\n  a: 1\n}");
+        apply_change(&mut doc, range((1, 5), (1, 6)), "2").unwrap();
+        assert_eq!(text(&doc), "{\n  a: 2\n}");
+
+        // Columns past the end of a line are the end of the line.
+        apply_change(&mut doc, range((1, 6), (1, 99)), ", b: 3").unwrap();
+        assert_eq!(text(&doc), "{\n  a: 2, b: 3\n}");
+        assert_eq!(doc.mapper, document("{\n  a: 2, b: 3\n}").mapper);
+
+        assert!(apply_change(&mut doc, range((5, 0), (5, 1)), "x").is_none());
+        assert_eq!(text(&doc), "{\n  a: 2, b: 3\n}");
+    }
+}
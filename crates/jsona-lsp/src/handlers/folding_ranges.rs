@@ -3,15 +3,12 @@ use jsona::syntax::{
     SyntaxKind::{ANNOTATION_VALUE, ARRAY, BLOCK_COMMENT, LINE_COMMENT, OBJECT},
     SyntaxNode,
 };
+use jsona::util::mapper::Mapper;
 use jsona_util::environment::Environment;
-use lsp_async_stub::{
-    rpc::Error,
-    util::{LspExt, Mapper},
-    Context, Params,
-};
+use lsp_async_stub::{rpc::Error, Context, Params};
 use lsp_types::{FoldingRange, FoldingRangeKind, FoldingRangeParams, Range};
 
-use crate::world::World;
+use crate::{util::LspExt, world::World};
 
 #[tracing::instrument(skip_all)]
 pub(crate) async fn folding_ranges<E: Environment>(
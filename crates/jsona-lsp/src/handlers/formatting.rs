@@ -1,9 +1,9 @@
 use jsona::formatter;
 use jsona_util::environment::Environment;
-use lsp_async_stub::{rpc::Error, util::LspExt, Context, Params};
+use lsp_async_stub::{rpc::Error, Context, Params};
 use lsp_types::{DocumentFormattingParams, TextEdit};
 
-use crate::World;
+use crate::{util::LspExt, World};
 
 #[tracing::instrument(skip_all)]
 pub(crate) async fn format<E: Environment>(
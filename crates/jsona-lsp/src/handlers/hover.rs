@@ -1,15 +1,12 @@
 use crate::{
     query::{Query, ScopeKind},
+    util::LspExt,
     world::DocumentState,
 };
 use itertools::Itertools;
-use jsona::rowan::TextRange;
+use jsona::{rowan::TextRange, util::mapper::Position};
 use jsona_util::environment::Environment;
-use lsp_async_stub::{
-    rpc::Error,
-    util::{LspExt, Position},
-    Context, Params,
-};
+use lsp_async_stub::{rpc::Error, Context, Params};
 use lsp_types::{Hover, HoverContents, HoverParams, MarkupContent, MarkupKind};
 
 use crate::World;
@@ -37,12 +34,12 @@ pub(crate) async fn hover<E: Environment>(
         }
     };
 
-    let query = Query::at(&doc.dom, offset, false);
+    let query = Query::at(doc.dom(), offset, false);
     if query.scope == ScopeKind::Unknown || (query.key.is_none() && query.value.is_none()) {
         return Ok(None);
     }
 
-    let (keys, _) = match Query::node_at(&doc.dom, offset) {
+    let (keys, _) = match Query::node_at(doc.dom(), offset) {
         Some(v) => v,
         None => return Ok(None),
     };
@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use super::update_configuration;
 use crate::config::InitializationOptions;
+use crate::util::{negotiate_position_encoding, LspExt};
 use crate::world::{WorkspaceState, DEFAULT_WORKSPACE_URI};
 use crate::World;
 use jsona_util::environment::Environment;
@@ -10,9 +11,10 @@ use lsp_types::notification::{DidChangeConfiguration, Notification};
 use lsp_types::request::RegisterCapability;
 use lsp_types::{
     CompletionOptions, FoldingRangeProviderCapability, HoverProviderCapability, InitializedParams,
-    OneOf, Registration, RegistrationParams, SelectionRangeProviderCapability, ServerCapabilities,
-    ServerInfo, TextDocumentSyncCapability, TextDocumentSyncKind,
-    WorkspaceFoldersServerCapabilities, WorkspaceServerCapabilities,
+    OneOf, Registration, RegistrationParams, SaveOptions, SelectionRangeProviderCapability,
+    ServerCapabilities, ServerInfo, TextDocumentSyncCapability, TextDocumentSyncKind,
+    TextDocumentSyncOptions, TextDocumentSyncSaveOptions, WorkspaceFoldersServerCapabilities,
+    WorkspaceServerCapabilities,
 };
 use lsp_types::{InitializeParams, InitializeResult};
 
@@ -32,6 +34,9 @@ pub async fn initialize<E: Environment>(
         }
     }
 
+    let position_encoding = negotiate_position_encoding(&p.capabilities);
+    context.position_encoding.store(Arc::new(position_encoding));
+
     let mut wss = context.workspaces.write().await;
     if let Some(workspaces) = p.workspace_folders {
         for workspace in workspaces {
@@ -48,6 +53,7 @@ pub async fn initialize<E: Environment>(
 
     Ok(InitializeResult {
         capabilities: ServerCapabilities {
+            position_encoding: Some(position_encoding.into_lsp()),
             workspace: Some(WorkspaceServerCapabilities {
                 workspace_folders: Some(WorkspaceFoldersServerCapabilities {
                     supported: Some(true),
@@ -55,12 +61,23 @@ pub async fn initialize<E: Environment>(
                 }),
                 ..Default::default()
             }),
-            text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+            text_document_sync: Some(TextDocumentSyncCapability::Options(
+                TextDocumentSyncOptions {
+                    open_close: Some(true),
+                    change: Some(TextDocumentSyncKind::INCREMENTAL),
+                    // The saved text is used to resync documents that failed to update.
+                    save: Some(TextDocumentSyncSaveOptions::SaveOptions(SaveOptions {
+                        include_text: Some(true),
+                    })),
+                    ..Default::default()
+                },
+            )),
             folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
             selection_range_provider: Some(SelectionRangeProviderCapability::Simple(true)),
             document_symbol_provider: Some(OneOf::Left(true)),
             document_formatting_provider: Some(OneOf::Left(true)),
             hover_provider: Some(HoverProviderCapability::Simple(true)),
+            definition_provider: Some(OneOf::Left(true)),
             completion_provider: Some(CompletionOptions {
                 resolve_provider: Some(false),
                 trigger_characters: Some(vec![":".into(), "(".into(), "@".into()]),
@@ -19,6 +19,9 @@ pub(crate) use formatting::*;
 mod hover;
 pub(crate) use hover::*;
 
+mod definition;
+pub(crate) use definition::*;
+
 mod completion;
 pub(crate) use completion::*;
 
@@ -1,12 +1,9 @@
+use jsona::util::mapper::Position;
 use jsona_util::environment::Environment;
-use lsp_async_stub::{
-    rpc::Error,
-    util::{LspExt, Position},
-    Context, Params,
-};
+use lsp_async_stub::{rpc::Error, Context, Params};
 use lsp_types::{SelectionRange, SelectionRangeParams};
 
-use crate::{query::Query, world::World};
+use crate::{query::Query, util::LspExt, world::World};
 #[tracing::instrument(skip_all)]
 pub(crate) async fn selection_ranges<E: Environment>(
     context: Context<World<E>>,
@@ -29,7 +26,7 @@ pub(crate) async fn selection_ranges<E: Environment>(
                         return None;
                     }
                 };
-                let (_, node) = Query::node_at(&doc.dom, offset)?;
+                let (_, node) = Query::node_at(doc.dom(), offset)?;
                 let range = node.text_range().and_then(|v| doc.mapper.range(v))?;
                 Some(SelectionRange {
                     range: range.into_lsp(),
@@ -6,6 +6,7 @@ use world::{World, WorldState};
 
 mod diagnostics;
 mod handlers;
+mod util;
 
 pub mod config;
 pub mod lsp_ext;
@@ -23,6 +24,7 @@ pub fn create_server<E: Environment>() -> Server<World<E>> {
         .on_request::<request::DocumentSymbolRequest, _>(handlers::document_symbols)
         .on_request::<request::Completion, _>(handlers::completion)
         .on_request::<request::HoverRequest, _>(handlers::hover)
+        .on_request::<request::GotoDefinition, _>(handlers::goto_definition)
         .on_request::<request::Formatting, _>(handlers::format)
         .on_notification::<notification::Initialized, _>(handlers::initialized)
         .on_notification::<notification::DidOpenTextDocument, _>(handlers::document_open)
@@ -204,7 +204,7 @@ impl Query {
                     for event in parent.preorder_with_tokens() {
                         if let WalkEvent::Enter(ele) = event {
                             if let Some(t) = ele.as_token() {
-                                if t.text().contains(|p| p == '\r' || p == '\n') {
+                                if t.text().contains(['\r', '\n']) {
                                     exist_new_line = true;
                                 }
                                 if found && !t.kind().is_ws_or_comment() {
@@ -0,0 +1,107 @@
+//! Conversions between the positions of the language server protocol and [jsona::util::mapper].
+
+use jsona::util::mapper::{Position, PositionEncoding, Range};
+use lsp_types::{ClientCapabilities, PositionEncodingKind};
+
+pub(crate) trait LspExt<T>: Sized {
+    fn into_lsp(self) -> T;
+    fn from_lsp(val: T) -> Self;
+}
+
+impl LspExt<lsp_types::Position> for Position {
+    fn into_lsp(self) -> lsp_types::Position {
+        lsp_types::Position {
+            line: self.line as u32,
+            character: self.column as u32,
+        }
+    }
+
+    /// The index is not known from the protocol and left as zero.
+    fn from_lsp(val: lsp_types::Position) -> Self {
+        Position::new(0, val.line as u64, val.character as u64)
+    }
+}
+
+impl LspExt<lsp_types::Range> for Range {
+    fn into_lsp(self) -> lsp_types::Range {
+        lsp_types::Range {
+            start: self.start.into_lsp(),
+            end: self.end.into_lsp(),
+        }
+    }
+
+    fn from_lsp(val: lsp_types::Range) -> Self {
+        Range {
+            start: Position::from_lsp(val.start),
+            end: Position::from_lsp(val.end),
+        }
+    }
+}
+
+impl LspExt<PositionEncodingKind> for PositionEncoding {
+    fn into_lsp(self) -> PositionEncodingKind {
+        match self {
+            PositionEncoding::Utf8 => PositionEncodingKind::UTF8,
+            PositionEncoding::Utf16 => PositionEncodingKind::UTF16,
+            PositionEncoding::Utf32 => PositionEncodingKind::UTF32,
+        }
+    }
+
+    /// Unknown encodings fall back to UTF-16, which every client supports.
+    fn from_lsp(val: PositionEncodingKind) -> Self {
+        val.as_str().parse().unwrap_or_default()
+    }
+}
+
+/// Picks the position encoding from the ones offered by the client.
+///
+/// UTF-8 is preferred since it needs no conversion, then UTF-32,
+/// and UTF-16 is used if the client offers neither.
+pub(crate) fn negotiate_position_encoding(capabilities: &ClientCapabilities) -> PositionEncoding {
+    let offered: Vec<PositionEncoding> = capabilities
+        .general
+        .as_ref()
+        .and_then(|v| v.position_encodings.as_ref())
+        .map(|v| v.iter().cloned().map(PositionEncoding::from_lsp).collect())
+        .unwrap_or_default();
+    [PositionEncoding::Utf8, PositionEncoding::Utf32]
+        .into_iter()
+        .find(|v| offered.contains(v))
+        .unwrap_or(PositionEncoding::Utf16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_types::GeneralClientCapabilities;
+
+    fn capabilities(encodings: Option<Vec<PositionEncodingKind>>) -> ClientCapabilities {
+        ClientCapabilities {
+            general: Some(GeneralClientCapabilities {
+                position_encodings: encodings,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_negotiate_position_encoding() {
+        let caps = capabilities(Some(vec![
+            PositionEncodingKind::UTF16,
+            PositionEncodingKind::UTF32,
+            PositionEncodingKind::UTF8,
+        ]));
+        assert_eq!(negotiate_position_encoding(&caps), PositionEncoding::Utf8);
+
+        let caps = capabilities(Some(vec![PositionEncodingKind::UTF32]));
+        assert_eq!(negotiate_position_encoding(&caps), PositionEncoding::Utf32);
+
+        let caps = capabilities(None);
+        assert_eq!(negotiate_position_encoding(&caps), PositionEncoding::Utf16);
+        assert_eq!(
+            negotiate_position_encoding(&ClientCapabilities::default()),
+            PositionEncoding::Utf16
+        );
+    }
+}
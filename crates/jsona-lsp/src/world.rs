@@ -3,6 +3,7 @@ use crate::{
     lsp_ext::notification::{InitializeWorkspace, InitializeWorkspaceParams},
 };
 use arc_swap::ArcSwap;
+use jsona::util::mapper::{Mapper, PositionEncoding};
 use jsona::{
     dom::{Keys, Node},
     parser::Parse,
@@ -10,17 +11,18 @@ use jsona::{
 use jsona_schema::Schema;
 use jsona_util::{
     environment::Environment,
+    include::Composed,
     schema::{
         associations::{priority, source, AssociationRule, SchemaAssociation},
         Schemas,
     },
     AsyncRwLock, HashMap, IndexMap,
 };
-use lsp_async_stub::{rpc, util::Mapper, Context, RequestWriter};
+use lsp_async_stub::{rpc, Context, RequestWriter};
 use lsp_types::Url;
-use once_cell::sync::Lazy;
+use once_cell::sync::{Lazy, OnceCell};
 use serde_json::{json, Value};
-use std::sync::Arc;
+use std::{rc::Rc, sync::Arc};
 pub type World<E> = Arc<WorldState<E>>;
 
 #[repr(transparent)]
@@ -75,6 +77,8 @@ pub struct WorldState<E: Environment> {
     pub(crate) id: String,
     pub(crate) workspaces: AsyncRwLock<Workspaces<E>>,
     pub(crate) initialization_options: ArcSwap<InitializationOptions>,
+    /// The position encoding negotiated with the client.
+    pub(crate) position_encoding: ArcSwap<PositionEncoding>,
 }
 
 pub static DEFAULT_WORKSPACE_URI: Lazy<Url> = Lazy::new(|| Url::parse("root:///").unwrap());
@@ -89,6 +93,7 @@ impl<E: Environment> WorldState<E> {
             id,
             workspaces: AsyncRwLock::new(Workspaces(IndexMap::default())),
             initialization_options: Default::default(),
+            position_encoding: Default::default(),
             env,
         }
     }
@@ -97,6 +102,13 @@ impl<E: Environment> WorldState<E> {
 pub struct WorkspaceState<E: Environment> {
     pub(crate) root: Url,
     pub(crate) documents: HashMap<lsp_types::Url, DocumentState>,
+    /// Files included by each document, to update its diagnostics when they change.
+    pub(crate) includes: HashMap<lsp_types::Url, Vec<lsp_types::Url>>,
+    /// Includes composed into each document, until the document or a file it includes changes.
+    pub(crate) composed: HashMap<lsp_types::Url, Rc<Composed>>,
+    /// Bumped whenever composed includes are dropped,
+    /// so that includes composed from older files are not cached.
+    pub(crate) composed_revision: u64,
     pub(crate) schemas: Schemas<E>,
     pub(crate) lsp_config: LspConfig,
 }
@@ -106,6 +118,9 @@ impl<E: Environment> WorkspaceState<E> {
         Self {
             root,
             documents: Default::default(),
+            includes: Default::default(),
+            composed: Default::default(),
+            composed_revision: 0,
             schemas: Schemas::new(env),
             lsp_config: LspConfig::default(),
         }
@@ -113,6 +128,17 @@ impl<E: Environment> WorkspaceState<E> {
 }
 
 impl<E: Environment> WorkspaceState<E> {
+    /// Drop the composed includes of the document and of the documents including it.
+    pub(crate) fn invalidate_composed(&mut self, document_uri: &Url) {
+        self.composed_revision += 1;
+        self.composed.remove(document_uri);
+        for (url, included) in &self.includes {
+            if included.contains(document_uri) {
+                self.composed.remove(url);
+            }
+        }
+    }
+
     pub(crate) fn try_get_document(
         &self,
         document_uri: &Url,
@@ -220,7 +246,7 @@ impl<E: Environment> WorkspaceState<E> {
             if association.is_none() {
                 self.schemas
                     .associations()
-                    .add_from_document(document_uri, &doc.dom);
+                    .add_from_document(document_uri, doc.dom());
             }
         }
     }
@@ -229,6 +255,27 @@ impl<E: Environment> WorkspaceState<E> {
 #[derive(Debug, Clone)]
 pub struct DocumentState {
     pub(crate) parse: Parse,
-    pub(crate) dom: Node,
     pub(crate) mapper: Mapper,
+    /// Built from the parse on first use.
+    dom: OnceCell<Node>,
+}
+
+impl DocumentState {
+    pub(crate) fn new(parse: Parse, mapper: Mapper) -> Self {
+        Self {
+            parse,
+            mapper,
+            dom: OnceCell::new(),
+        }
+    }
+
+    /// Replace the parse, the mapper must already be updated for the same text.
+    pub(crate) fn set_parse(&mut self, parse: Parse) {
+        self.parse = parse;
+        self.dom = OnceCell::new();
+    }
+
+    pub(crate) fn dom(&self) -> &Node {
+        self.dom.get_or_init(|| self.parse.clone().into_dom())
+    }
 }
@@ -11,6 +11,9 @@ pub use jsona_schema::Schema;
 
 pub const VALUE_KEY: &str = "_";
 
+/// Local name of a declaration applying to every annotation in a namespace, e.g. `"openapi.*"`.
+pub const NAMESPACE_WILDCARD: &str = "*";
+
 #[derive(Debug)]
 pub struct JSONASchemaValidator {
     schema: Schema,
@@ -32,7 +35,10 @@ impl JSONASchemaValidator {
         }
         for (keys, value) in visit_annotations(node).into_iter() {
             if let Some(key) = keys.last_annotation_key() {
-                if let Some(schema) = self.get_entry_schema(key.value()) {
+                if let Some(schema) = self
+                    .annotation_declaration(key)
+                    .and_then(|name| self.get_entry_schema(&name))
+                {
                     collect_errors.extend(validates::validate(defs, schema, &keys, &value));
                 }
             }
@@ -44,15 +50,12 @@ impl JSONASchemaValidator {
         let (annotation_key, keys) = keys.shift_annotation();
         let new_keys = match annotation_key {
             Some(key) => {
-                if !self.contains_annotation_key(key.value()) {
-                    return vec![&self.annotations];
-                }
+                let name = match self.annotation_declaration(&key) {
+                    Some(v) => v,
+                    None => return vec![&self.annotations],
+                };
                 let new_keys = Keys::new(
-                    [
-                        KeyOrIndex::property(key.annotation_name().unwrap()),
-                        KeyOrIndex::property("value"),
-                    ]
-                    .into_iter(),
+                    [KeyOrIndex::property(name), KeyOrIndex::property("value")].into_iter(),
                 );
                 new_keys.extend(keys)
             }
@@ -77,6 +80,29 @@ impl JSONASchemaValidator {
         properties.get("value")
     }
 
+    /// Find the name of the declaration for an annotation key.
+    ///
+    /// An annotation is declared by its full name, or otherwise by a wildcard
+    /// declaration of its namespace or of any enclosing namespace,
+    /// e.g. `"openapi.*"` applies to both `@openapi.deprecated` and `@openapi.v3.deprecated`.
+    pub fn annotation_declaration(&self, key: &Key) -> Option<String> {
+        let name = key.annotation_name()?;
+        let properties = self.schema.properties.as_ref()?;
+        let full_name = name.to_string();
+        if properties.contains_key(&full_name) {
+            return Some(full_name);
+        }
+        let mut namespace = name.namespace;
+        while let Some(value) = namespace {
+            let wildcard = format!("{value}.{NAMESPACE_WILDCARD}");
+            if properties.contains_key(&wildcard) {
+                return Some(wildcard);
+            }
+            namespace = value.rsplit_once('.').map(|(v, _)| v.to_string());
+        }
+        None
+    }
+
     pub fn contains_annotation_key(&self, key: &str) -> bool {
         match self.annotations.properties.as_ref() {
             Some(properties) => properties.contains_key(key),
@@ -101,7 +127,7 @@ impl TryFrom<&Node> for JSONASchemaValidator {
         let mut errors = vec![];
         for (key, value) in object.value().read().iter() {
             let keys = Keys::single(key.clone());
-            if key.is_quote() {
+            if !is_declaration_name(key) {
                 errors.push(SchemaError::InvalidSchemaValue {
                     keys,
                     error: "invalid name".into(),
@@ -122,7 +148,7 @@ impl TryFrom<&Node> for JSONASchemaValidator {
             let keys = keys.join(value_key.clone());
             match object.get(&value_key) {
                 Some(value) => {
-                    if key.value() != VALUE_KEY {
+                    if key.value() != VALUE_KEY && !is_namespace_wildcard(key.value()) {
                         let schema = Schema {
                             description: parse_string_annotation(&keys, &value, "@describe")
                                 .ok()
@@ -162,6 +188,38 @@ impl TryFrom<&Node> for JSONASchemaValidator {
     }
 }
 
+/// Whether the key is a valid annotation name, optionally namespaced,
+/// or a wildcard of a namespace.
+///
+/// Names are only quoted for their `.`, e.g. `"openapi.deprecated"`.
+fn is_declaration_name(key: &Key) -> bool {
+    let name = key.value();
+    let quoted = key.to_origin_string().starts_with(['"', '\'', '`']);
+    if quoted && !name.contains('.') {
+        return false;
+    }
+    let mut segments = name.split('.').peekable();
+    let mut first = true;
+    while let Some(segment) = segments.next() {
+        let wildcard = !first && segments.peek().is_none() && segment == NAMESPACE_WILDCARD;
+        if !wildcard
+            && (segment.is_empty()
+                || !segment
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_'))
+        {
+            return false;
+        }
+        first = false;
+    }
+    true
+}
+
+fn is_namespace_wildcard(name: &str) -> bool {
+    name.strip_suffix(NAMESPACE_WILDCARD)
+        .is_some_and(|v| v.ends_with('.'))
+}
+
 fn parse_string_annotation(keys: &Keys, node: &Node, name: &str) -> SchemaResult<Option<String>> {
     match node.get_as_string(name) {
         Some((_, Some(value))) => Ok(Some(value.value().to_string())),
@@ -171,3 +229,145 @@ fn parse_string_annotation(keys: &Keys, node: &Node, name: &str) -> SchemaResult
         None => Ok(None),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn declaration_errors(name: &str) -> Vec<String> {
+        let schema = Node::from_str(&format!("{{ {name}: {{ value: 1 }} }}")).unwrap();
+        match JSONASchemaValidator::try_from(&schema) {
+            Ok(_) => vec![],
+            Err(errors) => errors.iter().map(|v| v.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_declaration_name() {
+        for name in ["foo", "foo_1", r#""openapi.deprecated""#, r#""x.*""#] {
+            assert!(declaration_errors(name).is_empty(), "{name}");
+        }
+        for name in [
+            r#""foo""#,
+            "'foo'",
+            r#""foo-bar""#,
+            r#""foo bar""#,
+            r#""foo.""#,
+            r#"".foo""#,
+            r#""*.foo""#,
+        ] {
+            assert_eq!(
+                declaration_errors(name),
+                ["invalid schema value, invalid name"],
+                "{name}"
+            );
+        }
+    }
+    #[test]
+    fn test_error_pointer() {
+        let schema = Node::from_str(
+            r#"{ _: { value: { a: [{ "@b": 1 }] } }, "openapi.deprecated": { value: true } }"#,
+        )
+        .unwrap();
+        let validator = JSONASchemaValidator::try_from(&schema).unwrap();
+        let node = Node::from_str(r#"{ a: [{ "@b": "1" @openapi.deprecated(1) }] }"#).unwrap();
+        let pointers: Vec<_> = validator
+            .validate(&node)
+            .iter()
+            .map(|v| (v.pointer(), v.pointer_ext()))
+            .collect();
+        assert_eq!(
+            pointers,
+            [
+                ("/a/0/@b".to_string(), "/a/0/~2b".to_string()),
+                (
+                    "/a/0/@b/@openapi.deprecated".to_string(),
+                    "/a/0/~2b/@openapi.deprecated".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_non_finite_numbers() {
+        let schema = Node::from_str(
+            r#"{
+      _: {
+        value: {
+          min: 0.0, @schema({minimum: 0})
+          max: 0.0, @schema({maximum: 10})
+          step: 0.5, @schema({multipleOf: 0.5})
+        }
+      }
+    }"#,
+        )
+        .unwrap();
+        let validator = JSONASchemaValidator::try_from(&schema).unwrap();
+        let validate = |source: &str| {
+            let node = Node::from_str(source).unwrap();
+            validator
+                .validate(&node)
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(
+            validate("{ min: inf, max: -inf, step: 1.5 }"),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            validate("{ min: -inf, max: inf, step: inf }"),
+            [
+                ".min: Minimum condition is not met",
+                ".max: Maximum condition is not met",
+                ".step: MultipleOf condition is not met"
+            ]
+        );
+        assert_eq!(validate("{ min: nan, max: nan, step: nan }").len(), 3);
+    }
+
+    #[test]
+    fn test_namespaced_annotations() {
+        let schema = Node::from_str(
+            r#"{
+      "openapi.deprecated": {
+        value: true,
+      },
+      "x.*": {
+        value: "",
+      },
+    }"#,
+        )
+        .unwrap();
+        let validator = JSONASchemaValidator::try_from(&schema).unwrap();
+        let validate = |source: &str| {
+            let node = Node::from_str(source).unwrap();
+            validator
+                .validate(&node)
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(
+            validate(r#"{ a: 1, @openapi.deprecated(true) @x.mock("a") @x.y.z("b") @mock(1) }"#),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            validate(r#"{ a: 1, @openapi.deprecated(1) @x.y.z(2) }"#),
+            [
+                r#".a@"openapi.deprecated": The value must be any of: boolean"#,
+                r#".a@"x.y.z": The value must be any of: string"#
+            ]
+        );
+        let schemas = validator.pointer(&r#"a@"x.mock""#.parse().unwrap());
+        assert_eq!(schemas.len(), 1);
+        assert_eq!(
+            serde_json::to_value(schemas[0]).unwrap(),
+            serde_json::json!({"type": "string"})
+        );
+
+        let schema = Node::from_str(r#"{ "openapi.": { value: 1 } }"#).unwrap();
+        assert!(JSONASchemaValidator::try_from(&schema).is_err());
+    }
+}
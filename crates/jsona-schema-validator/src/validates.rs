@@ -468,42 +468,37 @@ fn validate_maxmin(
     keys: &Keys,
     node: &Node,
 ) {
+    // NaN is neither below a minimum nor above a maximum, so it is out of range for both.
     let value = match node.as_number() {
-        Some(v) => v.value(),
+        Some(v) => v.as_f64(),
         None => return,
     };
-    if let Some(maximum) = local_schema.maximum.as_ref() {
-        let mut valid = true;
-        if local_schema
+    if let Some(maximum) = local_schema.maximum.as_ref().and_then(|v| v.as_f64()) {
+        let valid = if local_schema
             .exclusive_maximum
             .as_ref()
             .copied()
             .unwrap_or_default()
         {
-            if value.as_f64() >= maximum.as_f64() {
-                valid = false;
-            }
-        } else if value.as_f64() > maximum.as_f64() {
-            valid = false;
-        }
+            value < maximum
+        } else {
+            value <= maximum
+        };
         if !valid {
             errors.push(Error::new(keys, ErrorKind::Maximum))
         }
     }
-    if let Some(minimum) = local_schema.minimum.as_ref() {
-        let mut valid = true;
-        if local_schema
+    if let Some(minimum) = local_schema.minimum.as_ref().and_then(|v| v.as_f64()) {
+        let valid = if local_schema
             .exclusive_minimum
             .as_ref()
             .copied()
             .unwrap_or_default()
         {
-            if value.as_f64() <= minimum.as_f64() {
-                valid = false;
-            }
-        } else if value.as_f64() < minimum.as_f64() {
-            valid = false;
-        }
+            value > minimum
+        } else {
+            value >= minimum
+        };
         if !valid {
             errors.push(Error::new(keys, ErrorKind::Minimum))
         }
@@ -518,11 +513,13 @@ fn validate_multiple_of(
     node: &Node,
 ) {
     if let Some(multiple_of) = local_schema.multiple_of.as_ref() {
-        let value = match node.as_number().and_then(|v| v.value().as_f64()) {
-            Some(v) => v,
+        let value = match node.as_number() {
+            Some(v) => v.as_f64(),
             None => return,
         };
-        let valid = if (value.fract() == 0f64) && (multiple_of.fract() == 0f64) {
+        let valid = if !value.is_finite() {
+            false
+        } else if (value.fract() == 0f64) && (multiple_of.fract() == 0f64) {
             (value % multiple_of) == 0f64
         } else {
             let remainder: f64 = (value / multiple_of) % 1f64;
@@ -681,6 +678,15 @@ impl Error {
             kind,
         }
     }
+    /// The JSON Pointer of the invalid value, see [Keys::to_pointer].
+    pub fn pointer(&self) -> String {
+        self.keys.to_pointer()
+    }
+    /// The JSON Pointer of the invalid value with annotations addressed by `@` segments,
+    /// see [Keys::to_pointer_ext].
+    pub fn pointer_ext(&self) -> String {
+        self.keys.to_pointer_ext()
+    }
     pub fn to_error_object(&self, node: &Node, mapper: &Mapper) -> ErrorObject {
         let message = self.to_string();
         ErrorObject::new(
@@ -822,18 +828,12 @@ impl Display for ErrorKind {
 
 fn resolve<'a>(defs: &'a IndexMap<String, Schema>, local_schema: &'a Schema) -> Option<&'a Schema> {
     let schema = match local_schema.ref_value.as_ref() {
-        Some(ref_value) => {
-            match REF_REGEX
-                .captures(ref_value)
-                .ok()
-                .flatten()
-                .and_then(|v| v.get(1))
-                .and_then(|v| defs.get(v.as_str()))
-            {
-                Some(v) => v,
-                None => return None,
-            }
-        }
+        Some(ref_value) => REF_REGEX
+            .captures(ref_value)
+            .ok()
+            .flatten()
+            .and_then(|v| v.get(1))
+            .and_then(|v| defs.get(v.as_str()))?,
         None => local_schema,
     };
     Some(schema)
@@ -435,7 +435,7 @@ impl SchemaParser {
             Node::Array(arr) => {
                 let mut errors = vec![];
                 let arr = arr.value().read();
-                if arr.len() > 0 {
+                if !arr.is_empty() {
                     let mut schemas = vec![];
                     for (i, child) in arr.iter().enumerate() {
                         let child_parser = self.spawn(i, child.clone());
@@ -603,17 +603,14 @@ fn resolve<'a>(root_schema: &'a Schema, local_schema: &'a Schema) -> Option<&'a
             if ref_value == "#" {
                 root_schema
             } else {
-                match root_schema.defs.as_ref().and_then(|defs| {
+                root_schema.defs.as_ref().and_then(|defs| {
                     REF_REGEX
                         .captures(ref_value)
                         .ok()
                         .flatten()
                         .and_then(|v| v.get(1))
                         .and_then(|v| defs.get(v.as_str()))
-                }) {
-                    Some(v) => v,
-                    None => return None,
-                }
+                })?
             }
         }
         None => local_schema,
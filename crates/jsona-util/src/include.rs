@@ -0,0 +1,535 @@
+//! Composing a document from several files with `@include`.
+//!
+//! A value annotated with `@include("./common/auth.jsona")` is replaced by the document
+//! of the given file. Relative paths are resolved against the URL of the including file,
+//! only `file:` URLs can be included. Files are read through the [Environment], or taken
+//! from the given sources with [compose_with_sources], so they can include other files in turn.
+//!
+//! Aliases of every file are replaced by the values of its [anchors] before its includes
+//! are resolved, anchors are not shared between files.
+//!
+//! Every file keeps its own syntax tree, so positions of the composed document are
+//! located in the file they come from with [IncludeGraph::locate].
+
+use futures::future::LocalBoxFuture;
+use jsona::{
+    anchors,
+    dom::{visit_annotations, DomError, DomNode, Keys, Node},
+    parser,
+    processor::{Context, Pipeline},
+    rowan::TextRange,
+};
+use std::fmt;
+use thiserror::Error;
+use url::Url;
+
+use crate::{environment::Environment, util::url::is_url, HashMap, IndexMap};
+
+pub const INCLUDE_KEY: &str = "@include";
+
+/// A file of a composed document.
+#[derive(Debug, Clone)]
+pub struct IncludedFile {
+    pub url: Url,
+    pub source: String,
+    /// The parsed document, before its aliases and includes are resolved.
+    pub node: Node,
+    pub includes: Vec<Include>,
+}
+
+/// An `@include` annotation of a file.
+#[derive(Debug, Clone)]
+pub struct Include {
+    /// Path of the annotated value in the including file.
+    pub keys: Keys,
+    /// Range of the annotation in the including file.
+    pub range: Option<TextRange>,
+    pub url: Url,
+    /// Whether the value was replaced by the included document.
+    pub resolved: bool,
+}
+
+/// The files of a composed document and the includes between them.
+#[derive(Debug, Clone)]
+pub struct IncludeGraph {
+    /// All files, the root file first.
+    files: IndexMap<Url, IncludedFile>,
+}
+
+impl IncludeGraph {
+    pub fn root(&self) -> &IncludedFile {
+        &self.files[0]
+    }
+
+    pub fn file(&self, url: &Url) -> Option<&IncludedFile> {
+        self.files.get(url)
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = &IncludedFile> {
+        self.files.values()
+    }
+
+    /// Pairs of including and included files.
+    pub fn edges(&self) -> impl Iterator<Item = (&Url, &Url)> {
+        self.files
+            .values()
+            .flat_map(|file| file.includes.iter().map(move |v| (&file.url, &v.url)))
+    }
+
+    /// Find the file and range of the value at the keys of the composed document.
+    pub fn locate(&self, keys: &Keys) -> Location {
+        let mut file = self.root();
+        let mut keys = keys.clone();
+        let mut include_range = None;
+        while let Some(include) = file
+            .includes
+            .iter()
+            .filter(|v| v.resolved && starts_with(&keys, &v.keys))
+            .max_by_key(|v| v.keys.len())
+        {
+            let included = match self.files.get(&include.url) {
+                Some(v) => v,
+                None => break,
+            };
+            include_range = include_range.or(include.range);
+            keys = Keys::new(keys.iter().skip(include.keys.len()).cloned());
+            file = included;
+        }
+        let range = anchors::locate(&file.node, &keys)
+            .text_range()
+            .or_else(|| keys.last_text_range());
+        Location {
+            file: file.url.clone(),
+            range,
+            include_range,
+        }
+    }
+}
+
+/// A range in one of the files of a composed document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub file: Url,
+    pub range: Option<TextRange>,
+    /// Range of the `@include` of the root file the location is reached through,
+    /// `None` for locations in the root file.
+    pub include_range: Option<TextRange>,
+}
+
+#[derive(Debug, Clone, Error)]
+pub enum IncludeErrorKind {
+    #[error("the value of @include must be a relative path or file URL")]
+    InvalidPath,
+    #[error("failed to read {url}, {error}")]
+    Read { url: Url, error: String },
+    #[error("{0}")]
+    Syntax(String),
+    #[error("{0}")]
+    Semantic(String),
+    #[error("{0}")]
+    Alias(String),
+    #[error("cyclic include {}", display_cycle(.0))]
+    Cycle(Vec<Url>),
+}
+
+#[derive(Debug, Clone)]
+pub struct IncludeError {
+    pub location: Location,
+    pub kind: IncludeErrorKind,
+}
+
+impl fmt::Display for IncludeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.kind.fmt(f)
+    }
+}
+
+/// A document with its includes resolved.
+#[derive(Debug, Clone)]
+pub struct Composed {
+    pub node: Node,
+    pub graph: IncludeGraph,
+    pub errors: Vec<IncludeError>,
+}
+
+impl Composed {
+    /// See [IncludeGraph::locate].
+    pub fn locate(&self, keys: &Keys) -> Location {
+        self.graph.locate(keys)
+    }
+}
+
+/// Resolve the includes of a document read from the given URL.
+///
+/// Includes that cannot be resolved, because the file cannot be read, is not valid or
+/// would include itself, are reported as errors and the annotated value is kept without the annotation.
+pub async fn compose<E: Environment>(env: &E, url: &Url, source: &str, node: &Node) -> Composed {
+    compose_with_sources(env, url, source, node, &|_| None).await
+}
+
+/// Like [compose], but included files are taken from `sources` first, like the
+/// unsaved documents of an editor, and only read if it returns `None`.
+pub async fn compose_with_sources<E: Environment>(
+    env: &E,
+    url: &Url,
+    source: &str,
+    node: &Node,
+    sources: &dyn Fn(&Url) -> Option<String>,
+) -> Composed {
+    let mut composer = Composer {
+        env,
+        sources,
+        files: IndexMap::default(),
+        composed: HashMap::default(),
+        errors: vec![],
+        stack: vec![],
+    };
+    let node = composer
+        .compose_file(url.clone(), source.to_string(), node.clone(), None)
+        .await;
+    Composed {
+        node,
+        graph: IncludeGraph {
+            files: composer.files,
+        },
+        errors: composer.errors,
+    }
+}
+
+/// Resolve the path of an include against the URL of the including file,
+/// `None` if it is not a `file:` URL.
+pub fn include_url(base: &Url, path: &str) -> Option<Url> {
+    let url: Url = if is_url(path) {
+        path.parse().ok()?
+    } else {
+        base.join(path).ok()?
+    };
+    (url.scheme() == "file").then_some(url)
+}
+
+struct Composer<'a, E: Environment> {
+    env: &'a E,
+    sources: &'a dyn Fn(&Url) -> Option<String>,
+    files: IndexMap<Url, IncludedFile>,
+    /// Composed documents by URL, `None` for files that failed.
+    composed: HashMap<Url, Option<Node>>,
+    errors: Vec<IncludeError>,
+    /// Files being composed.
+    stack: Vec<Url>,
+}
+
+impl<'a, E: Environment> Composer<'a, E> {
+    /// Resolve the includes of a file, `include_range` is the include of the root file
+    /// the file is reached through.
+    fn compose_file(
+        &mut self,
+        url: Url,
+        source: String,
+        node: Node,
+        include_range: Option<TextRange>,
+    ) -> LocalBoxFuture<'_, Node> {
+        Box::pin(async move {
+            let anchored = anchors::resolve(&node);
+            self.errors
+                .extend(anchored.diagnostics.into_iter().map(|v| IncludeError {
+                    location: Location {
+                        file: url.clone(),
+                        range: v.range,
+                        include_range,
+                    },
+                    kind: IncludeErrorKind::Alias(v.message),
+                }));
+            let parsed = node;
+            let node = anchored.node.unwrap_or_else(|| parsed.clone());
+
+            self.stack.push(url.clone());
+            self.files.insert(
+                url.clone(),
+                IncludedFile {
+                    url: url.clone(),
+                    source,
+                    node: parsed.clone(),
+                    includes: vec![],
+                },
+            );
+
+            let mut includes = vec![];
+            let mut resolved: HashMap<Url, Node> = HashMap::default();
+            for (keys, value) in visit_annotations(&node).into_iter() {
+                if keys.last_annotation_key().map(|v| v.value()) != Some(INCLUDE_KEY) {
+                    continue;
+                }
+                let keys = Keys::new(keys.iter().take(keys.len() - 1).cloned());
+                let range = annotation_range(&parsed, &keys);
+                let location = |range: Option<TextRange>| Location {
+                    file: url.clone(),
+                    range,
+                    include_range,
+                };
+                let target = match value.as_string().and_then(|v| include_url(&url, v.value())) {
+                    Some(v) => v,
+                    None => {
+                        self.errors.push(IncludeError {
+                            location: location(range),
+                            kind: IncludeErrorKind::InvalidPath,
+                        });
+                        continue;
+                    }
+                };
+                let mut include = Include {
+                    keys,
+                    range,
+                    url: target.clone(),
+                    resolved: false,
+                };
+                if let Some(i) = self.stack.iter().position(|v| v == &target) {
+                    let mut cycle = self.stack[i..].to_vec();
+                    cycle.push(target);
+                    self.errors.push(IncludeError {
+                        location: location(range),
+                        kind: IncludeErrorKind::Cycle(cycle),
+                    });
+                    includes.push(include);
+                    continue;
+                }
+                let composed = match self.composed.get(&target) {
+                    Some(v) => v.clone(),
+                    None => {
+                        let v = self.load(&target, location(range)).await;
+                        self.composed.insert(target.clone(), v.clone());
+                        v
+                    }
+                };
+                if let Some(v) = composed {
+                    include.resolved = true;
+                    resolved.insert(target, v);
+                }
+                includes.push(include);
+            }
+
+            self.stack.pop();
+            if includes.is_empty() {
+                return node;
+            }
+            self.files[&url].includes = includes;
+
+            let mut pipeline = Pipeline::new();
+            pipeline.register(INCLUDE_KEY, move |cx: &mut Context| {
+                let node = cx
+                    .value()
+                    .as_string()
+                    .and_then(|v| include_url(&url, v.value()))
+                    .and_then(|v| resolved.get(&v));
+                if let Some(node) = node {
+                    cx.replace(node.clone());
+                }
+            });
+            pipeline.run(&node).node.unwrap_or(node)
+        })
+    }
+
+    /// Read, parse and compose a file included at the given location.
+    async fn load(&mut self, target: &Url, include: Location) -> Option<Node> {
+        let source = match (self.sources)(target) {
+            Some(v) => Ok(v),
+            None => self.env.read_file(target).await.and_then(|v| {
+                String::from_utf8(v).map_err(|_| anyhow::anyhow!("the file is not valid UTF-8"))
+            }),
+        };
+        let source = match source {
+            Ok(v) => v,
+            Err(error) => {
+                self.errors.push(IncludeError {
+                    location: include,
+                    kind: IncludeErrorKind::Read {
+                        url: target.clone(),
+                        error: error.to_string(),
+                    },
+                });
+                return None;
+            }
+        };
+        let include_range = include.include_range.or(include.range);
+        let location = |range: Option<TextRange>| Location {
+            file: target.clone(),
+            range,
+            include_range,
+        };
+        let parse = parser::parse(&source);
+        if !parse.errors.is_empty() {
+            self.errors
+                .extend(parse.errors.iter().map(|v| IncludeError {
+                    location: location(Some(v.range)),
+                    kind: IncludeErrorKind::Syntax(v.message.clone()),
+                }));
+            self.add_invalid_file(target, source, parse.into_dom());
+            return None;
+        }
+        let node = parse.into_dom();
+        if let Err(errors) = node.validate() {
+            self.errors.extend(errors.map(|v| IncludeError {
+                location: location(dom_error_range(&v)),
+                kind: IncludeErrorKind::Semantic(v.to_string()),
+            }));
+            self.add_invalid_file(target, source, node);
+            return None;
+        }
+        Some(
+            self.compose_file(target.clone(), source, node, include_range)
+                .await,
+        )
+    }
+
+    fn add_invalid_file(&mut self, url: &Url, source: String, node: Node) {
+        self.files.insert(
+            url.clone(),
+            IncludedFile {
+                url: url.clone(),
+                source,
+                node,
+                includes: vec![],
+            },
+        );
+    }
+}
+
+/// Range of the include at the keys of the resolved document, see [anchors::locate].
+fn annotation_range(node: &Node, keys: &Keys) -> Option<TextRange> {
+    anchors::locate(node, keys)
+        .annotations()?
+        .annotation_syntax(&INCLUDE_KEY.into())
+        .map(|v| v.text_range())
+}
+
+fn dom_error_range(error: &DomError) -> Option<TextRange> {
+    match error {
+        DomError::ConflictingKeys { key, .. } | DomError::DuplicateKeys { key, .. } => {
+            key.text_range()
+        }
+        DomError::InvalidNode { syntax }
+        | DomError::InvalidString { syntax }
+        | DomError::InvalidNumber { syntax } => Some(syntax.text_range()),
+    }
+}
+
+fn starts_with(keys: &Keys, prefix: &Keys) -> bool {
+    keys.len() >= prefix.len() && keys.iter().zip(prefix.iter()).all(|(a, b)| a == b)
+}
+
+fn display_cycle(urls: &[Url]) -> String {
+    urls.iter()
+        .map(|v| v.as_str())
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::native::NativeEnvironment;
+    use std::path::Path;
+
+    fn write(dir: &Path, name: &str, source: &str) {
+        let path = dir.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, source).unwrap();
+    }
+
+    #[test]
+    fn test_compose() {
+        let dir = std::env::temp_dir().join(format!("jsona-include-{}", std::process::id()));
+        write(
+            &dir,
+            "common/auth.jsona",
+            "{ type: 'bearer', scopes: null @include('../scopes.jsona') }",
+        );
+        let scopes = "['read' @anchor('r'), 'write', null @alias('r'), null @alias('x')]";
+        write(&dir, "scopes.jsona", scopes);
+        write(&dir, "a.jsona", "{ b: null @include('./b.jsona') }");
+        write(&dir, "b.jsona", "{ a: null @include('./a.jsona') }");
+        write(&dir, "bad.jsona", "{ x: }");
+        let source = r#"{
+  auth: null @include("./common/auth.jsona") @mock,
+  auth2: null @include("./common/auth.jsona"),
+  cycle: null @include("./a.jsona"),
+  bad: 1 @include("./bad.jsona"),
+  missing: 2 @include("./missing.jsona"),
+  remote: 3 @include("https://example.com/a.jsona"),
+  unsaved: 4 @include("./unsaved.jsona"),
+  anchored: { k: 5 } @anchor("a"),
+  copy: null @alias("a"),
+}"#;
+        let url = Url::from_file_path(dir.join("main.jsona")).unwrap();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let composed = runtime.block_on(async {
+            let env = NativeEnvironment::new();
+            let unsaved = Url::from_file_path(dir.join("unsaved.jsona")).unwrap();
+            let sources = |v: &Url| (v == &unsaved).then(|| "{ x: 1 }".to_string());
+            compose_with_sources(&env, &url, source, &source.parse().unwrap(), &sources).await
+        });
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            composed.node.to_canonical_string(),
+            r#"{"anchored":{"k":5},"auth":{@mock "scopes":["read","write","read",null],"type":"bearer"},"auth2":{"scopes":["read","write","read",null],"type":"bearer"},"bad":1,"copy":{"k":5},"cycle":{"b":{"a":null}},"missing":2,"remote":3,"unsaved":{"x":1}}"#
+        );
+        let file = |name: &str| Url::from_file_path(dir.join(name)).unwrap();
+        assert_eq!(composed.graph.edges().count(), 9);
+
+        let location = composed.locate(&"auth.scopes[1]".parse().unwrap());
+        assert_eq!(location.file, file("scopes.jsona"));
+        assert_eq!(&scopes[location.range.unwrap()], "'write'");
+        assert_eq!(
+            &source[location.include_range.unwrap()],
+            r#"@include("./common/auth.jsona")"#
+        );
+        let location = composed.locate(&"missing".parse().unwrap());
+        assert_eq!(location.file, url);
+        assert_eq!(location.include_range, None);
+        let location = composed.locate(&"copy.k".parse().unwrap());
+        let range = location.range.unwrap();
+        assert_eq!(usize::from(range.start()), source.find("5").unwrap());
+        assert_eq!(&source[range], "5");
+        let location = composed.locate(&"auth2.scopes[2]".parse().unwrap());
+        assert_eq!(&scopes[location.range.unwrap()], "null");
+
+        let errors: Vec<(String, String)> = composed
+            .errors
+            .iter()
+            .map(|v| {
+                let name = v
+                    .location
+                    .file
+                    .path()
+                    .rsplit('/')
+                    .next()
+                    .unwrap()
+                    .to_string();
+                (name, v.kind.to_string())
+            })
+            .collect();
+        assert_eq!(errors[0].0, "scopes.jsona");
+        assert_eq!(errors[0].1, "unknown anchor `x`");
+        assert_eq!(errors[1].0, "b.jsona");
+        assert_eq!(
+            errors[1].1,
+            format!(
+                "cyclic include {} -> {} -> {}",
+                file("a.jsona"),
+                file("b.jsona"),
+                file("a.jsona")
+            )
+        );
+        assert_eq!(errors[2].0, "bad.jsona");
+        assert_eq!(errors[3].0, "main.jsona");
+        assert!(errors[3].1.starts_with("failed to read"));
+        assert_eq!(errors[4].0, "main.jsona");
+        assert_eq!(
+            errors[4].1,
+            "the value of @include must be a relative path or file URL"
+        );
+        assert_eq!(errors.len(), 5);
+    }
+}
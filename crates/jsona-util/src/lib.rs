@@ -1,4 +1,5 @@
 pub mod environment;
+pub mod include;
 pub mod log;
 pub mod schema;
 pub mod util;
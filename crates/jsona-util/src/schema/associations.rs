@@ -135,13 +135,11 @@ impl<E: Environment> SchemaAssociations<E> {
         self.associations
             .write()
             .retain(|(rule, assoc)| match rule {
-                AssociationRule::Url(u) => {
-                    if u == doc_url && assoc.meta["source"] == source::SCHEMA_FIELD {
-                        dirty = true;
-                        false
-                    } else {
-                        true
-                    }
+                AssociationRule::Url(u)
+                    if u == doc_url && assoc.meta["source"] == source::SCHEMA_FIELD =>
+                {
+                    dirty = true;
+                    false
                 }
                 _ => true,
             });
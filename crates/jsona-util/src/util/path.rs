@@ -41,5 +41,5 @@ pub fn encode_url<T: AsRef<str>>(path: T) -> String {
 
 pub fn to_string<T: AsRef<Path>>(path: T) -> String {
     let path = path.as_ref().display().to_string();
-    path.trim_end_matches(|v| v == '/' || v == '\\').to_string()
+    path.trim_end_matches(['/', '\\']).to_string()
 }
@@ -16,12 +16,14 @@ mod environment;
 #[cfg(feature = "lsp")]
 mod lsp;
 
+#[allow(dead_code)]
 #[derive(Serialize)]
 struct Range {
     start: u32,
     end: u32,
 }
 
+#[allow(dead_code)]
 #[derive(Serialize)]
 struct LintError {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -29,6 +31,7 @@ struct LintError {
     error: String,
 }
 
+#[allow(dead_code)]
 #[derive(Serialize)]
 struct LintResult {
     errors: Vec<LintError>,
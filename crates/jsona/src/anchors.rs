@@ -0,0 +1,333 @@
+//! Reusing values inside a document with anchors and aliases.
+//!
+//! `@anchor("name")` marks a value, `@alias("name")` replaces the annotated value with a
+//! copy of it. If the annotated value is a non-empty object, its properties are added to
+//! the copy, replacing the properties of the anchor with the same key.
+//!
+//! The source stays compact, [resolve] builds the expanded view of a document and
+//! [locate] finds where a value of that view is written.
+//!
+//! ```
+//! use jsona::anchors::resolve;
+//! use jsona::dom::Node;
+//!
+//! let node: Node = r#"{
+//!   a: { type: "json", size: 1 } @anchor("headers"),
+//!   b: null @alias("headers"),
+//!   c: { size: 2 } @alias("headers"),
+//! }"#.parse().unwrap();
+//!
+//! let output = resolve(&node);
+//! assert!(output.diagnostics.is_empty());
+//! assert_eq!(
+//!     output.node.unwrap().to_canonical_string(),
+//!     r#"{"a":{"size":1,"type":"json"},"b":{"size":1,"type":"json"},"c":{"size":2,"type":"json"}}"#
+//! );
+//! ```
+
+use crate::dom::{visit_annotations, DomNode, Key, Keys, Node, Object};
+use crate::processor::{Context, Diagnostic, Pipeline, Processed, Severity};
+
+use rowan::{TextRange, TextSize};
+use std::collections::{HashMap, HashSet};
+
+pub const ANCHOR_KEY: &str = "@anchor";
+pub const ALIAS_KEY: &str = "@alias";
+
+/// An `@anchor` or `@alias` annotation.
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub name: String,
+    /// Path of the annotated value.
+    pub keys: Keys,
+    /// The annotated value.
+    pub node: Node,
+    /// Range of the annotation.
+    pub range: Option<TextRange>,
+}
+
+/// All anchors of the document, in the order they are written.
+pub fn anchors(node: &Node) -> Vec<Reference> {
+    references(node, ANCHOR_KEY)
+}
+
+/// All aliases of the document, in the order they are written.
+pub fn aliases(node: &Node) -> Vec<Reference> {
+    references(node, ALIAS_KEY)
+}
+
+/// Find the anchor of the alias at the offset.
+pub fn definition_at(node: &Node, offset: TextSize) -> Option<Reference> {
+    let alias = aliases(node)
+        .into_iter()
+        .find(|v| v.range.is_some_and(|v| v.contains_inclusive(offset)))?;
+    anchors(node).into_iter().find(|v| v.name == alias.name)
+}
+
+/// Build the document with aliases replaced by the values of their anchors.
+///
+/// Both `@anchor` and `@alias` annotations are removed, unknown anchors, anchors that
+/// contain aliases of themselves and duplicate anchors are reported as diagnostics.
+pub fn resolve(node: &Node) -> Processed {
+    let mut resolver = Resolver::default();
+    for anchor in anchors(node) {
+        if resolver.anchors.contains_key(&anchor.name) {
+            resolver.diagnostics.push(Diagnostic {
+                keys: anchor.keys.clone(),
+                annotation: Key::annotation(ANCHOR_KEY),
+                severity: Severity::Error,
+                message: format!("duplicate anchor `{}`", anchor.name),
+                range: anchor.range,
+            });
+            continue;
+        }
+        resolver.anchors.insert(anchor.name.clone(), anchor.node);
+    }
+    let output = resolver.resolve_node(node);
+    let mut diagnostics = resolver.diagnostics;
+    diagnostics.extend(output.diagnostics);
+    Processed {
+        node: output.node,
+        diagnostics,
+    }
+}
+
+#[derive(Default)]
+struct Resolver {
+    anchors: HashMap<String, Node>,
+    /// Resolved anchors, `None` for anchors that contain aliases of themselves.
+    resolved: HashMap<String, Option<Node>>,
+    stack: Vec<String>,
+    cyclic: HashSet<String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Resolver {
+    fn resolve_node(&mut self, node: &Node) -> Processed {
+        for alias in aliases(node) {
+            self.resolve_anchor(&alias.name);
+        }
+        let resolved = self.resolved.clone();
+        let mut pipeline = Pipeline::new();
+        pipeline
+            .register(ANCHOR_KEY, |_: &mut Context| {})
+            .register(ALIAS_KEY, move |cx: &mut Context| {
+                let name = match cx.value().as_string() {
+                    Some(v) => v.value().to_string(),
+                    None => return cx.error("the value of @alias must be a string"),
+                };
+                match resolved.get(&name) {
+                    Some(Some(anchor)) => {
+                        let node = cx.node().clone();
+                        let node = merge(anchor, &node, cx);
+                        cx.replace(node)
+                    }
+                    Some(None) => {
+                        cx.error(format!("the anchor `{name}` contains an alias of itself"))
+                    }
+                    None => cx.error(format!("unknown anchor `{name}`")),
+                }
+            });
+        pipeline.run(node)
+    }
+
+    fn resolve_anchor(&mut self, name: &str) {
+        if self.resolved.contains_key(name) {
+            return;
+        }
+        if self.stack.iter().any(|v| v == name) {
+            self.cyclic.insert(name.to_string());
+            return;
+        }
+        let node = match self.anchors.get(name) {
+            Some(v) => v.clone(),
+            None => return,
+        };
+        self.stack.push(name.to_string());
+        // Problems inside of the anchor are reported when the document itself is resolved.
+        let output = self.resolve_node(&node);
+        self.stack.pop();
+        let output = if self.cyclic.contains(name) {
+            None
+        } else {
+            output.node
+        };
+        self.resolved.insert(name.to_string(), output);
+    }
+}
+
+/// Find the node of the document that the value at the keys of the [resolve]d document
+/// comes from.
+///
+/// Values copied from an anchor are found in the anchor, properties that override
+/// those of the anchor at the alias. Keys that do not exist end at the deepest node found.
+pub fn locate(node: &Node, keys: &Keys) -> Node {
+    let anchors = anchors(node);
+    let mut current = node.clone();
+    for key in keys.iter() {
+        let mut target = current.clone();
+        let mut found = None;
+        // An anchor can be an alias in turn, every anchor is followed at most once.
+        for _ in 0..=anchors.len() {
+            if let Some(v) = target.get(key) {
+                found = Some(v);
+                break;
+            }
+            let name = target
+                .annotations()
+                .and_then(|v| v.get(&Key::annotation(ALIAS_KEY)))
+                .and_then(|v| v.as_string().map(|v| v.value().to_string()));
+            match name.and_then(|name| anchors.iter().find(|v| v.name == name)) {
+                Some(anchor) => target = anchor.node.clone(),
+                None => break,
+            }
+        }
+        match found {
+            Some(v) => current = v,
+            None => return current,
+        }
+    }
+    current
+}
+
+/// Copy the anchor, with the properties of the aliased object added.
+fn merge(anchor: &Node, node: &Node, cx: &mut Context) -> Node {
+    let properties = match node.as_object() {
+        Some(v) if !v.value().read().is_empty() => v.value().read(),
+        _ => return anchor.clone(),
+    };
+    let anchor_object = match anchor.as_object() {
+        Some(v) => v,
+        None => {
+            cx.error("only objects can be overridden, the properties are ignored");
+            return anchor.clone();
+        }
+    };
+    let mut map = (**anchor_object.value().read()).clone();
+    for (key, (value, syntax)) in properties.value.iter() {
+        map.add(key.clone(), value.clone(), syntax.clone());
+    }
+    Object::new(map, anchor.annotations().cloned()).into()
+}
+
+fn references(node: &Node, annotation: &str) -> Vec<Reference> {
+    visit_annotations(node)
+        .into_iter()
+        .filter(|(keys, _)| keys.last_annotation_key().map(|v| v.value()) == Some(annotation))
+        .filter_map(|(keys, value)| {
+            let name = value.as_string()?.value().to_string();
+            let keys = Keys::new(keys.iter().take(keys.len() - 1).cloned());
+            let node = node.path(&keys)?;
+            let range = node
+                .annotations()
+                .and_then(|v| v.annotation_syntax(&Key::annotation(annotation)))
+                .map(|v| v.text_range());
+            Some(Reference {
+                name,
+                keys,
+                node,
+                range,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let node: Node = r#"{
+  a: [1, 2] @anchor("list"),
+  b: { x: 0, y: null @alias("list") } @anchor("obj") @mock,
+  c: { z: 3 } @alias("obj") @mock,
+  d: [0, null @alias("obj")],
+}"#
+        .parse()
+        .unwrap();
+        let output = resolve(&node);
+        assert!(output.diagnostics.is_empty());
+        assert_eq!(
+            output.node.unwrap().to_canonical_string(),
+            r#"{"a":[1,2],"b":{@mock "x":0,"y":[1,2]},"c":{@mock "x":0,"y":[1,2],"z":3},"d":[0,{@mock "x":0,"y":[1,2]}]}"#
+        );
+    }
+
+    #[test]
+    fn test_resolve_errors() {
+        let source = r#"{
+  a: { b: 1 @alias("a") } @anchor("a"),
+  c: 1 @anchor("c"),
+  d: 2 @anchor("c"),
+  e: { f: 1 } @alias("c"),
+  g: null @alias("nope"),
+}"#;
+        let node: Node = source.parse().unwrap();
+        let output = resolve(&node);
+        let diagnostics: Vec<(String, &str)> = output
+            .diagnostics
+            .iter()
+            .map(|v| (v.to_string(), &source[v.range.unwrap()]))
+            .collect();
+        assert_eq!(
+            diagnostics,
+            [
+                (
+                    "@anchor at .d: duplicate anchor `c`".to_string(),
+                    r#"@anchor("c")"#
+                ),
+                (
+                    "@alias at .a.b: the anchor `a` contains an alias of itself".to_string(),
+                    r#"@alias("a")"#
+                ),
+                (
+                    "@alias at .e: only objects can be overridden, the properties are ignored"
+                        .to_string(),
+                    r#"@alias("c")"#
+                ),
+                (
+                    "@alias at .g: unknown anchor `nope`".to_string(),
+                    r#"@alias("nope")"#
+                ),
+            ]
+        );
+        assert_eq!(
+            output.node.unwrap().to_canonical_string(),
+            r#"{"a":{"b":1},"c":1,"d":2,"e":1,"g":null}"#
+        );
+    }
+
+    #[test]
+    fn test_locate() {
+        let source = r#"{
+  a: { x: 0, y: [1, 2] } @anchor("a"),
+  b: { x: 3 } @alias("a") @anchor("b"),
+  c: null @alias("b"),
+}"#;
+        let node: Node = source.parse().unwrap();
+        let text = |keys: &str| {
+            let keys: Keys = keys.parse().unwrap();
+            let range = locate(&node, &keys).text_range().unwrap();
+            &source[range]
+        };
+        assert_eq!(text("b.x"), "3");
+        assert_eq!(text("b.y[1]"), "2");
+        assert_eq!(text("c.x"), "3");
+        assert_eq!(text("c.y"), "[1, 2]");
+        assert_eq!(text("c"), "null");
+        assert_eq!(text("c.z"), "null");
+        assert_eq!(text("c@alias"), r#""b""#);
+    }
+
+    #[test]
+    fn test_definition_at() {
+        let source = "{ a: 1 @anchor('x'), b: null @alias('x') }";
+        let node: Node = source.parse().unwrap();
+        let offset = TextSize::from(source.find("@alias").unwrap() as u32 + 3);
+        let anchor = definition_at(&node, offset).unwrap();
+        assert_eq!(anchor.keys.to_string(), ".a");
+        assert_eq!(&source[anchor.range.unwrap()], "@anchor('x')");
+        assert!(definition_at(&node, 0.into()).is_none());
+    }
+}
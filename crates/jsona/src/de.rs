@@ -0,0 +1,513 @@
+//! Deserialize Rust types from JSONA.
+//!
+//! The [Deserializer] walks a [DOM](crate::dom) node, so errors
+//! carry the [Keys] path and the text range of the offending node.
+//!
+//! Annotations are ignored.
+//!
+//! ```
+//! use serde::Deserialize;
+//!
+//! #[derive(Debug, Deserialize)]
+//! struct Config {
+//!     name: String,
+//!     port: u16,
+//! }
+//!
+//! let config: Config = jsona::from_str(r#"{ name: "foo", port: 8080 } @describe("config")"#).unwrap();
+//! assert_eq!(config.port, 8080);
+//!
+//! let err = jsona::from_str::<Config>(r#"{ name: "foo", port: "8080" }"#).unwrap_err();
+//! assert_eq!(err.keys().unwrap().to_string(), ".port");
+//! ```
+
+use crate::dom::{Key, Keys, Node};
+use rowan::TextRange;
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess,
+    VariantAccess, Visitor,
+};
+use serde::forward_to_deserialize_any;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Deserialize an instance of `T` from JSONA text.
+///
+/// The text must be free of syntax and DOM errors.
+pub fn from_str<T: DeserializeOwned>(s: &str) -> Result<T, Error> {
+    let node = Node::from_str(s)?;
+    from_node(&node)
+}
+
+/// Deserialize an instance of `T` from a DOM node.
+pub fn from_node<T: DeserializeOwned>(node: &Node) -> Result<T, Error> {
+    T::deserialize(Deserializer::new(node.clone()))
+}
+
+#[derive(Debug, Clone, Error)]
+pub enum Error {
+    /// The text is not valid JSONA.
+    #[error(transparent)]
+    Jsona(#[from] crate::error::Error),
+    /// The node does not match the type.
+    #[error("{message}{}", display_keys(.keys))]
+    Data {
+        message: String,
+        keys: Option<Keys>,
+        range: Option<TextRange>,
+    },
+}
+
+impl Error {
+    /// The path of the node that failed to deserialize.
+    pub fn keys(&self) -> Option<&Keys> {
+        match self {
+            Error::Data { keys, .. } => keys.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// The text range of the node that failed to deserialize.
+    pub fn text_range(&self) -> Option<TextRange> {
+        match self {
+            Error::Data { range, .. } => *range,
+            _ => None,
+        }
+    }
+
+    /// Attach the location if the error has none yet.
+    fn at(mut self, at_keys: &Keys, at_range: Option<TextRange>) -> Self {
+        if let Error::Data { keys, range, .. } = &mut self {
+            if keys.is_none() {
+                *keys = Some(at_keys.clone());
+                *range = at_range;
+            }
+        }
+        self
+    }
+}
+
+fn display_keys(keys: &Option<Keys>) -> String {
+    match keys {
+        Some(keys) if !keys.is_empty() => format!(" at {keys}"),
+        _ => String::new(),
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Error::Data {
+            message: msg.to_string(),
+            keys: None,
+            range: None,
+        }
+    }
+}
+
+/// A deserializer over a DOM node.
+pub struct Deserializer {
+    node: Node,
+    keys: Keys,
+}
+
+impl Deserializer {
+    pub fn new(node: Node) -> Self {
+        Self {
+            node,
+            keys: Keys::default(),
+        }
+    }
+
+    fn child(node: Node, keys: Keys) -> Self {
+        Self { node, keys }
+    }
+
+    fn locate<T>(&self, ret: Result<T, Error>) -> Result<T, Error> {
+        ret.map_err(|err| err.at(&self.keys, self.node.text_range()))
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let ret = match &self.node {
+            Node::Null(_) => visitor.visit_unit(),
+            Node::Bool(v) => visitor.visit_bool(v.value()),
+            Node::Number(v) => {
+                let value = v.value();
+                if !v.is_finite() {
+                    visitor.visit_f64(v.as_f64())
+                } else if let Some(n) = value.as_u64() {
+                    visitor.visit_u64(n)
+                } else if let Some(n) = value.as_i64() {
+                    visitor.visit_i64(n)
+                } else if let Some(n) = v.is_integer().then(|| v.as_u128()).flatten() {
+                    visitor.visit_u128(n)
+                } else if let Some(n) = v.is_integer().then(|| v.as_i128()).flatten() {
+                    visitor.visit_i128(n)
+                } else {
+                    visitor.visit_f64(v.as_f64())
+                }
+            }
+            Node::String(v) => visitor.visit_str(v.value()),
+            Node::Array(v) => {
+                let items = v.value().read().to_vec();
+                visitor.visit_seq(SeqDeserializer {
+                    items: items.into_iter().enumerate(),
+                    keys: &self.keys,
+                })
+            }
+            Node::Object(v) => {
+                let entries: Vec<(Key, Node)> = v
+                    .value()
+                    .read()
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect();
+                visitor.visit_map(MapDeserializer {
+                    entries: entries.into_iter(),
+                    value: None,
+                    keys: &self.keys,
+                })
+            }
+        };
+        self.locate(ret)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match &self.node {
+            Node::Null(_) => {
+                let ret = visitor.visit_none();
+                self.locate(ret)
+            }
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let ret = match &self.node {
+            Node::String(v) => visitor.visit_enum(v.value().to_string().into_deserializer()),
+            Node::Object(v) if v.value().read().len() == 1 => {
+                let (key, node) = v
+                    .value()
+                    .read()
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .next()
+                    .unwrap();
+                visitor.visit_enum(EnumDeserializer {
+                    key,
+                    node,
+                    keys: &self.keys,
+                })
+            }
+            _ => Err(de::Error::invalid_type(
+                unexpected(&self.node),
+                &"string or map with a single key",
+            )),
+        };
+        self.locate(ret)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier
+    }
+}
+
+fn unexpected(node: &Node) -> de::Unexpected<'_> {
+    match node {
+        Node::Null(_) => de::Unexpected::Unit,
+        Node::Bool(v) => de::Unexpected::Bool(v.value()),
+        Node::Number(v) => match v.value().as_i64() {
+            Some(n) if v.is_finite() => de::Unexpected::Signed(n),
+            _ => de::Unexpected::Float(v.as_f64()),
+        },
+        Node::String(v) => de::Unexpected::Str(v.value()),
+        Node::Array(_) => de::Unexpected::Seq,
+        Node::Object(_) => de::Unexpected::Map,
+    }
+}
+
+struct SeqDeserializer<'a> {
+    items: std::iter::Enumerate<std::vec::IntoIter<Node>>,
+    keys: &'a Keys,
+}
+
+impl<'de, 'a> SeqAccess<'de> for SeqDeserializer<'a> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        match self.items.next() {
+            Some((index, node)) => seed
+                .deserialize(Deserializer::child(node, self.keys.join(index)))
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.items.len())
+    }
+}
+
+struct MapDeserializer<'a> {
+    entries: std::vec::IntoIter<(Key, Node)>,
+    value: Option<(Keys, Node)>,
+    keys: &'a Keys,
+}
+
+impl<'de, 'a> MapAccess<'de> for MapDeserializer<'a> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match self.entries.next() {
+            Some((key, node)) => {
+                let keys = self.keys.join(key.clone());
+                let range = key.text_range();
+                let ret = seed
+                    .deserialize(MapKeyDeserializer { key })
+                    .map_err(|err| err.at(&keys, range))?;
+                self.value = Some((keys, node));
+                Ok(Some(ret))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        match self.value.take() {
+            Some((keys, node)) => seed.deserialize(Deserializer::child(node, keys)),
+            None => Err(de::Error::custom("value is missing")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+struct EnumDeserializer<'a> {
+    key: Key,
+    node: Node,
+    keys: &'a Keys,
+}
+
+impl<'de, 'a> EnumAccess<'de> for EnumDeserializer<'a> {
+    type Error = Error;
+    type Variant = Deserializer;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Error> {
+        let keys = self.keys.join(self.key.clone());
+        let range = self.key.text_range();
+        let variant = seed
+            .deserialize(MapKeyDeserializer { key: self.key })
+            .map_err(|err| err.at(&keys, range))?;
+        Ok((variant, Deserializer::child(self.node, keys)))
+    }
+}
+
+impl<'de> VariantAccess<'de> for Deserializer {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        de::Deserialize::deserialize(self)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
+
+/// Deserialize object keys, integer types are parsed from the key text.
+struct MapKeyDeserializer {
+    key: Key,
+}
+
+macro_rules! deserialize_key_from_str {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                match self.key.value().parse() {
+                    Ok(v) => visitor.$visit(v),
+                    Err(_) => visitor.visit_str(self.key.value()),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for MapKeyDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_str(self.key.value())
+    }
+
+    deserialize_key_from_str! {
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_bool => visit_bool,
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_enum(self.key.value().to_string().into_deserializer())
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 f32 f64 char str string bytes byte_buf unit unit_struct
+        seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Config {
+        name: String,
+        version: Option<u32>,
+        tags: Vec<String>,
+        ports: HashMap<u16, Protocol>,
+        log_level: Level,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Protocol {
+        Tcp,
+        Udp { buffer: usize },
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Level {
+        Info,
+        Debug(bool),
+    }
+
+    #[test]
+    fn test_from_str() {
+        let config: Config = from_str(
+            r#"{ @describe("config")
+  name: "foo",
+  version: null,
+  tags: ['a', `b`],
+  ports: { 80: "tcp", 53: { udp: { buffer: 0x200 } } },
+  logLevel: { Debug: true }, @mock
+}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            Config {
+                name: "foo".into(),
+                version: None,
+                tags: vec!["a".into(), "b".into()],
+                ports: HashMap::from([(80, Protocol::Tcp), (53, Protocol::Udp { buffer: 512 })]),
+                log_level: Level::Debug(true),
+            }
+        );
+    }
+
+    #[test]
+    fn test_from_str_error() {
+        macro_rules! assert_error {
+            ($source:literal, $message:literal, $range:expr) => {
+                let err = from_str::<Config>($source).unwrap_err();
+                assert_eq!(err.to_string(), $message);
+                let range = $range;
+                assert_eq!(
+                    err.text_range(),
+                    Some(TextRange::new(range.start.into(), range.end.into()))
+                );
+            };
+        }
+        assert_error!(
+            r#"{ name: 1 }"#,
+            "invalid type: integer `1`, expected a string at .name",
+            8..9
+        );
+        assert_error!(
+            r#"{ name: "a", tags: ["a", null] }"#,
+            "invalid type: unit value, expected a string at .tags[1]",
+            25..29
+        );
+        assert_error!(
+            r#"{ name: "a", tags: [], ports: { 80: "http" } }"#,
+            "unknown variant `http`, expected `tcp` or `udp` at .ports.80",
+            36..42
+        );
+        assert_error!(r#"{ name: "a", tags: [] }"#, "missing field `ports`", 0..23);
+        assert!(matches!(
+            from_str::<Config>("{ a: 1, a: 2 }"),
+            Err(Error::Jsona(_))
+        ));
+    }
+}
@@ -0,0 +1,299 @@
+use super::node::{DomNode, Key, NonFinite, Number, NumberRepr};
+use super::Node;
+
+impl Node {
+    /// Serialize the node into its canonical form, a byte-stable representation
+    /// suitable for hashing and signing.
+    ///
+    /// Two nodes have the same canonical form if and only if they hold the same data and
+    /// annotations, regardless of the layout and comments of their source. The form is:
+    ///
+    /// - No whitespace, except a single space before every annotation that does not
+    ///   directly follow an opening bracket, and between the annotations and the first
+    ///   item of an object or array.
+    /// - `null`, `true` and `false` as is.
+    /// - Numbers:
+    ///   - `nan`, `inf` and `-inf` for non-finite numbers.
+    ///   - Integers, including floats without fraction up to 2^53, in decimal
+    ///     without leading zeros or `+`, and `0` for negative zero.
+    ///   - Other numbers are read as IEEE 754 doubles and written with the shortest
+    ///     digits that read back to the same double. Numbers with a decimal exponent
+    ///     `e` in `-7 < e < 21` are written in plain notation, like `0.001`, others in
+    ///     exponent notation with one leading digit, like `1.5e-7` or `1e300`.
+    ///   - With the `arbitrary_precision` feature, other numbers are not rounded, their
+    ///     exact digits without trailing zeros are written in the same notation.
+    /// - Strings and property keys are double-quoted. `"` and `\` are escaped with a
+    ///   backslash, `\b`, `\t`, `\n`, `\f` and `\r` use these short escapes, other
+    ///   characters below U+0020 and U+007F are written as `\u00XX` with lowercase
+    ///   hex digits and all other characters are written as UTF-8.
+    /// - Object properties are sorted by the code points of their unescaped keys,
+    ///   array items keep their order.
+    /// - Annotations are sorted by name. Annotations of objects and arrays are written
+    ///   right after the opening bracket, those of other values after the value.
+    ///   An annotation with `null` value is written without parentheses, others are
+    ///   followed by their canonical value in parentheses.
+    ///
+    /// ```
+    /// use jsona::dom::Node;
+    ///
+    /// let node: Node = r#"{
+    ///   b: [0x10, 1.50, 'x'], @mock
+    ///   a: 1.0e-7, // comment
+    ///   @z @y(null)
+    /// }"#.parse().unwrap();
+    /// assert_eq!(
+    ///     node.to_canonical_string(),
+    ///     r#"{"a":1e-7 @y @z,"b":[@mock 16,1.5,"x"]}"#
+    /// );
+    /// ```
+    pub fn to_canonical_string(&self) -> String {
+        let mut output = String::new();
+        write_canonical(&mut output, self);
+        output
+    }
+}
+
+fn write_canonical(output: &mut String, node: &Node) {
+    match node {
+        Node::Array(v) => {
+            output.push('[');
+            let mut sep = write_annotations(output, node, true);
+            for item in v.value().read().iter() {
+                output.push_str(sep);
+                write_canonical(output, item);
+                sep = ",";
+            }
+            output.push(']');
+        }
+        Node::Object(v) => {
+            output.push('{');
+            let mut sep = write_annotations(output, node, true);
+            let map = v.value().read();
+            let mut properties: Vec<(&Key, &Node)> = map.iter().collect();
+            properties.sort_by(|a, b| a.0.value().cmp(b.0.value()));
+            for (key, value) in properties {
+                output.push_str(sep);
+                write_string(output, key.value());
+                output.push(':');
+                write_canonical(output, value);
+                sep = ",";
+            }
+            output.push('}');
+        }
+        Node::Null(_) => {
+            output.push_str("null");
+            write_annotations(output, node, false);
+        }
+        Node::Bool(v) => {
+            output.push_str(if v.value() { "true" } else { "false" });
+            write_annotations(output, node, false);
+        }
+        Node::Number(v) => {
+            output.push_str(&canonical_number(v));
+            write_annotations(output, node, false);
+        }
+        Node::String(v) => {
+            write_string(output, v.value());
+            write_annotations(output, node, false);
+        }
+    }
+}
+
+/// Write the annotations of the node, returns the separator for the first item.
+fn write_annotations(output: &mut String, node: &Node, inner: bool) -> &'static str {
+    let annotations = match node.annotations() {
+        Some(v) => v.value().read(),
+        None => return "",
+    };
+    if annotations.is_empty() {
+        return "";
+    }
+    let mut entries: Vec<(&Key, &Node)> = annotations.iter().collect();
+    entries.sort_by(|a, b| a.0.value().cmp(b.0.value()));
+    for (i, (key, value)) in entries.into_iter().enumerate() {
+        if i > 0 || !inner {
+            output.push(' ');
+        }
+        output.push_str(key.value());
+        if !value.is_null() {
+            output.push('(');
+            write_canonical(output, value);
+            output.push(')');
+        }
+    }
+    " "
+}
+
+fn write_string(output: &mut String, value: &str) {
+    output.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\u{8}' => output.push_str("\\b"),
+            '\t' => output.push_str("\\t"),
+            '\n' => output.push_str("\\n"),
+            '\u{c}' => output.push_str("\\f"),
+            '\r' => output.push_str("\\r"),
+            c if c.is_ascii_control() => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+}
+
+fn canonical_number(number: &Number) -> String {
+    if let Some(v) = number.non_finite() {
+        let text = match v {
+            NonFinite::NaN => "nan",
+            NonFinite::Infinity => "inf",
+            NonFinite::NegInfinity => "-inf",
+        };
+        return text.to_string();
+    }
+    if let Some(v) = number.as_i128() {
+        return v.to_string();
+    }
+    if let Some(v) = number.as_u128() {
+        return v.to_string();
+    }
+    if number.repr() != NumberRepr::Float {
+        return number.to_decimal_string();
+    }
+    #[cfg(feature = "arbitrary_precision")]
+    if let Some(v) = canonical_decimal(&number.to_decimal_string()) {
+        return v;
+    }
+    canonical_float(number.as_f64())
+}
+
+fn canonical_float(value: f64) -> String {
+    // `{:e}` writes the shortest digits that read back to the same value, e.g. `1.5e-7`.
+    let scientific = format!("{:e}", value.abs());
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+    let exponent: i64 = exponent.parse().unwrap_or_default();
+    let digits = mantissa.replace('.', "");
+    let sign = if value < 0.0 { "-" } else { "" };
+    canonical_digits(sign, &digits, exponent)
+}
+
+/// Write a decimal like `-0.50e3` exactly, `None` if the exponent is out of range.
+#[cfg(feature = "arbitrary_precision")]
+fn canonical_decimal(text: &str) -> Option<String> {
+    let (sign, text) = match text.strip_prefix('-') {
+        Some(v) => ("-", v),
+        None => ("", text),
+    };
+    let (mantissa, exponent) = match text.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().ok()?),
+        None => (text, 0),
+    };
+    let (int, fract) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{int}{fract}");
+    let significant = digits.trim_start_matches('0');
+    let leading_zeros = (digits.len() - significant.len()) as i64;
+    let significant = significant.trim_end_matches('0');
+    if significant.is_empty() {
+        return Some("0".to_string());
+    }
+    let exponent = exponent.checked_add(int.len() as i64 - leading_zeros - 1)?;
+    Some(canonical_digits(sign, significant, exponent))
+}
+
+/// Write significant digits with the exponent of the first digit.
+fn canonical_digits(sign: &str, digits: &str, exponent: i64) -> String {
+    if (-7 < exponent) && (exponent < 21) {
+        // Position of the decimal point relative to the first digit.
+        let point = exponent + 1;
+        if point <= 0 {
+            format!("{sign}0.{}{digits}", "0".repeat(-point as usize))
+        } else if point as usize >= digits.len() {
+            format!(
+                "{sign}{digits}{}",
+                "0".repeat(point as usize - digits.len())
+            )
+        } else {
+            let (int, fract) = digits.split_at(point as usize);
+            format!("{sign}{int}.{fract}")
+        }
+    } else {
+        let (first, rest) = digits.split_at(1);
+        let point = if rest.is_empty() { "" } else { "." };
+        format!("{sign}{first}{point}{rest}e{exponent}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_with_options, Dialect, ParseOptions};
+
+    fn canonical(s: &str) -> String {
+        s.parse::<Node>().unwrap().to_canonical_string()
+    }
+
+    #[test]
+    fn test_canonical_numbers() {
+        assert_eq!(
+            canonical("[0, -0, +1_000, 0x1F, 0o17, 0b101, 2.0, -0.0, 1.0e3]"),
+            "[0,0,1000,31,15,5,2,0,1000]"
+        );
+        assert_eq!(
+            canonical("[1.50, .5, -3.25, 0.001, 1.0e-6, 1.5e-7, 1.0e21, 1.2e20, 12345678.9]"),
+            "[1.5,0.5,-3.25,0.001,0.000001,1.5e-7,1e21,120000000000000000000,12345678.9]"
+        );
+        assert_eq!(canonical("[nan, inf, -inf, 1.0e400]"), "[nan,inf,-inf,inf]");
+        assert_eq!(
+            canonical("340282366920938463463374607431768211455"),
+            "340282366920938463463374607431768211455"
+        );
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn test_canonical_decimals() {
+        assert_eq!(
+            canonical("[0.10000000000000000000001, 0.1, 1.500e-7, -0.0e5, 12345678901234567890.0]"),
+            "[0.10000000000000000000001,0.1,1.5e-7,0,12345678901234567890]"
+        );
+        assert_eq!(
+            canonical("[1.0000000000000000001e300, 0.00000012e-3, 100.0e18]"),
+            "[1.0000000000000000001e300,1.2e-10,100000000000000000000]"
+        );
+    }
+
+    #[test]
+    fn test_canonical_layout() {
+        let a = canonical(
+            r#"{ @b @a("x")
+  z: 'it\'s', // comment
+  "y": `line
+break`,
+  x: [ @c(1) true, null @d ],
+  "": {}, @e({ k: 1 })
+}"#,
+        );
+        let b = canonical(
+            r#"{@a(`x`) @b "":{} @e({k: 1}), x:[@c(0x1) true, null @d], y: "line\nbreak", z: "it's"}"#,
+        );
+        assert_eq!(a, b);
+        assert_eq!(
+            a,
+            r#"{@a("x") @b "":{@e({"k":1})},"x":[@c(1) true,null @d],"y":"line\nbreak","z":"it's"}"#
+        );
+        assert_eq!(
+            canonical(r#""\u0001\u007F\u00e9\t""#),
+            r#""\u0001\u007fé\t""#
+        );
+        let json = ParseOptions {
+            dialect: Dialect::Json,
+        };
+        assert_eq!(
+            parse_with_options(r#""a\/b""#, &json)
+                .into_dom()
+                .to_canonical_string(),
+            r#""a/b""#
+        );
+        assert_eq!(a.parse::<Node>().unwrap().to_canonical_string(), a);
+    }
+}
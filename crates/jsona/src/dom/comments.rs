@@ -0,0 +1,307 @@
+//! Comments attached to DOM nodes and keys.
+//!
+//! Comments are attached to the element they describe, where an element is a
+//! property (key and value), an annotation, an array item or the root value.
+//! The rules are deterministic and only depend on the layout of the source:
+//!
+//! - Leading comments are the comments on the lines directly above an element,
+//!   plus any comment on the same line right before it. The block stops at a
+//!   blank line or at the previous token, and a comment that shares its line
+//!   with a previous token belongs to that token instead.
+//! - Trailing comments are the comments after an element on the same line,
+//!   before or after its comma. A comment after the comma that is followed by
+//!   another element on the same line is a leading comment of that element.
+//!   Comments after the last annotation of a value trail the value, not the annotation.
+
+use super::node::{DomNode, Key, Node};
+use crate::syntax::{SyntaxKind, SyntaxKind::*, SyntaxNode, SyntaxToken};
+
+use rowan::{NodeOrToken, TextRange};
+use std::fmt;
+
+/// A comment in the source.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Comment {
+    syntax: SyntaxToken,
+}
+
+impl Comment {
+    pub fn syntax(&self) -> &SyntaxToken {
+        &self.syntax
+    }
+
+    pub fn text_range(&self) -> TextRange {
+        self.syntax.text_range()
+    }
+
+    /// Whether this is a `/* */` comment.
+    pub fn is_block(&self) -> bool {
+        self.syntax.kind() == BLOCK_COMMENT
+    }
+
+    /// The raw text of the comment, including the comment markers.
+    pub fn text(&self) -> &str {
+        self.syntax.text()
+    }
+
+    /// The text of the comment without the comment markers and surrounding whitespace.
+    pub fn content(&self) -> &str {
+        let text = self.text();
+        let text = if self.is_block() {
+            let text = text.strip_prefix("/*").unwrap_or(text);
+            text.strip_suffix("*/").unwrap_or(text)
+        } else {
+            text.strip_prefix("//").unwrap_or(text)
+        };
+        text.trim()
+    }
+}
+
+impl fmt::Display for Comment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.text().fmt(f)
+    }
+}
+
+impl Node {
+    /// Comments attached before this node, see the [module docs](self) for the rules.
+    ///
+    /// The comments of a property value are the comments of its property.
+    pub fn leading_comments(&self) -> Vec<Comment> {
+        self.node_syntax()
+            .and_then(NodeOrToken::as_node)
+            .and_then(node_element)
+            .map(|v| leading_comments(&v))
+            .unwrap_or_default()
+    }
+
+    /// Comments attached after this node, see the [module docs](self) for the rules.
+    pub fn trailing_comments(&self) -> Vec<Comment> {
+        self.node_syntax()
+            .and_then(NodeOrToken::as_node)
+            .and_then(node_element)
+            .map(|v| trailing_comments(&v))
+            .unwrap_or_default()
+    }
+}
+
+impl Key {
+    /// Comments attached before the property or annotation of this key.
+    pub fn leading_comments(&self) -> Vec<Comment> {
+        self.key_element()
+            .map(|v| leading_comments(&v))
+            .unwrap_or_default()
+    }
+
+    /// Comments attached after the property or annotation of this key.
+    pub fn trailing_comments(&self) -> Vec<Comment> {
+        self.key_element()
+            .map(|v| trailing_comments(&v))
+            .unwrap_or_default()
+    }
+
+    fn key_element(&self) -> Option<SyntaxNode> {
+        self.syntax()?
+            .parent()?
+            .ancestors()
+            .find(|v| matches!(v.kind(), PROPERTY | ANNOTATION_PROPERTY))
+    }
+}
+
+/// Finds the element that comments attach to for the syntax of a node.
+fn node_element(syntax: &SyntaxNode) -> Option<SyntaxNode> {
+    match syntax.kind() {
+        ANNOTATION_VALUE => return syntax.parent(),
+        ANNOTATION_PROPERTY => return Some(syntax.clone()),
+        _ => {}
+    }
+    match syntax.parent() {
+        Some(parent) if parent.kind() == PROPERTY => Some(parent),
+        Some(parent) if parent.kind() == ANNOTATION_VALUE => parent.parent(),
+        _ => Some(syntax.clone()),
+    }
+}
+
+fn leading_comments(element: &SyntaxNode) -> Vec<Comment> {
+    let first = match element
+        .descendants_with_tokens()
+        .filter_map(NodeOrToken::into_token)
+        .find(|v| !v.kind().is_ws_or_comment())
+    {
+        Some(v) => v,
+        None => return vec![],
+    };
+    let mut comments = vec![];
+    let mut pending = vec![];
+    let mut own_line = true;
+    // Line breaks since the last comment, a blank line may hold whitespace.
+    let mut newlines = 0;
+    let mut token = first.prev_token();
+    while let Some(current) = token {
+        token = current.prev_token();
+        match current.kind() {
+            WHITESPACE => {}
+            NEWLINE => {
+                own_line = false;
+                comments.append(&mut pending);
+                newlines += current.text().matches('\n').count();
+                if newlines > 1 {
+                    break;
+                }
+            }
+            kind if kind.is_comment() => {
+                newlines = 0;
+                if own_line {
+                    comments.push(Comment { syntax: current });
+                } else {
+                    pending.push(Comment { syntax: current });
+                }
+            }
+            _ => {
+                pending.clear();
+                break;
+            }
+        }
+    }
+    comments.append(&mut pending);
+    comments.reverse();
+    comments
+}
+
+fn trailing_comments(element: &SyntaxNode) -> Vec<Comment> {
+    let last = match last_token(element) {
+        Some(v) => v,
+        None => return vec![],
+    };
+    if element.kind() == ANNOTATION_PROPERTY {
+        let value = element
+            .ancestors()
+            .find(|v| v.kind() == VALUE)
+            .and_then(|v| node_element(&v));
+        if value.and_then(|v| last_token(&v)).as_ref() == Some(&last) {
+            return vec![];
+        }
+    }
+    let mut comments = vec![];
+    let mut after_comma: Option<Vec<Comment>> = None;
+    let mut token = last.next_token();
+    while let Some(current) = token {
+        token = current.next_token();
+        match current.kind() {
+            WHITESPACE => {}
+            NEWLINE => break,
+            COMMA if after_comma.is_none() => after_comma = Some(vec![]),
+            kind if kind.is_comment() => match after_comma.as_mut() {
+                Some(after_comma) => after_comma.push(Comment { syntax: current }),
+                None => comments.push(Comment { syntax: current }),
+            },
+            kind => {
+                if !is_closing(kind) {
+                    after_comma = None;
+                }
+                break;
+            }
+        }
+    }
+    comments.extend(after_comma.unwrap_or_default());
+    comments
+}
+
+/// The last token of an element that is not whitespace, a comment or a comma.
+fn last_token(element: &SyntaxNode) -> Option<SyntaxToken> {
+    element
+        .descendants_with_tokens()
+        .filter_map(NodeOrToken::into_token)
+        .filter(|v| !v.kind().is_ws_or_comment() && v.kind() != COMMA)
+        .last()
+}
+
+fn is_closing(kind: SyntaxKind) -> bool {
+    matches!(kind, BRACE_END | BRACKET_END | PARENTHESES_END)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::{KeyOrIndex, Keys};
+
+    fn contents(comments: Vec<Comment>) -> Vec<String> {
+        comments.iter().map(|v| v.content().to_string()).collect()
+    }
+
+    #[test]
+    fn test_comments() {
+        let text = r#"// root
+{ // brace
+  // detached
+
+  // a1
+  /* a2 */
+  a: 1, // a3
+  b: /* b1 */ 2 /* b2 */, /* b3 */
+  c: [1 /* c1 */, /* c2 */ 2, 3 /* c3 */], // c4
+  d: 4, @x(5) // d1
+}
+"#;
+        let node: Node = text.parse().unwrap();
+        let comments_at = |path: &str| {
+            let keys: Keys = path.parse().unwrap();
+            let node = node.path(&keys).unwrap();
+            (
+                contents(node.leading_comments()),
+                contents(node.trailing_comments()),
+            )
+        };
+        assert_eq!(
+            (
+                contents(node.leading_comments()),
+                contents(node.trailing_comments())
+            ),
+            (vec!["root".into()], vec![])
+        );
+        assert_eq!(
+            comments_at("a"),
+            (vec!["a1".into(), "a2".into()], vec!["a3".into()])
+        );
+        assert_eq!(comments_at("b"), (vec![], vec!["b2".into(), "b3".into()]));
+        assert_eq!(comments_at("c[0]"), (vec![], vec!["c1".into()]));
+        assert_eq!(comments_at("c[1]"), (vec!["c2".into()], vec![]));
+        assert_eq!(comments_at("c[2]"), (vec![], vec!["c3".into()]));
+        assert_eq!(comments_at("c"), (vec![], vec!["c4".into()]));
+        assert_eq!(comments_at("d"), (vec![], vec!["d1".into()]));
+        assert_eq!(comments_at("d@x"), (vec![], vec![]));
+
+        let object = node.as_object().unwrap();
+        let map = object.value().read();
+        let (key, _) = map.iter().next().unwrap();
+        assert_eq!(contents(key.leading_comments()), vec!["a1", "a2"]);
+        assert_eq!(contents(key.trailing_comments()), vec!["a3"]);
+        let d = node.get(&KeyOrIndex::property("d")).unwrap();
+        let annotations = d.annotations().unwrap().value().read();
+        let (key, _) = annotations.iter().next().unwrap();
+        assert!(key.trailing_comments().is_empty());
+
+        let node: Node = "{\n  // detached\n  \n  // a1\n  a: 1 @x /* x1 */ @y, // a2\n}"
+            .parse()
+            .unwrap();
+        let a = node.get(&KeyOrIndex::property("a")).unwrap();
+        assert_eq!(contents(a.leading_comments()), vec!["a1"]);
+        assert_eq!(contents(a.trailing_comments()), vec!["a2"]);
+        let annotations = a.annotations().unwrap().value().read();
+        let trailing: Vec<_> = annotations
+            .iter()
+            .map(|(key, _)| contents(key.trailing_comments()))
+            .collect();
+        assert_eq!(trailing, [vec!["x1".to_string()], vec![]]);
+    }
+
+    #[test]
+    fn test_comment_content() {
+        let node: Node = "/** doc\n */\n1".parse().unwrap();
+        let comments = node.leading_comments();
+        assert_eq!(comments.len(), 1);
+        assert!(comments[0].is_block());
+        assert_eq!(comments[0].text(), "/** doc\n */");
+        assert_eq!(comments[0].content(), "* doc");
+    }
+}
@@ -0,0 +1,351 @@
+//! Structural differences between two DOM trees.
+//!
+//! Values are compared by their data only, so the layout, comments, quotes of
+//! strings and representations of numbers (`0x10` and `16`) do not count as changes.
+//! Annotations are compared like values, with paths ending in the annotation key.
+//!
+//! ```
+//! use jsona::dom::{diff, DiffOptions, Node};
+//!
+//! let old: Node = "{ a: 1, b: 'x', c: [1] }".parse().unwrap();
+//! let new: Node = "{ @deprecated a: 0x1, b: \"y\", c: [1, 2] }".parse().unwrap();
+//!
+//! let changes: Vec<String> = diff(&old, &new, &DiffOptions::default())
+//!     .iter()
+//!     .map(|v| v.to_string())
+//!     .collect();
+//! assert_eq!(changes, ["~ .b", "+ .c[1]", "+ @deprecated"]);
+//! ```
+
+use super::node::{DomNode, Key, Node, Number};
+use super::Keys;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// How array items of the two trees are paired with each other.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ArrayMatch {
+    /// Items are paired by their position.
+    #[default]
+    Index,
+    /// Object items are paired by the value of the given property, so reordered
+    /// items are not reported. Items without the property are paired by position.
+    Key(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffOptions {
+    pub array_match: ArrayMatch,
+}
+
+/// A difference between two trees.
+///
+/// Paths of removed values refer to the old tree, all other paths refer to the new tree.
+#[derive(Debug, Clone)]
+pub enum Change {
+    Added { keys: Keys, new: Node },
+    Removed { keys: Keys, old: Node },
+    Changed { keys: Keys, old: Node, new: Node },
+}
+
+impl Change {
+    pub fn keys(&self) -> &Keys {
+        match self {
+            Change::Added { keys, .. }
+            | Change::Removed { keys, .. }
+            | Change::Changed { keys, .. } => keys,
+        }
+    }
+
+    pub fn old_value(&self) -> Option<&Node> {
+        match self {
+            Change::Added { .. } => None,
+            Change::Removed { old, .. } | Change::Changed { old, .. } => Some(old),
+        }
+    }
+
+    pub fn new_value(&self) -> Option<&Node> {
+        match self {
+            Change::Removed { .. } => None,
+            Change::Added { new, .. } | Change::Changed { new, .. } => Some(new),
+        }
+    }
+
+    /// The kind of the change, one of `added`, `removed` or `changed`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Change::Added { .. } => "added",
+            Change::Removed { .. } => "removed",
+            Change::Changed { .. } => "changed",
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = match self {
+            Change::Added { .. } => '+',
+            Change::Removed { .. } => '-',
+            Change::Changed { .. } => '~',
+        };
+        match self.keys().is_empty() {
+            true => write!(f, "{sign} ."),
+            false => write!(f, "{sign} {}", self.keys()),
+        }
+    }
+}
+
+/// Report the differences between two trees.
+pub fn diff(old: &Node, new: &Node, options: &DiffOptions) -> Vec<Change> {
+    let mut changes = vec![];
+    diff_node(Keys::default(), old, new, options, &mut changes);
+    changes
+}
+
+impl Node {
+    /// Report the differences from this tree to the other, see [diff].
+    pub fn diff(&self, other: &Node, options: &DiffOptions) -> Vec<Change> {
+        diff(self, other, options)
+    }
+}
+
+fn diff_node(keys: Keys, old: &Node, new: &Node, options: &DiffOptions, changes: &mut Vec<Change>) {
+    match (old, new) {
+        (Node::Object(old_obj), Node::Object(new_obj)) => {
+            let old_map = old_obj.value().read();
+            let new_map = new_obj.value().read();
+            for (key, old_value) in old_map.iter() {
+                let keys = keys.join(key.clone());
+                match new_obj.get(key) {
+                    Some(new_value) => diff_node(keys, old_value, &new_value, options, changes),
+                    None => changes.push(Change::Removed {
+                        keys,
+                        old: old_value.clone(),
+                    }),
+                }
+            }
+            for (key, new_value) in new_map.iter() {
+                if old_obj.get(key).is_none() {
+                    changes.push(Change::Added {
+                        keys: keys.join(key.clone()),
+                        new: new_value.clone(),
+                    });
+                }
+            }
+        }
+        (Node::Array(old_arr), Node::Array(new_arr)) => {
+            let old_items = old_arr.value().read();
+            let new_items = new_arr.value().read();
+            diff_items(
+                &keys,
+                old_items.as_ref(),
+                new_items.as_ref(),
+                options,
+                changes,
+            );
+        }
+        _ => {
+            if !scalar_eq(old, new) {
+                changes.push(Change::Changed {
+                    keys: keys.clone(),
+                    old: old.clone(),
+                    new: new.clone(),
+                });
+            }
+        }
+    }
+    diff_annotations(&keys, old, new, options, changes);
+}
+
+fn diff_items(
+    keys: &Keys,
+    old: &[Node],
+    new: &[Node],
+    options: &DiffOptions,
+    changes: &mut Vec<Change>,
+) {
+    let field = match &options.array_match {
+        ArrayMatch::Index => None,
+        ArrayMatch::Key(field) => Some(Key::property(field.as_str())),
+    };
+    let identity = |node: &Node| {
+        let field = field.as_ref()?;
+        let value = node.as_object()?.get(field)?;
+        value.is_scalar().then(|| value.to_plain_json().to_string())
+    };
+
+    let mut old_by_identity: HashMap<String, usize> = HashMap::new();
+    let mut old_unkeyed = vec![];
+    for (i, item) in old.iter().enumerate() {
+        match identity(item) {
+            Some(id) if !old_by_identity.contains_key(&id) => {
+                old_by_identity.insert(id, i);
+            }
+            _ => old_unkeyed.push(i),
+        }
+    }
+
+    let mut old_matched = vec![false; old.len()];
+    let mut old_unkeyed = old_unkeyed.into_iter();
+    let mut pairs = vec![];
+    for (i, item) in new.iter().enumerate() {
+        let old_index = match identity(item) {
+            Some(id) => old_by_identity.remove(&id),
+            None => old_unkeyed.next(),
+        };
+        if let Some(j) = old_index {
+            old_matched[j] = true;
+        }
+        pairs.push((i, old_index));
+    }
+
+    for (j, item) in old.iter().enumerate() {
+        if !old_matched[j] {
+            changes.push(Change::Removed {
+                keys: keys.join(j),
+                old: item.clone(),
+            });
+        }
+    }
+    for (i, old_index) in pairs {
+        match old_index {
+            Some(j) => diff_node(keys.join(i), &old[j], &new[i], options, changes),
+            None => changes.push(Change::Added {
+                keys: keys.join(i),
+                new: new[i].clone(),
+            }),
+        }
+    }
+}
+
+fn diff_annotations(
+    keys: &Keys,
+    old: &Node,
+    new: &Node,
+    options: &DiffOptions,
+    changes: &mut Vec<Change>,
+) {
+    let old_annotations = old.annotations().map(|v| v.value().read());
+    let new_annotations = new.annotations().map(|v| v.value().read());
+    let old_iter = old_annotations.iter().flat_map(|v| v.iter());
+    let new_iter = new_annotations.iter().flat_map(|v| v.iter());
+    for (key, old_value) in old_iter {
+        let keys = keys.join(key.clone());
+        match new.annotations().and_then(|v| v.get(key)) {
+            Some(new_value) => diff_node(keys, old_value, &new_value, options, changes),
+            None => changes.push(Change::Removed {
+                keys,
+                old: old_value.clone(),
+            }),
+        }
+    }
+    for (key, new_value) in new_iter {
+        if old.annotations().and_then(|v| v.get(key)).is_none() {
+            changes.push(Change::Added {
+                keys: keys.join(key.clone()),
+                new: new_value.clone(),
+            });
+        }
+    }
+}
+
+/// Compare the data of two nodes, ignoring annotations and children.
+fn scalar_eq(old: &Node, new: &Node) -> bool {
+    match (old, new) {
+        (Node::Null(_), Node::Null(_)) => true,
+        (Node::Bool(a), Node::Bool(b)) => a.value() == b.value(),
+        (Node::String(a), Node::String(b)) => a.value() == b.value(),
+        (Node::Number(a), Node::Number(b)) => number_eq(a, b),
+        _ => false,
+    }
+}
+
+fn number_eq(a: &Number, b: &Number) -> bool {
+    if let (Some(a), Some(b)) = (a.as_i128(), b.as_i128()) {
+        return a == b;
+    }
+    if let (Some(a), Some(b)) = (a.as_u128(), b.as_u128()) {
+        return a == b;
+    }
+    let (a, b) = (a.as_f64(), b.as_f64());
+    a == b || (a.is_nan() && b.is_nan())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changes(old: &str, new: &str, options: &DiffOptions) -> Vec<String> {
+        let old: Node = old.parse().unwrap();
+        let new: Node = new.parse().unwrap();
+        diff(&old, &new, options)
+            .iter()
+            .map(|v| match v {
+                Change::Changed { old, new, .. } => {
+                    format!("{v} {} -> {}", old.to_plain_json(), new.to_plain_json())
+                }
+                _ => v.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_diff() {
+        let options = DiffOptions::default();
+        assert!(changes(
+            "{ @foo(`bar`) a: 16, b: 'x', c: [1.0, nan], d: null }",
+            "{\n  @foo(\"bar\") // comment\n  a: 0x10,\n  b: \"x\",\n  c: [1, nan],\n  d: null,\n}",
+            &options
+        )
+        .is_empty());
+        assert_eq!(
+            changes(
+                "{ a: 1, b: { c: true }, d: [1, 2], @x(1) @y }",
+                "{ a: '1', b: { c: false, e: null }, d: [1], @x(2) @z }",
+                &options
+            ),
+            [
+                "~ .a 1 -> \"1\"",
+                "~ .b.c true -> false",
+                "+ .b.e",
+                "- .d[1]",
+                "~ .d@x 1 -> 2",
+                "- .d@y",
+                "+ .d@z",
+            ]
+        );
+        assert_eq!(changes("1 @a", "2", &options), ["~ . 1 -> 2", "- @a"]);
+        assert_eq!(
+            changes("{ a: [@b 1] }", "{ a: 1 }", &options),
+            ["~ .a [1] -> 1", "- .a@b"]
+        );
+    }
+
+    #[test]
+    fn test_diff_array_by_key() {
+        let old = "[{ id: 1, v: 'a' }, { id: 2, v: 'b' }, { id: 3 }, 'x']";
+        let new = "[{ id: 2, v: 'c' }, { id: 1, v: 'a' }, 'x', { id: 4 }]";
+        assert_eq!(
+            changes(old, new, &DiffOptions::default()),
+            [
+                "~ [0].id 1 -> 2",
+                "~ [0].v \"a\" -> \"c\"",
+                "~ [1].id 2 -> 1",
+                "~ [1].v \"b\" -> \"a\"",
+                "~ [2] {\"id\":3} -> \"x\"",
+                "~ [3] \"x\" -> {\"id\":4}",
+            ]
+        );
+        let options = DiffOptions {
+            array_match: ArrayMatch::Key("id".into()),
+        };
+        assert_eq!(
+            changes(old, new, &options),
+            ["- [2]", "~ [0].v \"b\" -> \"c\"", "+ [3]"]
+        );
+    }
+}
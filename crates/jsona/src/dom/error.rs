@@ -1,3 +1,4 @@
+use super::keys::Keys;
 use super::node::Key;
 use crate::syntax::SyntaxElement;
 use thiserror::Error;
@@ -12,4 +13,28 @@ pub enum DomError {
     InvalidNumber { syntax: SyntaxElement },
     #[error("conflicting keys")]
     ConflictingKeys { key: Key, other_key: Key },
+    #[error("duplicate keys")]
+    DuplicateKeys { key: Key, other_key: Key },
+}
+
+impl DomError {
+    /// Warnings are reported by [`Node::warnings`](super::Node::warnings) and
+    /// do not make the DOM invalid.
+    pub fn is_warning(&self) -> bool {
+        matches!(self, DomError::DuplicateKeys { .. })
+    }
+}
+
+#[derive(Debug, Clone, Error)]
+pub enum PointerError {
+    #[error("the pointer must be empty or start with `/`")]
+    MissingSlash,
+    #[error("invalid escape sequence in `{segment}`")]
+    InvalidEscape { segment: String },
+}
+
+#[derive(Debug, Clone, Error)]
+pub enum ExportError {
+    #[error("the number at {keys} is not finite")]
+    NonFiniteNumber { keys: Keys },
 }
@@ -1,29 +1,80 @@
+use indexmap::map::MutableKeys;
+use once_cell::unsync::OnceCell;
 use rowan::NodeOrToken;
+use serde::{Deserialize, Serialize};
+use serde_json::Number as JsonNumber;
 
 use super::{
     error::DomError,
     node::{
-        Annotations, AnnotationsInner, ArrayInner, BoolInner, Key, KeyInner, KeyKind, Map, Node,
-        NullInner, NumberInner, NumberRepr, ObjectInner, StringInner,
+        annotation_key_value, Annotations, AnnotationsInner, ArrayInner, BoolInner, DomNode, Key,
+        KeyInner, KeyKind, Map, Node, NonFinite, NullInner, NumberInner, NumberRepr, ObjectInner,
+        StringInner,
     },
     query_keys::QueryKey,
 };
-use serde_json::Number as JsonNumber;
 
 use crate::{
     dom::KeyOrIndex,
+    parser::Dialect,
     syntax::{SyntaxElement, SyntaxKind::*},
-    util::shared::Shared,
+    util::{shared::Shared, unquote_json5},
 };
 
+/// How to handle a key that appears more than once in the same object or annotations.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DuplicateKeyPolicy {
+    /// Keep the last value and report [`DomError::ConflictingKeys`].
+    #[default]
+    Error,
+    /// Keep the first value.
+    FirstWins,
+    /// Keep the last value.
+    LastWins,
+    /// Collect all values into an array.
+    Collect,
+}
+
+impl DuplicateKeyPolicy {
+    pub fn is_error(self) -> bool {
+        self == Self::Error
+    }
+}
+
+/// Options for building the DOM from syntax.
+///
+/// Unless the policy is [`DuplicateKeyPolicy::Error`], duplicate keys are
+/// reported as [`DomError::DuplicateKeys`] warnings, see [`Node::warnings`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DomOptions {
+    /// Policy for duplicate property keys.
+    #[serde(default)]
+    pub properties: DuplicateKeyPolicy,
+    /// Policy for duplicate annotation keys.
+    #[serde(default)]
+    pub annotations: DuplicateKeyPolicy,
+}
+
 pub fn from_syntax(root: SyntaxElement) -> Node {
+    from_syntax_with_options(root, &DomOptions::default())
+}
+
+pub fn from_syntax_with_options(root: SyntaxElement, options: &DomOptions) -> Node {
+    from_syntax_in(root, options, Dialect::Jsona)
+}
+
+/// Build the DOM of a tree parsed in the given dialect,
+/// the dialect decides how strings are unescaped.
+pub(crate) fn from_syntax_in(root: SyntaxElement, options: &DomOptions, dialect: Dialect) -> Node {
     assert!(root.kind() == VALUE);
-    let annotations = annotations_from_syntax(root.clone());
+    let annotations = annotations_from_syntax(root.clone(), options);
     if let Some(syntax) = first_value_child(&root) {
         match syntax.kind() {
-            SCALAR => scalar_from_syntax(root, syntax, annotations),
-            ARRAY => array_from_syntax(root, syntax, annotations),
-            OBJECT => object_from_syntax(root, syntax, annotations),
+            SCALAR => scalar_from_syntax(root, syntax, annotations, dialect),
+            ARRAY => array_from_syntax(root, syntax, annotations, options, dialect),
+            OBJECT => object_from_syntax(root, syntax, annotations, options, dialect),
             _ => null_from_syntax(root, annotations, true),
         }
     } else {
@@ -51,7 +102,7 @@ pub(crate) fn query_keys_from_syntax(
                         let key = KeyInner {
                             errors: Shared::default(),
                             syntax: Some(child.clone().into()),
-                            value: child.to_string().into(),
+                            value: annotation_key_value(child.text()).into(),
                             kind: KeyKind::Annotation,
                         }
                         .into();
@@ -120,7 +171,7 @@ pub(crate) fn keys_from_syntax(
                         let key = KeyInner {
                             errors: Shared::default(),
                             syntax: Some(child.clone().into()),
-                            value: child.to_string().into(),
+                            value: annotation_key_value(child.text()).into(),
                             kind: KeyKind::Annotation,
                         }
                         .into();
@@ -157,14 +208,18 @@ pub(crate) fn keys_from_syntax(
 }
 
 pub(crate) fn key_from_syntax(syntax: SyntaxElement) -> Key {
+    key_from_syntax_in(syntax, Dialect::Jsona)
+}
+
+fn key_from_syntax_in(syntax: SyntaxElement, dialect: Dialect) -> Key {
     assert!(syntax.kind() == KEY);
     if let Some(child) =
         first_value_child(&syntax).and_then(|v| if v.kind().is_key() { Some(v) } else { None })
     {
         KeyInner {
             errors: Shared::default(),
+            value: string_value(&child, dialect),
             syntax: Some(child),
-            value: Default::default(),
             kind: KeyKind::Property,
         }
         .into()
@@ -185,6 +240,7 @@ fn scalar_from_syntax(
     root: SyntaxElement,
     syntax: SyntaxElement,
     annotations: Option<Annotations>,
+    dialect: Dialect,
 ) -> Node {
     assert!(syntax.kind() == SCALAR);
     let errors: Vec<DomError> = Default::default();
@@ -214,6 +270,7 @@ fn scalar_from_syntax(
             node_syntax: Some(root),
             annotations,
             value: Default::default(),
+            non_finite: None,
             repr: NumberRepr::Dec,
         }
         .into_node(),
@@ -223,6 +280,7 @@ fn scalar_from_syntax(
             node_syntax: Some(root),
             annotations,
             value: Default::default(),
+            non_finite: None,
             repr: NumberRepr::Bin,
         }
         .into_node(),
@@ -232,6 +290,7 @@ fn scalar_from_syntax(
             node_syntax: Some(root),
             annotations,
             value: Default::default(),
+            non_finite: None,
             repr: NumberRepr::Hex,
         }
         .into_node(),
@@ -241,43 +300,40 @@ fn scalar_from_syntax(
             node_syntax: Some(root),
             annotations,
             value: Default::default(),
+            non_finite: None,
             repr: NumberRepr::Oct,
         }
         .into_node(),
-        FLOAT => {
-            if let Some(v) = syntax
-                .to_string()
-                .parse::<f64>()
-                .ok()
-                .and_then(JsonNumber::from_f64)
-            {
-                NumberInner {
-                    errors: errors.into(),
-                    syntax: Some(syntax),
-                    node_syntax: Some(root),
-                    annotations,
-                    value: v.into(),
-                    repr: NumberRepr::Float,
-                }
-                .into_node()
-            } else {
-                null_from_syntax(root, annotations, true)
+        FLOAT => match syntax.to_string().replace('_', "").parse::<f64>() {
+            Ok(value) => NumberInner {
+                errors: errors.into(),
+                syntax: Some(syntax),
+                node_syntax: Some(root),
+                annotations,
+                value: match NonFinite::from_f64(value) {
+                    Some(_) => JsonNumber::from(0).into(),
+                    None => Default::default(),
+                },
+                non_finite: NonFinite::from_f64(value),
+                repr: NumberRepr::Float,
             }
-        }
+            .into_node(),
+            Err(_) => null_from_syntax(root, annotations, true),
+        },
         SINGLE_QUOTE => StringInner {
             errors: errors.into(),
+            value: string_value(&syntax, dialect),
             syntax: Some(syntax),
             node_syntax: Some(root),
             annotations,
-            value: Default::default(),
         }
         .into_node(),
         DOUBLE_QUOTE => StringInner {
             errors: errors.into(),
+            value: string_value(&syntax, dialect),
             syntax: Some(syntax),
             node_syntax: Some(root),
             annotations,
-            value: Default::default(),
         }
         .into_node(),
         BACKTICK_QUOTE => StringInner {
@@ -296,13 +352,15 @@ fn array_from_syntax(
     root: SyntaxElement,
     syntax: SyntaxElement,
     annotations: Option<Annotations>,
+    options: &DomOptions,
+    dialect: Dialect,
 ) -> Node {
     assert!(syntax.kind() == ARRAY);
     let syntax = syntax.into_node().unwrap();
     let items: Vec<Node> = syntax
         .children()
         .filter(|v| v.kind() == VALUE)
-        .map(|syntax| from_syntax(syntax.into()))
+        .map(|syntax| from_syntax_in(syntax.into(), options, dialect))
         .collect();
 
     ArrayInner {
@@ -319,13 +377,15 @@ fn object_from_syntax(
     root: SyntaxElement,
     syntax: SyntaxElement,
     annotations: Option<Annotations>,
+    options: &DomOptions,
+    dialect: Dialect,
 ) -> Node {
     assert!(syntax.kind() == OBJECT);
     let syntax = syntax.into_node().unwrap();
     let mut errors = Vec::new();
     let mut properties = Map::default();
     for child in syntax.children().filter(|v| v.kind() == PROPERTY) {
-        property_from_syntax(child.into(), &mut properties, &mut errors)
+        property_from_syntax(child.into(), &mut properties, &mut errors, options, dialect)
     }
     ObjectInner {
         errors: errors.into(),
@@ -337,11 +397,17 @@ fn object_from_syntax(
     .into_node()
 }
 
-fn property_from_syntax(syntax: SyntaxElement, props: &mut Map, errors: &mut Vec<DomError>) {
+fn property_from_syntax(
+    syntax: SyntaxElement,
+    props: &mut Map,
+    errors: &mut Vec<DomError>,
+    options: &DomOptions,
+    dialect: Dialect,
+) {
     assert!(syntax.kind() == PROPERTY);
     let syntax = syntax.into_node().unwrap();
     let key = match syntax.children().find(|v| v.kind() == KEY) {
-        Some(key) => key_from_syntax(key.into()),
+        Some(key) => key_from_syntax_in(key.into(), dialect),
         None => {
             errors.push(DomError::InvalidNode {
                 syntax: syntax.into(),
@@ -350,7 +416,7 @@ fn property_from_syntax(syntax: SyntaxElement, props: &mut Map, errors: &mut Vec
         }
     };
     let value = match syntax.children().find(|v| v.kind() == VALUE) {
-        Some(value) => from_syntax(value.into()),
+        Some(value) => from_syntax_in(value.into(), options, dialect),
         None => {
             errors.push(DomError::InvalidNode {
                 syntax: syntax.clone().into(),
@@ -358,10 +424,17 @@ fn property_from_syntax(syntax: SyntaxElement, props: &mut Map, errors: &mut Vec
             NullInner::default().into_node()
         }
     };
-    add_to_map(props, errors, key, value, Some(syntax.into()));
+    add_to_map(
+        props,
+        errors,
+        key,
+        value,
+        Some(syntax.into()),
+        options.properties,
+    );
 }
 
-fn annotations_from_syntax(syntax: SyntaxElement) -> Option<Annotations> {
+fn annotations_from_syntax(syntax: SyntaxElement, options: &DomOptions) -> Option<Annotations> {
     assert!(syntax.kind() == VALUE);
     let syntax = syntax.into_node()?;
 
@@ -377,20 +450,20 @@ fn annotations_from_syntax(syntax: SyntaxElement) -> Option<Annotations> {
         (None, None) => return None,
         (None, Some(inner_annotations)) => {
             for child in inner_annotations.children() {
-                annotation_from_syntax(child.into(), &mut map, &mut errors);
+                annotation_from_syntax(child.into(), &mut map, &mut errors, options);
             }
         }
         (Some(outer_annotations), None) => {
             for child in outer_annotations.children() {
-                annotation_from_syntax(child.into(), &mut map, &mut errors);
+                annotation_from_syntax(child.into(), &mut map, &mut errors, options);
             }
         }
         (Some(outer_annotations), Some(inner_annotations)) => {
             for child in inner_annotations.children() {
-                annotation_from_syntax(child.into(), &mut map, &mut errors);
+                annotation_from_syntax(child.into(), &mut map, &mut errors, options);
             }
             for child in outer_annotations.children() {
-                annotation_from_syntax(child.into(), &mut map, &mut errors);
+                annotation_from_syntax(child.into(), &mut map, &mut errors, options);
             }
         }
     };
@@ -403,7 +476,12 @@ fn annotations_from_syntax(syntax: SyntaxElement) -> Option<Annotations> {
     )
 }
 
-fn annotation_from_syntax(syntax: SyntaxElement, map: &mut Map, errors: &mut Vec<DomError>) {
+fn annotation_from_syntax(
+    syntax: SyntaxElement,
+    map: &mut Map,
+    errors: &mut Vec<DomError>,
+    options: &DomOptions,
+) {
     assert!(syntax.kind() == ANNOTATION_PROPERTY);
     let syntax = match syntax.into_node() {
         Some(v) => v,
@@ -429,7 +507,7 @@ fn annotation_from_syntax(syntax: SyntaxElement, map: &mut Map, errors: &mut Vec
     };
     let value = match syntax.children().find(|v| v.kind() == ANNOTATION_VALUE) {
         Some(anno_value) => match anno_value.children().find(|v| v.kind() == VALUE) {
-            Some(value) => from_syntax(value.into()),
+            Some(value) => from_syntax_with_options(value.into(), options),
             None => NullInner {
                 node_syntax: Some(anno_value.into()),
                 ..Default::default()
@@ -438,7 +516,28 @@ fn annotation_from_syntax(syntax: SyntaxElement, map: &mut Map, errors: &mut Vec
         },
         None => NullInner::default().into_node(),
     };
-    add_to_map(map, errors, key, value, Some(syntax.into()));
+    add_to_map(
+        map,
+        errors,
+        key,
+        value,
+        Some(syntax.into()),
+        options.annotations,
+    );
+}
+
+/// The value of a string token in JSON5,
+/// where a line continuation keeps the indentation of the next line.
+///
+/// Left empty in the other dialects and on invalid escapes, for the node to unescape on first use.
+fn string_value(syntax: &SyntaxElement, dialect: Dialect) -> OnceCell<String> {
+    let value = OnceCell::new();
+    if dialect == Dialect::Json5 && matches!(syntax.kind(), SINGLE_QUOTE | DOUBLE_QUOTE) {
+        if let Some(Ok(v)) = syntax.as_token().map(|t| unquote_json5(t.text())) {
+            let _ = value.set(v);
+        }
+    }
+    value
 }
 
 fn null_from_syntax(syntax: SyntaxElement, annotations: Option<Annotations>, error: bool) -> Node {
@@ -464,20 +563,115 @@ fn first_value_child(syntax: &SyntaxElement) -> Option<SyntaxElement> {
         .find(|v| !v.kind().is_ws_or_comment())
 }
 
-/// Add an prop and also collect errors on conflicts.
+/// Add an prop and also handle conflicts according to the policy.
 fn add_to_map(
     map: &mut Map,
     errors: &mut Vec<DomError>,
     key: Key,
     node: Node,
     syntax: Option<SyntaxElement>,
+    policy: DuplicateKeyPolicy,
 ) {
-    if let Some((existing_key, _)) = map.value.get_key_value(&key) {
+    let (existing_key, existing) = match map.value.get_full_mut2(&key) {
+        Some((_, existing_key, existing)) => (existing_key, existing),
+        None => {
+            map.add(key, node, syntax);
+            return;
+        }
+    };
+    if policy.is_error() {
         errors.push(DomError::ConflictingKeys {
             key: key.clone(),
             other_key: existing_key.clone(),
+        });
+        map.add(key, node, syntax);
+        return;
+    }
+    errors.push(DomError::DuplicateKeys {
+        key: key.clone(),
+        other_key: existing_key.clone(),
+    });
+    match policy {
+        DuplicateKeyPolicy::FirstWins | DuplicateKeyPolicy::Error => {}
+        DuplicateKeyPolicy::LastWins => {
+            *existing_key = key;
+            *existing = (node, syntax);
+        }
+        DuplicateKeyPolicy::Collect => match &mut existing.0 {
+            // Arrays from syntax always have a node syntax, so this one was collected before.
+            Node::Array(array) if array.node_syntax().is_none() => {
+                array.value().update(|items| items.push(node));
+            }
+            existing_node => {
+                let items = vec![existing_node.clone(), node];
+                *existing_node = ArrayInner {
+                    errors: Default::default(),
+                    node_syntax: None,
+                    syntax: None,
+                    annotations: None,
+                    items: items.into(),
+                }
+                .into_node();
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+    use rowan::TextRange;
+
+    fn build(text: &str, properties: DuplicateKeyPolicy, annotations: DuplicateKeyPolicy) -> Node {
+        parse(text).into_dom_with_options(&DomOptions {
+            properties,
+            annotations,
         })
     }
 
-    map.add(key, node, syntax);
+    #[test]
+    fn test_duplicate_key_policy() {
+        let text = "{ a: 1, b: 2, a: 3, a: 4 } @x(1) @x(2)";
+        let to_string = |node: &Node| {
+            let x = node.get(&KeyOrIndex::annotation("@x")).unwrap();
+            format!("{}@x({})", node.to_plain_json(), x.to_plain_json())
+        };
+
+        let node = build(text, DuplicateKeyPolicy::Error, DuplicateKeyPolicy::Error);
+        assert_eq!(node.validate().unwrap_err().count(), 3);
+        assert!(node.warnings().is_empty());
+        assert!(!node.is_valid());
+
+        let node = build(
+            text,
+            DuplicateKeyPolicy::FirstWins,
+            DuplicateKeyPolicy::LastWins,
+        );
+        assert!(node.is_valid());
+        assert_eq!(node.warnings().len(), 3);
+        assert_eq!(to_string(&node), r#"{"a":1,"b":2}@x(2)"#);
+
+        let node = build(
+            text,
+            DuplicateKeyPolicy::LastWins,
+            DuplicateKeyPolicy::Error,
+        );
+        assert!(!node.is_valid());
+        assert_eq!(node.warnings().len(), 2);
+        assert_eq!(node.validate().unwrap_err().count(), 1);
+        let object = node.as_object().unwrap();
+        let map = object.value().read();
+        let (key, _) = map.iter().next().unwrap();
+        assert_eq!(key.text_range(), Some(TextRange::new(20.into(), 21.into())));
+        assert_eq!(to_string(&node), r#"{"a":4,"b":2}@x(2)"#);
+
+        let node = build(
+            text,
+            DuplicateKeyPolicy::Collect,
+            DuplicateKeyPolicy::Collect,
+        );
+        assert!(node.is_valid());
+        assert_eq!(to_string(&node), r#"{"a":[1,3,4],"b":2}@x([1,2])"#);
+    }
 }
@@ -1,6 +1,7 @@
 use crate::parser::Parser;
 use crate::util::mapper;
 
+use super::error::PointerError;
 use super::from_syntax::keys_from_syntax;
 use super::node::Key;
 use super::Node;
@@ -34,9 +35,9 @@ impl core::fmt::Display for KeyOrIndex {
             KeyOrIndex::Index(v) => write!(f, "[{}]", v),
             KeyOrIndex::Key(v) => {
                 if v.is_property() {
-                    write!(f, ".{}", v)
+                    write!(f, ".{}", v.to_keys_string())
                 } else {
-                    write!(f, "{}", v)
+                    write!(f, "{}", v.to_keys_string())
                 }
             }
         }
@@ -209,7 +210,7 @@ impl Keys {
         }
         let (left, right) = self.keys.split_at(1);
         Some((
-            left.get(0).cloned().unwrap(),
+            left.first().cloned().unwrap(),
             Self::new(right.iter().cloned()),
         ))
     }
@@ -230,6 +231,49 @@ impl Keys {
         }
     }
 
+    /// Convert to a [RFC 6901](https://www.rfc-editor.org/rfc/rfc6901) JSON Pointer.
+    ///
+    /// Annotation keys are written like property keys, use [Keys::to_pointer_ext]
+    /// to address annotations.
+    pub fn to_pointer(&self) -> String {
+        let mut pointer = String::new();
+        for key in self.iter() {
+            pointer.push('/');
+            match key {
+                KeyOrIndex::Index(i) => pointer.push_str(&i.to_string()),
+                KeyOrIndex::Key(k) => pointer.push_str(&escape_pointer_segment(k.value())),
+            }
+        }
+        pointer
+    }
+
+    /// Convert to a JSON Pointer with the annotation extension, which JSON tools do not know.
+    ///
+    /// Annotation keys are written as segments starting with `@`, a leading `@`
+    /// of a property key is escaped as `~2`.
+    pub fn to_pointer_ext(&self) -> String {
+        let mut pointer = String::new();
+        for key in self.iter() {
+            pointer.push('/');
+            match key {
+                KeyOrIndex::Index(i) => pointer.push_str(&i.to_string()),
+                KeyOrIndex::Key(k) if k.is_annotation() => pointer.push_str(k.value()),
+                KeyOrIndex::Key(k) => {
+                    let value = k.value();
+                    let value = match value.strip_prefix('@') {
+                        Some(rest) => {
+                            pointer.push_str("~2");
+                            rest
+                        }
+                        None => value,
+                    };
+                    pointer.push_str(&escape_pointer_segment(value));
+                }
+            }
+        }
+        pointer
+    }
+
     pub fn mapper_range(&self, node: &Node, mapper: &mapper::Mapper) -> Option<mapper::Range> {
         let key = self.last().and_then(|v| v.as_key())?;
         let key_range = key.mapper_range(mapper)?;
@@ -240,6 +284,70 @@ impl Keys {
     }
 }
 
+pub(crate) enum PointerSegment {
+    Property(String),
+    Annotation(String),
+}
+
+fn escape_pointer_segment(value: &str) -> String {
+    value.replace('~', "~0").replace('/', "~1")
+}
+
+/// Split a JSON Pointer into unescaped segments, `None` refers to the whole document.
+///
+/// With `ext`, segments starting with `@` are annotations and `~2` escapes a leading `@`,
+/// see [Keys::to_pointer_ext].
+pub(crate) fn pointer_segments(
+    pointer: &str,
+    ext: bool,
+) -> Result<Option<Vec<PointerSegment>>, PointerError> {
+    if pointer.is_empty() {
+        return Ok(None);
+    }
+    let pointer = pointer
+        .strip_prefix('/')
+        .ok_or(PointerError::MissingSlash)?;
+    pointer
+        .split('/')
+        .map(|segment| {
+            if ext && segment.starts_with('@') {
+                return Ok(PointerSegment::Annotation(segment.to_string()));
+            }
+            let mut value = String::new();
+            let mut chars = segment.chars();
+            while let Some(c) = chars.next() {
+                if c != '~' {
+                    value.push(c);
+                    continue;
+                }
+                match chars.next() {
+                    Some('0') => value.push('~'),
+                    Some('1') => value.push('/'),
+                    Some('2') if ext && value.is_empty() => value.push('@'),
+                    _ => {
+                        return Err(PointerError::InvalidEscape {
+                            segment: segment.to_string(),
+                        })
+                    }
+                }
+            }
+            Ok(PointerSegment::Property(value))
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
+}
+
+/// Parse an array index, leading zeros are not allowed.
+pub(crate) fn parse_pointer_index(segment: &str) -> Option<usize> {
+    if segment.is_empty()
+        || (segment.len() > 1 && segment.starts_with('0'))
+        || !segment.bytes().all(|v| v.is_ascii_digit())
+    {
+        return None;
+    }
+    segment.parse().ok()
+}
+
 impl Default for Keys {
     fn default() -> Self {
         Self::new(empty())
@@ -276,7 +384,18 @@ impl FromStr for Keys {
 
 impl PartialEq for Keys {
     fn eq(&self, other: &Self) -> bool {
-        self.dotted == other.dotted
+        self.keys.len() == other.keys.len()
+            && self
+                .keys
+                .iter()
+                .zip(other.keys.iter())
+                .all(|(a, b)| match (a, b) {
+                    (KeyOrIndex::Index(a), KeyOrIndex::Index(b)) => a == b,
+                    (KeyOrIndex::Key(a), KeyOrIndex::Key(b)) => {
+                        a.is_annotation() == b.is_annotation() && a.value() == b.value()
+                    }
+                    _ => false,
+                })
     }
 }
 
@@ -284,6 +403,105 @@ impl Eq for Keys {}
 
 impl std::hash::Hash for Keys {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.dotted.hash(state);
+        self.keys.len().hash(state);
+        for key in self.keys.iter() {
+            match key {
+                KeyOrIndex::Index(v) => {
+                    0u8.hash(state);
+                    v.hash(state);
+                }
+                KeyOrIndex::Key(v) => {
+                    (if v.is_annotation() { 2u8 } else { 1u8 }).hash(state);
+                    v.value().hash(state);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pointer() {
+        let keys = Keys::new(
+            [
+                KeyOrIndex::property("a.b"),
+                KeyOrIndex::property("c/d~e"),
+                KeyOrIndex::Index(1),
+                KeyOrIndex::property("@f[0]"),
+                KeyOrIndex::annotation("@g"),
+            ]
+            .into_iter(),
+        );
+        assert_eq!(keys.to_pointer(), "/a.b/c~1d~0e/1/@f[0]/@g");
+        assert_eq!(keys.to_pointer_ext(), "/a.b/c~1d~0e/1/~2f[0]/@g");
+    }
+
+    #[test]
+    fn test_annotation_keys() {
+        let node: Node = "{ a: 1 @mock({ foo: 2 }) @openapi.deprecated({ foo: 3 }) }"
+            .parse()
+            .unwrap();
+        let keys: Keys = "a@mock.foo".parse().unwrap();
+        assert_eq!(keys.len(), 3);
+        assert_eq!(node.path(&keys).unwrap().to_canonical_string(), "2");
+
+        let keys: Keys = r#"a@"openapi.deprecated".foo"#.parse().unwrap();
+        assert_eq!(keys.len(), 3);
+        assert_eq!(keys.to_string(), r#".a@"openapi.deprecated".foo"#);
+        assert_eq!(keys.to_string().parse::<Keys>().unwrap(), keys);
+        assert_eq!(node.path(&keys).unwrap().to_canonical_string(), "3");
+
+        let namespaced = Keys::new(
+            [
+                KeyOrIndex::property("a"),
+                KeyOrIndex::annotation("@mock.foo"),
+            ]
+            .into_iter(),
+        );
+        assert_ne!(namespaced, "a@mock.foo".parse::<Keys>().unwrap());
+        assert_ne!(
+            Keys::single(KeyOrIndex::property("@mock")),
+            Keys::single(KeyOrIndex::annotation("@mock"))
+        );
+    }
+
+    #[test]
+    fn test_node_pointer() {
+        let node: Node = r#"{ @x(1)
+  "a.b": [0, { "0": "zero" @y }],
+  "@c": { "": true, "1": "one" },
+  "~2d": 2,
+}"#
+        .parse()
+        .unwrap();
+        let get = |pointer: &str| node.pointer(pointer).map(|v| v.to_plain_json());
+        assert_eq!(get("/a.b/1/0"), Some(serde_json::json!("zero")));
+        assert_eq!(get("/@c/"), Some(serde_json::json!(true)));
+        assert_eq!(get("/@c/1"), Some(serde_json::json!("one")));
+        assert_eq!(get("/~02d"), Some(serde_json::json!(2)));
+        assert_eq!(get("/@x"), None);
+        assert_eq!(get("/~2c"), None);
+        assert_eq!(get("/a.b/01"), None);
+        assert_eq!(get("/a.b/2"), None);
+        assert!(get("").is_some());
+        assert!(get("a").is_none());
+
+        let get = |pointer: &str| node.pointer_ext(pointer).map(|v| v.to_plain_json());
+        assert_eq!(get("/a.b/1/0/@y"), Some(serde_json::json!(null)));
+        assert_eq!(get("/@x"), Some(serde_json::json!(1)));
+        assert_eq!(get("/~2c/"), Some(serde_json::json!(true)));
+        assert_eq!(get("/@c"), None);
+
+        for pointer in ["/a.b/1/0", "/~2c/1", "/~02d", "/a.b/1/0/@y", "/@x"] {
+            let keys = node.pointer_keys_ext(pointer).unwrap();
+            assert_eq!(keys.to_pointer_ext(), pointer);
+            assert!(node.path(&keys).is_some());
+        }
+        let keys = node.pointer_keys("/@c/1").unwrap();
+        assert_eq!(keys.last(), Some(&KeyOrIndex::property("1")));
+        assert_eq!(keys.to_pointer(), "/@c/1");
     }
 }
@@ -6,19 +6,28 @@
 #[macro_use]
 mod macros;
 
+pub mod comments;
+pub mod diff;
 pub mod error;
 pub mod keys;
 pub mod node;
 pub mod query_keys;
+pub mod source_map;
 pub mod visitor;
 
+mod canonical;
 pub(crate) mod from_syntax;
 mod serde;
 mod to_string;
 
+pub use self::serde::NonFiniteMode;
+pub use comments::Comment;
+pub use diff::{diff, ArrayMatch, Change, DiffOptions};
 pub use error::*;
-pub use from_syntax::from_syntax;
+pub(crate) use from_syntax::from_syntax_in;
+pub use from_syntax::{from_syntax, from_syntax_with_options, DomOptions, DuplicateKeyPolicy};
 pub use keys::*;
 pub use node::*;
 pub use query_keys::*;
+pub use source_map::{SourceMap, SourceMapEntry};
 pub use visitor::*;
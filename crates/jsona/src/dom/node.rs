@@ -1,5 +1,5 @@
 use super::error::DomError;
-use super::keys::{KeyOrIndex, Keys};
+use super::keys::{parse_pointer_index, pointer_segments, KeyOrIndex, Keys, PointerSegment};
 use super::query_keys::QueryKeys;
 use super::visitor::{VisitControl, Visitor};
 use crate::error::Error as JsonaError;
@@ -13,6 +13,7 @@ use crate::util::{check_quote, quote, unquote};
 use indexmap::IndexMap;
 use once_cell::unsync::OnceCell;
 use rowan::{NodeOrToken, TextRange};
+use serde::{Deserialize, Serialize};
 use serde_json::Number as JsonNumber;
 use std::rc::Rc;
 use std::str::FromStr;
@@ -48,6 +49,53 @@ impl Node {
         Some(node)
     }
 
+    /// Look up a node by a [RFC 6901](https://www.rfc-editor.org/rfc/rfc6901) JSON Pointer.
+    ///
+    /// Returns `None` if the pointer is invalid or no such node exists.
+    pub fn pointer(&self, pointer: &str) -> Option<Node> {
+        self.path(&self.pointer_keys(pointer)?)
+    }
+
+    /// Look up a node by a JSON Pointer that may address annotations, see [Keys::to_pointer_ext].
+    pub fn pointer_ext(&self, pointer: &str) -> Option<Node> {
+        self.path(&self.pointer_keys_ext(pointer)?)
+    }
+
+    /// Resolve a JSON Pointer into the keys of an existing node.
+    ///
+    /// A segment is an index into arrays and a property key of objects, so the keys
+    /// convert back to the same pointer with [Keys::to_pointer].
+    pub fn pointer_keys(&self, pointer: &str) -> Option<Keys> {
+        self.pointer_keys_impl(pointer, false)
+    }
+
+    /// Like [Node::pointer_keys], for pointers written by [Keys::to_pointer_ext].
+    pub fn pointer_keys_ext(&self, pointer: &str) -> Option<Keys> {
+        self.pointer_keys_impl(pointer, true)
+    }
+
+    fn pointer_keys_impl(&self, pointer: &str, ext: bool) -> Option<Keys> {
+        let segments = match pointer_segments(pointer, ext).ok()? {
+            Some(v) => v,
+            None => return Some(Keys::default()),
+        };
+        let mut node = self.clone();
+        let mut keys = Keys::default();
+        for segment in segments {
+            let key = match segment {
+                PointerSegment::Annotation(v) => KeyOrIndex::annotation(v),
+                PointerSegment::Property(v) => match &node {
+                    Node::Array(_) => KeyOrIndex::Index(parse_pointer_index(&v)?),
+                    Node::Object(_) => KeyOrIndex::property(v),
+                    _ => return None,
+                },
+            };
+            node = node.get(&key)?;
+            keys = keys.join(key);
+        }
+        Some(keys)
+    }
+
     pub fn get(&self, key: &KeyOrIndex) -> Option<Node> {
         match key {
             KeyOrIndex::Index(i) => self.as_array().and_then(|v| v.get(*i)),
@@ -64,6 +112,7 @@ impl Node {
     pub fn validate(&self) -> Result<(), impl Iterator<Item = DomError> + core::fmt::Debug> {
         let mut errors = Vec::new();
         self.validate_all_impl(&mut errors);
+        errors.retain(|v| !v.is_warning());
         if errors.is_empty() {
             Ok(())
         } else {
@@ -71,6 +120,15 @@ impl Node {
         }
     }
 
+    /// Warnings that do not make the node invalid, such as duplicate keys
+    /// handled by a [DuplicateKeyPolicy](super::DuplicateKeyPolicy).
+    pub fn warnings(&self) -> Vec<DomError> {
+        let mut errors = Vec::new();
+        self.validate_all_impl(&mut errors);
+        errors.retain(|v| v.is_warning());
+        errors
+    }
+
     pub fn is_valid(&self) -> bool {
         let mut valid = true;
         self.is_valid_impl(&mut valid);
@@ -103,13 +161,13 @@ impl Node {
         &self,
         keys: QueryKeys,
         match_children: bool,
-    ) -> Result<impl Iterator<Item = (Keys, Node)> + ExactSizeIterator, DomError> {
+    ) -> Result<impl ExactSizeIterator<Item = (Keys, Node)>, DomError> {
         let all: Vec<(Keys, Node)> = Visitor::new(self, &(), |_, _, _| VisitControl::AddIter)
             .into_iter()
             .collect();
         let mut output = vec![];
         for (k, v) in all {
-            if keys.is_match(&k, match_children) {
+            if keys.is_match_in(self, &k, match_children) {
                 output.push((k, v));
             }
         }
@@ -135,7 +193,7 @@ impl Node {
             Node::Number(v) => {
                 let text = match self.syntax() {
                     Some(syntax) => syntax.to_string(),
-                    None => v.value().to_string(),
+                    None => v.to_repr_string(),
                 };
                 Some(text)
             }
@@ -158,7 +216,7 @@ impl Node {
     fn is_valid_impl(&self, valid: &mut bool) {
         match self {
             Node::Object(v) => {
-                if !v.errors().read().as_ref().is_empty() {
+                if v.errors().read().iter().any(|v| !v.is_warning()) {
                     *valid = false;
                     return;
                 }
@@ -213,7 +271,7 @@ impl Node {
             }
         }
         if let Some(v) = self.annotations() {
-            if !v.errors().read().as_ref().is_empty() {
+            if v.errors().read().iter().any(|v| !v.is_warning()) {
                 *valid = false;
                 return;
             }
@@ -376,6 +434,7 @@ pub(crate) struct NumberInner {
     pub(crate) node_syntax: Option<SyntaxElement>,
     pub(crate) annotations: Option<Annotations>,
     pub(crate) repr: NumberRepr,
+    pub(crate) non_finite: Option<NonFinite>,
     pub(crate) value: OnceCell<JsonNumber>,
 }
 
@@ -386,82 +445,272 @@ wrap_node! {
 
 impl Number {
     pub fn new(value: JsonNumber, annotations: Option<Annotations>) -> Self {
+        Self::with_repr(value, Default::default(), annotations)
+    }
+
+    /// Create a number written in the given representation, e.g. `0xff` for [NumberRepr::Hex].
+    ///
+    /// The representation is ignored when the value is not a non-negative integer.
+    pub fn with_repr(
+        value: JsonNumber,
+        repr: NumberRepr,
+        annotations: Option<Annotations>,
+    ) -> Self {
         NumberInner {
             errors: Default::default(),
             syntax: None,
             node_syntax: None,
             annotations,
-            repr: Default::default(),
+            repr,
+            non_finite: None,
             value: value.into(),
         }
         .into()
     }
 
+    /// Create a number from a float, including NaN and infinities.
+    pub fn from_f64(value: f64, annotations: Option<Annotations>) -> Self {
+        match (NonFinite::from_f64(value), JsonNumber::from_f64(value)) {
+            (None, Some(number)) => Self::new(number, annotations),
+            (non_finite, _) => NumberInner {
+                errors: Default::default(),
+                syntax: None,
+                node_syntax: None,
+                annotations,
+                repr: NumberRepr::Float,
+                non_finite,
+                value: JsonNumber::from(0).into(),
+            }
+            .into(),
+        }
+    }
+
     /// An number value.
+    ///
+    /// Integers beyond `u64`/`i64` and high-precision decimals are rounded, unless
+    /// the `arbitrary_precision` feature is enabled.
+    ///
+    /// NaN and infinities can not be held by a JSON number, their value is `0`,
+    /// see [Number::non_finite].
     pub fn value(&self) -> &JsonNumber {
         self.inner.value.get_or_init(|| {
             self.inner
                 .syntax
                 .as_ref()
-                .map(|s| {
-                    let text = s.as_token().unwrap().text().replace('_', "");
-
-                    match self.inner.repr {
-                        NumberRepr::Float => {
-                            match text.parse::<f64>().ok().and_then(JsonNumber::from_f64) {
-                                Some(v) => v,
-                                None => {
-                                    self.inner.errors.update(|errors| {
-                                        errors.push(DomError::InvalidNumber { syntax: s.clone() })
-                                    });
-                                    JsonNumber::from_f64(0.0).unwrap()
-                                }
-                            }
-                        }
-                        NumberRepr::Dec => {
-                            if text.starts_with('-') {
-                                JsonNumber::from(text.parse::<i64>().unwrap_or_default())
-                            } else {
-                                JsonNumber::from(text.parse::<u64>().unwrap_or_default())
-                            }
-                        }
-                        NumberRepr::Bin => JsonNumber::from(
-                            u64::from_str_radix(text.trim_start_matches("0b"), 2)
-                                .unwrap_or_default(),
-                        ),
-                        NumberRepr::Oct => JsonNumber::from(
-                            u64::from_str_radix(text.trim_start_matches("0o"), 8)
-                                .unwrap_or_default(),
-                        ),
-                        NumberRepr::Hex => JsonNumber::from(
-                            u64::from_str_radix(text.trim_start_matches("0x"), 16)
-                                .unwrap_or_default(),
-                        ),
+                .map(|s| match self.to_decimal_string().parse::<JsonNumber>() {
+                    Ok(v) => v,
+                    Err(_) => {
+                        self.inner.errors.update(|errors| {
+                            errors.push(DomError::InvalidNumber { syntax: s.clone() })
+                        });
+                        JsonNumber::from(0)
                     }
                 })
                 .unwrap_or_else(|| JsonNumber::from(0))
         })
     }
+
+    pub fn repr(&self) -> NumberRepr {
+        self.inner.repr
+    }
+
+    /// Whether the number is NaN or an infinity.
+    pub fn non_finite(&self) -> Option<NonFinite> {
+        self.inner.non_finite
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.inner.non_finite.is_none()
+    }
+
+    /// The value as `f64`, large integers and high-precision decimals are rounded.
+    pub fn as_f64(&self) -> f64 {
+        match self.inner.non_finite {
+            Some(v) => v.as_f64(),
+            None => self.value().as_f64().unwrap_or(f64::NAN),
+        }
+    }
+
     pub fn is_integer(&self) -> bool {
         self.inner.repr != NumberRepr::Float
     }
+
+    /// The exact value in decimal notation, integers written in other bases are converted.
+    ///
+    /// Returns `nan`, `inf` or `-inf` for non-finite numbers.
+    pub fn to_decimal_string(&self) -> StdString {
+        if let Some(v) = self.inner.non_finite {
+            return v.to_string();
+        }
+        let text = match self.inner.syntax.as_ref().and_then(|v| v.as_token()) {
+            Some(token) => token.text().replace('_', ""),
+            None => return self.value().to_string(),
+        };
+        let text = text.strip_prefix('+').unwrap_or(&text);
+        match self.inner.repr {
+            NumberRepr::Dec | NumberRepr::Float => normalize_decimal(text),
+            NumberRepr::Bin => radix_to_decimal(&text[2..], 2),
+            NumberRepr::Oct => radix_to_decimal(&text[2..], 8),
+            NumberRepr::Hex => radix_to_decimal(&text[2..], 16),
+        }
+    }
+
+    /// The value as `i128`, if it is an integer in range.
+    pub fn as_i128(&self) -> Option<i128> {
+        match self.inner.repr {
+            NumberRepr::Float => self.float_as_integer().and_then(|v| v.parse().ok()),
+            _ => self.to_decimal_string().parse().ok(),
+        }
+    }
+
+    /// The value as `u128`, if it is a non-negative integer in range.
+    pub fn as_u128(&self) -> Option<u128> {
+        match self.inner.repr {
+            NumberRepr::Float => self.float_as_integer().and_then(|v| v.parse().ok()),
+            _ => self.to_decimal_string().parse().ok(),
+        }
+    }
+
+    fn float_as_integer(&self) -> Option<StdString> {
+        // Integers beyond 2^53 are not exact in a float.
+        const MAX_EXACT: f64 = 9_007_199_254_740_992.0;
+        let value = self.value().as_f64()?;
+        if value.fract() == 0.0 && value.abs() <= MAX_EXACT {
+            Some((value as i64).to_string())
+        } else {
+            None
+        }
+    }
+
+    /// The text of a number without syntax, written in its representation.
+    pub(crate) fn to_repr_string(&self) -> StdString {
+        if let Some(v) = self.inner.non_finite {
+            return v.to_string();
+        }
+        let value = self.value();
+        let integer = value.as_u64().map(u128::from).or_else(|| {
+            if self.inner.repr == NumberRepr::Float {
+                None
+            } else {
+                value.to_string().parse::<u128>().ok()
+            }
+        });
+        match (self.inner.repr, integer) {
+            (NumberRepr::Bin, Some(v)) => format!("0b{v:b}"),
+            (NumberRepr::Oct, Some(v)) => format!("0o{v:o}"),
+            (NumberRepr::Hex, Some(v)) => format!("0x{v:x}"),
+            _ => value.to_string(),
+        }
+    }
+}
+
+/// A float that can not be held by a JSON number.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NonFinite {
+    NaN,
+    Infinity,
+    NegInfinity,
+}
+
+impl NonFinite {
+    pub fn from_f64(value: f64) -> Option<Self> {
+        if value.is_nan() {
+            Some(Self::NaN)
+        } else if value == f64::INFINITY {
+            Some(Self::Infinity)
+        } else if value == f64::NEG_INFINITY {
+            Some(Self::NegInfinity)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        match self {
+            NonFinite::NaN => f64::NAN,
+            NonFinite::Infinity => f64::INFINITY,
+            NonFinite::NegInfinity => f64::NEG_INFINITY,
+        }
+    }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+impl core::fmt::Display for NonFinite {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            NonFinite::NaN => "nan",
+            NonFinite::Infinity => "inf",
+            NonFinite::NegInfinity => "-inf",
+        };
+        text.fmt(f)
+    }
+}
+
+/// Normalize a decimal literal to the JSON number syntax, e.g. `-00.5e3` to `-0.5e3`.
+fn normalize_decimal(text: &str) -> StdString {
+    let (sign, text) = match text.strip_prefix('-') {
+        Some(v) => ("-", v),
+        None => ("", text),
+    };
+    let (mantissa, exp) = match text.find(['e', 'E']) {
+        Some(i) => text.split_at(i),
+        None => (text, ""),
+    };
+    let (int, frac) = match mantissa.split_once('.') {
+        Some((int, frac)) => (int, Some(frac)),
+        None => (mantissa, None),
+    };
+    let int = int.trim_start_matches('0');
+    let mut output = format!("{sign}{}", if int.is_empty() { "0" } else { int });
+    match frac {
+        Some(frac) if !frac.is_empty() => {
+            output.push('.');
+            output.push_str(frac);
+        }
+        Some(_) => output.push_str(".0"),
+        None => {}
+    }
+    output.push_str(exp);
+    output
+}
+
+/// Convert digits in the given radix to decimal digits, without limit of size.
+fn radix_to_decimal(digits: &str, radix: u32) -> StdString {
+    const BASE: u64 = 1_000_000_000;
+    // Little-endian limbs in base 10^9.
+    let mut limbs: Vec<u64> = vec![0];
+    for c in digits.chars() {
+        let mut carry = match c.to_digit(radix) {
+            Some(v) => v as u64,
+            None => continue,
+        };
+        for limb in limbs.iter_mut() {
+            let v = *limb * radix as u64 + carry;
+            *limb = v % BASE;
+            carry = v / BASE;
+        }
+        if carry > 0 {
+            limbs.push(carry);
+        }
+    }
+    let mut output = limbs.last().unwrap().to_string();
+    for limb in limbs.iter().rev().skip(1) {
+        output.push_str(&format!("{limb:09}"));
+    }
+    output
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum NumberRepr {
     Dec,
     Bin,
     Oct,
     Hex,
+    #[default]
     Float,
 }
 
-impl Default for NumberRepr {
-    fn default() -> Self {
-        Self::Float
-    }
-}
-
 #[derive(Debug)]
 pub(crate) struct StringInner {
     pub(crate) errors: Shared<Vec<DomError>>,
@@ -508,19 +757,14 @@ impl String {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Default)]
 pub enum StringRepr {
+    #[default]
     Double,
     Single,
     Backtick,
 }
 
-impl Default for StringRepr {
-    fn default() -> Self {
-        Self::Double
-    }
-}
-
 #[derive(Debug)]
 pub(crate) struct ArrayInner {
     pub(crate) errors: Shared<Vec<DomError>>,
@@ -668,7 +912,7 @@ impl Key {
                 .and_then(NodeOrToken::as_token)
                 .map(|s| {
                     if self.is_annotation() {
-                        return s.to_string();
+                        return annotation_key_value(s.text());
                     }
                     match unquote(s.text()) {
                         Ok(s) => s,
@@ -698,14 +942,27 @@ impl Key {
         self.syntax().map(|v| v.text_range())
     }
 
-    pub fn annotation_name(&self) -> Option<StdString> {
+    /// The name of an annotation key without the leading `@`.
+    pub fn annotation_name(&self) -> Option<AnnotationName> {
         if self.is_annotation() {
-            Some(self.value()[1..].to_string())
+            Some(AnnotationName::new(&self.value()[1..]))
         } else {
             None
         }
     }
 
+    /// The key as written in [Keys](super::Keys), namespaced annotation keys
+    /// are quoted so that their dots are not read as separators.
+    pub(crate) fn to_keys_string(&self) -> StdString {
+        match self.annotation_name() {
+            Some(name) if name.namespace.is_some() => {
+                format!("@{}", quote(&name.to_string(), true))
+            }
+            Some(_) => self.value().to_string(),
+            None => quote(self.value(), false),
+        }
+    }
+
     pub fn to_origin_string(&self) -> StdString {
         match self.syntax() {
             Some(v) => v.to_string(),
@@ -727,6 +984,64 @@ impl Key {
     }
 }
 
+/// The name of an annotation, split into its namespace and local name.
+///
+/// The namespace is everything before the last `.`, so `@openapi.deprecated` has the
+/// namespace `openapi` and the local name `deprecated`, while `@mock` has no namespace.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AnnotationName {
+    pub namespace: Option<StdString>,
+    pub name: StdString,
+}
+
+impl AnnotationName {
+    /// Split a name without the leading `@`.
+    pub fn new(name: &str) -> Self {
+        match name.rsplit_once('.') {
+            Some((namespace, name)) => Self {
+                namespace: Some(namespace.to_string()),
+                name: name.to_string(),
+            },
+            None => Self {
+                namespace: None,
+                name: name.to_string(),
+            },
+        }
+    }
+}
+
+/// Whether the name, without the leading `@`, is a valid annotation name,
+/// optionally namespaced with dots.
+pub fn is_annotation_name(name: &str) -> bool {
+    name.split('.').all(|segment| {
+        !segment.is_empty()
+            && segment
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+/// The value of an annotation key token, `@"openapi.deprecated"` in keys
+/// is the annotation key `@openapi.deprecated`.
+pub(crate) fn annotation_key_value(text: &str) -> StdString {
+    match text.strip_prefix('@') {
+        Some(name) if name.starts_with(['"', '\'']) => match unquote(name) {
+            Ok(name) => format!("@{name}"),
+            Err(_) => text.to_string(),
+        },
+        _ => text.to_string(),
+    }
+}
+
+impl core::fmt::Display for AnnotationName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(namespace) = &self.namespace {
+            write!(f, "{namespace}.")?;
+        }
+        self.name.fmt(f)
+    }
+}
+
 impl AsRef<str> for Key {
     fn as_ref(&self) -> &str {
         self.value()
@@ -844,3 +1159,102 @@ impl From<AnnotationsInner> for Annotations {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number(s: &str) -> Number {
+        Node::from_str(s).unwrap().as_number().cloned().unwrap()
+    }
+
+    #[test]
+    fn test_number_lossless() {
+        let big = number("0xffff_ffff_ffff_ffff_ffff_ffff_ffff_ffff");
+        assert_eq!(big.repr(), NumberRepr::Hex);
+        assert_eq!(big.as_u128(), Some(u128::MAX));
+        assert_eq!(big.as_i128(), None);
+        assert_eq!(
+            big.to_decimal_string(),
+            "340282366920938463463374607431768211455"
+        );
+        let big = number("-170141183460469231731687303715884105728");
+        assert_eq!(big.as_i128(), Some(i128::MIN));
+        assert_eq!(big.as_u128(), None);
+        assert_eq!(number("0b1010").as_i128(), Some(10));
+        assert_eq!(number("0o17").to_decimal_string(), "15");
+        assert_eq!(number("+1_000").to_decimal_string(), "1000");
+        assert_eq!(number("-0.50e3").to_decimal_string(), "-0.50e3");
+        assert_eq!(number(".5").to_decimal_string(), "0.5");
+        assert_eq!(number("2.0").as_u128(), Some(2));
+        assert_eq!(number("2.5").as_u128(), None);
+    }
+
+    #[test]
+    fn test_number_repr() {
+        let node: Node = Number::with_repr(JsonNumber::from(255), NumberRepr::Hex, None).into();
+        assert_eq!(node.to_string(), "0xff");
+        let node: Node = Number::with_repr(JsonNumber::from(5), NumberRepr::Bin, None).into();
+        assert_eq!(node.to_string(), "0b101");
+        let node: Node = Number::with_repr(JsonNumber::from(-5), NumberRepr::Oct, None).into();
+        assert_eq!(node.to_string(), "-5");
+    }
+
+    #[test]
+    fn test_non_finite() {
+        let node: Node = "[nan, inf, -inf, +inf, 1.5]".parse().unwrap();
+        let numbers: Vec<Number> = node
+            .as_array()
+            .unwrap()
+            .value()
+            .read()
+            .iter()
+            .map(|v| v.as_number().cloned().unwrap())
+            .collect();
+        assert_eq!(numbers[0].non_finite(), Some(NonFinite::NaN));
+        assert!(numbers[0].as_f64().is_nan());
+        assert_eq!(numbers[1].non_finite(), Some(NonFinite::Infinity));
+        assert_eq!(numbers[2].non_finite(), Some(NonFinite::NegInfinity));
+        assert_eq!(numbers[3].as_f64(), f64::INFINITY);
+        assert!(numbers[4].is_finite());
+
+        let node: Node = Number::from_f64(f64::NEG_INFINITY, None).into();
+        assert_eq!(node.to_string(), "-inf");
+    }
+
+    #[test]
+    fn test_annotation_name() {
+        let node: Node = "{ a: 1, @mock @openapi.v3.deprecated(true) }"
+            .parse()
+            .unwrap();
+        let names: Vec<_> = node
+            .get(&KeyOrIndex::property("a"))
+            .unwrap()
+            .annotations()
+            .unwrap()
+            .value()
+            .read()
+            .iter()
+            .map(|(k, _)| k.annotation_name().unwrap())
+            .collect();
+        assert_eq!(names[0].namespace, None);
+        assert_eq!(names[0].name, "mock");
+        assert_eq!(names[1].namespace.as_deref(), Some("openapi.v3"));
+        assert_eq!(names[1].name, "deprecated");
+        assert_eq!(names[1].to_string(), "openapi.v3.deprecated");
+        let keys: Keys = r#"a@"openapi.v3.deprecated""#.parse().unwrap();
+        assert_eq!(node.path(&keys).unwrap().to_plain_json(), true);
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn test_arbitrary_precision() {
+        let node: Node = "[0xffff_ffff_ffff_ffff_ffff, 3.141592653589793238462643383279]"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            serde_json::to_string(&node.to_plain_json()).unwrap(),
+            "[1208925819614629174706175,3.141592653589793238462643383279]"
+        );
+    }
+}
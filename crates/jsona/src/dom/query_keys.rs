@@ -1,9 +1,13 @@
 use super::from_syntax::query_keys_from_syntax;
 use super::node::Key;
-use super::{KeyOrIndex, Keys};
-use crate::parser::Parser;
-use crate::util::glob;
+use super::{KeyOrIndex, Keys, Node};
+use crate::parser::{Error as ParserError, Parser};
+use crate::util::{glob, quote, unquote};
 
+use regex::Regex;
+use rowan::{TextRange, TextSize};
+use serde_json::Value;
+use std::cmp::Ordering;
 use std::iter::empty;
 use std::str::FromStr;
 use std::sync::Arc;
@@ -15,6 +19,20 @@ pub enum QueryKey {
     GlobIndex(String),
     GlobKey(String),
     AnyRecursive,
+    /// `[-1]`, counts from the end of the array, `1` is the last item.
+    ReverseIndex(usize),
+    /// `[start:end:step]`, follows the semantics of Python slices.
+    Slice {
+        start: Option<isize>,
+        end: Option<isize>,
+        step: Option<isize>,
+    },
+    /// `[/pattern/]`, matches property keys.
+    Regex(KeyRegex),
+    /// `[0,-1,"foo",/bar/]`, matches if any of the keys matches.
+    Union(Vec<QueryKey>),
+    /// `[?(@.enabled == true)]`, matches items and properties satisfying the filter.
+    Filter(Filter),
 }
 
 impl core::fmt::Display for QueryKey {
@@ -23,20 +41,55 @@ impl core::fmt::Display for QueryKey {
             QueryKey::Index(v) => write!(f, "[{}]", v),
             QueryKey::Key(v) => {
                 if v.is_property() {
-                    write!(f, ".{}", v)
+                    write!(f, ".{}", v.to_keys_string())
                 } else {
-                    write!(f, "{}", v)
+                    write!(f, "{}", v.to_keys_string())
                 }
             }
             QueryKey::GlobIndex(v) => write!(f, "[{}]", v),
             QueryKey::GlobKey(v) => write!(f, ".{}", v),
             QueryKey::AnyRecursive => write!(f, "**"),
+            QueryKey::ReverseIndex(v) => write!(f, "[-{}]", v),
+            QueryKey::Slice { start, end, step } => {
+                let bound = |v: &Option<isize>| v.map(|v| v.to_string()).unwrap_or_default();
+                write!(f, "[{}:{}", bound(start), bound(end))?;
+                if let Some(step) = step {
+                    write!(f, ":{}", step)?;
+                }
+                write!(f, "]")
+            }
+            QueryKey::Regex(v) => write!(f, "[{}]", v),
+            QueryKey::Union(keys) => {
+                write!(f, "[")?;
+                for (i, key) in keys.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    match key {
+                        QueryKey::Index(v) => write!(f, "{}", v)?,
+                        QueryKey::ReverseIndex(v) => write!(f, "-{}", v)?,
+                        QueryKey::Key(v) => write!(f, "{}", quote(v.value(), false))?,
+                        QueryKey::Regex(v) => write!(f, "{}", v)?,
+                        _ => write!(f, "{}", key)?,
+                    }
+                }
+                write!(f, "]")
+            }
+            QueryKey::Filter(v) => write!(f, "[?({})]", v),
         }
     }
 }
 
 impl QueryKey {
+    /// Whether the key matches, selectors depending on values or array lengths never match.
+    ///
+    /// Use [QueryKey::is_match_in] to match them too.
     pub fn is_match(&self, other: &KeyOrIndex) -> bool {
+        self.is_match_in(other, None)
+    }
+
+    /// Whether the key of a child of `parent` matches.
+    pub fn is_match_in(&self, other: &KeyOrIndex, parent: Option<&Node>) -> bool {
         match self {
             QueryKey::Index(v1) => match other {
                 KeyOrIndex::Index(v2) => v1 == v2,
@@ -56,8 +109,246 @@ impl QueryKey {
                 _ => false,
             },
             QueryKey::AnyRecursive => true,
+            QueryKey::ReverseIndex(v1) => match (other, array_len(parent)) {
+                (KeyOrIndex::Index(v2), Some(len)) => v2 + v1 == len,
+                _ => false,
+            },
+            QueryKey::Slice { start, end, step } => match other {
+                KeyOrIndex::Index(v2) => {
+                    is_slice_match(*start, *end, *step, *v2, array_len(parent))
+                }
+                _ => false,
+            },
+            QueryKey::Regex(v1) => match other {
+                KeyOrIndex::Key(v2) if v2.is_property() => v1.0.is_match(v2.value()),
+                _ => false,
+            },
+            QueryKey::Union(keys) => keys.iter().any(|v| v.is_match_in(other, parent)),
+            QueryKey::Filter(filter) => {
+                if other.is_annotation_key() {
+                    return false;
+                }
+                match parent.and_then(|v| v.get(other)) {
+                    Some(node) => filter.is_match(&node),
+                    None => false,
+                }
+            }
+        }
+    }
+
+    fn needs_node(&self) -> bool {
+        match self {
+            QueryKey::ReverseIndex(_) | QueryKey::Filter(_) => true,
+            QueryKey::Slice { start, end, step } => {
+                start.unwrap_or_default() < 0
+                    || end.unwrap_or_default() < 0
+                    || step.unwrap_or(1) < 0
+            }
+            QueryKey::Union(keys) => keys.iter().any(|v| v.needs_node()),
+            _ => false,
+        }
+    }
+}
+
+fn array_len(node: Option<&Node>) -> Option<usize> {
+    node.and_then(|v| v.as_array())
+        .map(|v| v.value().read().len())
+}
+
+fn is_slice_match(
+    start: Option<isize>,
+    end: Option<isize>,
+    step: Option<isize>,
+    index: usize,
+    len: Option<usize>,
+) -> bool {
+    let step = step.unwrap_or(1);
+    let index = index as isize;
+    let normalize = |v: isize| -> Option<isize> {
+        if v >= 0 {
+            Some(v)
+        } else {
+            len.map(|len| (len as isize + v).max(-1))
+        }
+    };
+    let len = len.map(|v| v as isize);
+    if let Some(len) = len {
+        if index >= len {
+            return false;
         }
     }
+    match step.cmp(&0) {
+        Ordering::Greater => {
+            let start = match start.map(normalize) {
+                Some(Some(v)) => v.max(0),
+                Some(None) => return false,
+                None => 0,
+            };
+            let in_range = match end.map(normalize) {
+                Some(Some(end)) => index < end,
+                Some(None) => return false,
+                None => true,
+            };
+            in_range && index >= start && (index - start) % step == 0
+        }
+        Ordering::Less => {
+            let start = match start.map(normalize) {
+                Some(Some(v)) => v,
+                Some(None) => return false,
+                None => match len {
+                    Some(len) => len - 1,
+                    None => return false,
+                },
+            };
+            let in_range = match end.map(normalize) {
+                Some(Some(end)) => index > end,
+                Some(None) => return false,
+                None => true,
+            };
+            in_range && index <= start && (start - index) % -step == 0
+        }
+        Ordering::Equal => false,
+    }
+}
+
+/// A regular expression matching property keys.
+#[derive(Debug, Clone)]
+pub struct KeyRegex(Regex);
+
+impl KeyRegex {
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        Regex::new(pattern).map(Self)
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl PartialEq for KeyRegex {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for KeyRegex {}
+
+impl core::fmt::Display for KeyRegex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "/{}/", self.as_str().replace('/', "\\/"))
+    }
+}
+
+/// A filter expression, the keys are relative to the filtered node.
+///
+/// Annotations are addressed like in [Keys], e.g. `@@mock` checks
+/// whether the node has the `@mock` annotation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+    Exists(Keys),
+    Compare {
+        keys: Keys,
+        op: CompareOp,
+        value: Value,
+    },
+    Not(Box<Filter>),
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+}
+
+impl Filter {
+    pub fn is_match(&self, node: &Node) -> bool {
+        match self {
+            Filter::Exists(keys) => node.path(keys).is_some(),
+            Filter::Compare { keys, op, value } => match node.path(keys) {
+                Some(node) => op.compare(&node.to_plain_json(), value),
+                None => false,
+            },
+            Filter::Not(v) => !v.is_match(node),
+            Filter::And(v1, v2) => v1.is_match(node) && v2.is_match(node),
+            Filter::Or(v1, v2) => v1.is_match(node) || v2.is_match(node),
+        }
+    }
+
+    fn fmt_operand(&self, f: &mut std::fmt::Formatter<'_>, parent: &Filter) -> std::fmt::Result {
+        let group = matches!(
+            (parent, self),
+            (Filter::Not(_), Filter::And(..) | Filter::Or(..)) | (Filter::And(..), Filter::Or(..))
+        );
+        if group {
+            write!(f, "({})", self)
+        } else {
+            write!(f, "{}", self)
+        }
+    }
+}
+
+impl core::fmt::Display for Filter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Filter::Exists(keys) => write!(f, "@{}", keys),
+            Filter::Compare { keys, op, value } => write!(f, "@{} {} {}", keys, op, value),
+            Filter::Not(v) => {
+                write!(f, "!")?;
+                v.fmt_operand(f, self)
+            }
+            Filter::And(v1, v2) | Filter::Or(v1, v2) => {
+                let op = if matches!(self, Filter::And(..)) {
+                    "&&"
+                } else {
+                    "||"
+                };
+                v1.fmt_operand(f, self)?;
+                write!(f, " {} ", op)?;
+                v2.fmt_operand(f, self)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CompareOp {
+    fn compare(&self, v1: &Value, v2: &Value) -> bool {
+        let ordering = match (v1, v2) {
+            (Value::Number(n1), Value::Number(n2)) => n1
+                .as_f64()
+                .zip(n2.as_f64())
+                .and_then(|(n1, n2)| n1.partial_cmp(&n2)),
+            (Value::String(s1), Value::String(s2)) => Some(s1.cmp(s2)),
+            _ => None,
+        };
+        match self {
+            CompareOp::Eq => ordering == Some(Ordering::Equal) || v1 == v2,
+            CompareOp::Ne => !(ordering == Some(Ordering::Equal) || v1 == v2),
+            CompareOp::Lt => ordering == Some(Ordering::Less),
+            CompareOp::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+            CompareOp::Gt => ordering == Some(Ordering::Greater),
+            CompareOp::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+        }
+    }
+}
+
+impl core::fmt::Display for CompareOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let op = match self {
+            CompareOp::Eq => "==",
+            CompareOp::Ne => "!=",
+            CompareOp::Lt => "<",
+            CompareOp::Le => "<=",
+            CompareOp::Gt => ">",
+            CompareOp::Ge => ">=",
+        };
+        op.fmt(f)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -65,6 +356,7 @@ pub struct QueryKeys {
     dotted: Arc<str>,
     keys: Arc<[QueryKey]>,
     exist_any_recursive: bool,
+    needs_node: bool,
 }
 
 impl QueryKeys {
@@ -72,15 +364,13 @@ impl QueryKeys {
         let keys: Arc<[QueryKey]> = keys.collect();
         let mut dotted = String::new();
         let mut exist_any_recursive = false;
+        let mut needs_node = false;
         for k in keys.iter() {
             match k {
-                QueryKey::Index(_)
-                | QueryKey::Key(_)
-                | QueryKey::GlobIndex(_)
-                | QueryKey::GlobKey(_) => {}
                 QueryKey::AnyRecursive => {
                     exist_any_recursive = true;
                 }
+                _ => needs_node |= k.needs_node(),
             }
             dotted.push_str(&k.to_string());
         }
@@ -89,6 +379,7 @@ impl QueryKeys {
             keys,
             dotted,
             exist_any_recursive,
+            needs_node,
         }
     }
 
@@ -108,14 +399,45 @@ impl QueryKeys {
         self.keys.iter()
     }
 
+    /// Whether any key is a filter or depends on array lengths, see [QueryKeys::is_match_in].
+    pub fn needs_node(&self) -> bool {
+        self.needs_node
+    }
+
+    /// Match the keys without a document, see [QueryKey::is_match].
     pub fn is_match(&self, other: &Keys, match_children: bool) -> bool {
+        self.is_match_impl(other, match_children, |key, target, _| key.is_match(target))
+    }
+
+    /// Match keys of a node within `root`.
+    pub fn is_match_in(&self, root: &Node, other: &Keys, match_children: bool) -> bool {
+        if !self.needs_node {
+            return self.is_match(other, match_children);
+        }
+        let mut parents = Vec::with_capacity(other.len());
+        let mut node = Some(root.clone());
+        for key in other.iter() {
+            let child = node.as_ref().and_then(|v| v.get(key));
+            parents.push(node);
+            node = child;
+        }
+        self.is_match_impl(other, match_children, |key, target, j| {
+            key.is_match_in(target, parents[j].as_ref())
+        })
+    }
+
+    fn is_match_impl<F>(&self, other: &Keys, match_children: bool, is_match: F) -> bool
+    where
+        F: Fn(&QueryKey, &KeyOrIndex, usize) -> bool,
+    {
         if !self.exist_any_recursive {
             if self.len() > other.len() || !match_children && self.len() != other.len() {
                 false
             } else {
                 self.iter()
                     .zip(other.iter())
-                    .all(|(v1, v2)| v1.is_match(v2))
+                    .enumerate()
+                    .all(|(j, (v1, v2))| is_match(v1, v2, j))
             }
         } else {
             let keys: Vec<&QueryKey> = self.iter().collect();
@@ -125,26 +447,11 @@ impl QueryKeys {
             'outer: while i < self.len() {
                 let key = keys[i];
                 match key {
-                    QueryKey::Index(_)
-                    | QueryKey::Key(_)
-                    | QueryKey::GlobIndex(_)
-                    | QueryKey::GlobKey(_) => match target_keys.get(j) {
-                        Some(target_key) => {
-                            if key.is_match(target_key) {
-                                j += 1;
-                                i += 1;
-                                continue;
-                            } else {
-                                return false;
-                            }
-                        }
-                        _ => return false,
-                    },
                     QueryKey::AnyRecursive => {
                         if let Some(key) = keys.get(i + 1) {
                             let mut matched_target = false;
                             while let Some(target_key) = target_keys.get(j) {
-                                if key.is_match(target_key) {
+                                if is_match(key, target_key, j) {
                                     matched_target = true;
                                 } else if matched_target {
                                     j -= 1;
@@ -163,6 +470,14 @@ impl QueryKeys {
                             return true;
                         }
                     }
+                    _ => match target_keys.get(j) {
+                        Some(target_key) if is_match(key, target_key, j) => {
+                            j += 1;
+                            i += 1;
+                            continue;
+                        }
+                        _ => return false,
+                    },
                 }
             }
             if match_children {
@@ -187,13 +502,56 @@ impl FromStr for QueryKeys {
         if s.is_empty() || s == "." {
             return Ok(QueryKeys::default());
         }
-        let p = Parser::new(s).parse_keys_only(true);
-        if !p.errors.is_empty() {
-            return Err(p.errors);
+        let mut keys = vec![];
+        let mut plain_start = 0;
+        let mut after_selector = false;
+        let parse_plain = |keys: &mut Vec<QueryKey>, start: usize, end: usize, after_selector| {
+            let plain = &s[start..end];
+            if plain.is_empty() {
+                return Ok(());
+            }
+            if after_selector && !plain.starts_with(['.', '[', '@']) {
+                return Err(vec![error_at(start, end, r#"expect ".""#)]);
+            }
+            let p = Parser::new(plain).parse_keys_only(true);
+            if !p.errors.is_empty() {
+                return Err(p
+                    .errors
+                    .into_iter()
+                    .map(|err| ParserError {
+                        range: err.range + TextSize::from(start as u32),
+                        message: err.message,
+                    })
+                    .collect());
+            }
+            for key in query_keys_from_syntax(&p.into_syntax().into()) {
+                if key == QueryKey::AnyRecursive && keys.last() == Some(&QueryKey::AnyRecursive) {
+                    continue;
+                }
+                keys.push(key);
+            }
+            Ok(())
+        };
+        let mut pos = 0;
+        while pos < s.len() {
+            match s[pos..].chars().next() {
+                Some(c @ ('"' | '\'' | '`')) => pos = skip_string(s, pos, c),
+                Some('[') => match SelectorParser::new(s, pos + 1).parse()? {
+                    Some((key, end)) => {
+                        parse_plain(&mut keys, plain_start, pos, after_selector)?;
+                        keys.push(key);
+                        after_selector = true;
+                        plain_start = end;
+                        pos = end;
+                    }
+                    None => pos += 1,
+                },
+                Some(c) => pos += c.len_utf8(),
+                None => break,
+            }
         }
-        Ok(QueryKeys::new(query_keys_from_syntax(
-            &p.into_syntax().into(),
-        )))
+        parse_plain(&mut keys, plain_start, s.len(), after_selector)?;
+        Ok(QueryKeys::new(keys.into_iter()))
     }
 }
 
@@ -203,6 +561,320 @@ impl Default for QueryKeys {
     }
 }
 
+fn error_at(start: usize, end: usize, message: &str) -> ParserError {
+    ParserError {
+        range: TextRange::new(TextSize::from(start as u32), TextSize::from(end as u32)),
+        message: message.into(),
+    }
+}
+
+/// Returns the position after the string starting at `pos`.
+fn skip_string(s: &str, pos: usize, quote: char) -> usize {
+    let mut escaped = false;
+    for (i, c) in s[pos + 1..].char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' && quote != '`' {
+            escaped = true;
+        } else if c == quote {
+            return pos + 1 + i + 1;
+        }
+    }
+    s.len()
+}
+
+/// Parses the selectors of the extended query syntax inside of brackets,
+/// plain keys, indexes and globs are left to the keys parser.
+struct SelectorParser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> SelectorParser<'a> {
+    fn new(src: &'a str, pos: usize) -> Self {
+        Self { src, pos }
+    }
+
+    /// Returns the selector and the position after the closing bracket,
+    /// or `None` if the brackets contain a plain key.
+    fn parse(mut self) -> Result<Option<(QueryKey, usize)>, Vec<ParserError>> {
+        self.skip_ws();
+        let key = match self.peek() {
+            Some('?') => {
+                self.pos += 1;
+                self.skip_ws();
+                self.expect('(')?;
+                let filter = self.parse_or()?;
+                self.skip_ws();
+                self.expect(')')?;
+                QueryKey::Filter(filter)
+            }
+            Some(':') => self.parse_slice(None)?,
+            _ => {
+                let start = self.pos;
+                let first = match self.parse_item()? {
+                    Some(v) => v,
+                    None => return Ok(None),
+                };
+                self.skip_ws();
+                match (self.peek(), first) {
+                    (Some(':'), QueryKey::Index(v)) => self.parse_slice(Some(v as isize))?,
+                    (Some(':'), QueryKey::ReverseIndex(v)) => {
+                        self.parse_slice(Some(-(v as isize)))?
+                    }
+                    (Some(':'), _) => return Err(vec![self.error_here("expected integer")]),
+                    (Some(','), first) => {
+                        let mut keys = vec![first];
+                        while self.eat(',') {
+                            self.skip_ws();
+                            match self.parse_item()? {
+                                Some(v) => keys.push(v),
+                                None => return Err(vec![self.error_here("expected key")]),
+                            }
+                            self.skip_ws();
+                        }
+                        QueryKey::Union(keys)
+                    }
+                    (_, first @ (QueryKey::ReverseIndex(_) | QueryKey::Regex(_))) => first,
+                    _ => {
+                        // Plain keys are parsed by the keys parser.
+                        self.pos = start;
+                        return Ok(None);
+                    }
+                }
+            }
+        };
+        self.skip_ws();
+        self.expect(']')?;
+        Ok(Some((key, self.pos)))
+    }
+
+    fn parse_slice(&mut self, start: Option<isize>) -> Result<QueryKey, Vec<ParserError>> {
+        self.expect(':')?;
+        self.skip_ws();
+        let end = self.parse_int()?;
+        self.skip_ws();
+        let step = if self.eat(':') {
+            self.skip_ws();
+            self.parse_int()?
+        } else {
+            None
+        };
+        Ok(QueryKey::Slice { start, end, step })
+    }
+
+    /// Parse an index, a key or a regex.
+    fn parse_item(&mut self) -> Result<Option<QueryKey>, Vec<ParserError>> {
+        let start = self.pos;
+        let item = match self.peek() {
+            Some(c @ ('"' | '\'' | '`')) => {
+                self.pos = skip_string(self.src, self.pos, c);
+                let text = &self.src[start..self.pos];
+                match unquote(text) {
+                    Ok(v) if text.len() > 1 && text.ends_with(c) => QueryKey::Key(Key::property(v)),
+                    _ => return Err(vec![error_at(start, self.pos, "invalid string")]),
+                }
+            }
+            Some('/') => {
+                self.pos += 1;
+                let mut pattern = String::new();
+                let mut chars = self.src[self.pos..].char_indices();
+                let end = loop {
+                    match chars.next() {
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, '/')) => pattern.push('/'),
+                            Some((_, c)) => {
+                                pattern.push('\\');
+                                pattern.push(c);
+                            }
+                            None => break None,
+                        },
+                        Some((i, '/')) => break Some(i),
+                        Some((_, c)) => pattern.push(c),
+                        None => break None,
+                    }
+                };
+                let end = match end {
+                    Some(i) => self.pos + i + 1,
+                    None => return Err(vec![error_at(start, self.src.len(), "unclosed regex")]),
+                };
+                self.pos = end;
+                match KeyRegex::new(&pattern) {
+                    Ok(v) => QueryKey::Regex(v),
+                    Err(_) => return Err(vec![error_at(start, end, "invalid regex")]),
+                }
+            }
+            Some('-' | '0'..='9') => match self.parse_int()? {
+                Some(v) if v < 0 => QueryKey::ReverseIndex(v.unsigned_abs()),
+                Some(v) => QueryKey::Index(v as usize),
+                None => return Err(vec![self.error_here("expected integer")]),
+            },
+            Some(c) if c.is_ascii_alphanumeric() || c == '_' => {
+                let text = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_');
+                QueryKey::Key(Key::property(text))
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(item))
+    }
+
+    fn parse_int(&mut self) -> Result<Option<isize>, Vec<ParserError>> {
+        let start = self.pos;
+        self.eat('-');
+        self.take_while(|c| c.is_ascii_digit());
+        let text = &self.src[start..self.pos];
+        if text.is_empty() {
+            return Ok(None);
+        }
+        if !self
+            .peek()
+            .map(|c| c.is_ascii_digit() || c.is_whitespace() || ":,]".contains(c))
+            .unwrap_or(true)
+        {
+            // e.g. `1a`, leave it to the keys parser.
+            self.pos = start;
+            return Ok(None);
+        }
+        text.parse()
+            .map(Some)
+            .map_err(|_| vec![error_at(start, self.pos, "invalid integer")])
+    }
+
+    fn parse_or(&mut self) -> Result<Filter, Vec<ParserError>> {
+        let mut filter = self.parse_and()?;
+        loop {
+            self.skip_ws();
+            if !self.eat_str("||") {
+                return Ok(filter);
+            }
+            let other = self.parse_and()?;
+            filter = Filter::Or(Box::new(filter), Box::new(other));
+        }
+    }
+
+    fn parse_and(&mut self) -> Result<Filter, Vec<ParserError>> {
+        let mut filter = self.parse_unary()?;
+        loop {
+            self.skip_ws();
+            if !self.eat_str("&&") {
+                return Ok(filter);
+            }
+            let other = self.parse_unary()?;
+            filter = Filter::And(Box::new(filter), Box::new(other));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Filter, Vec<ParserError>> {
+        self.skip_ws();
+        if self.eat('!') {
+            return Ok(Filter::Not(Box::new(self.parse_unary()?)));
+        }
+        if self.eat('(') {
+            let filter = self.parse_or()?;
+            self.skip_ws();
+            self.expect(')')?;
+            return Ok(filter);
+        }
+        let start = self.pos;
+        self.expect('@')?;
+        let mut end = self.pos;
+        while let Some(c) = self.src[end..].chars().next() {
+            if matches!(c, '"' | '\'' | '`') {
+                end = skip_string(self.src, end, c);
+            } else if c.is_whitespace() || "=!<>&|)".contains(c) {
+                break;
+            } else {
+                end += c.len_utf8();
+            }
+        }
+        let keys = match &self.src[self.pos..end] {
+            "" => Keys::default(),
+            text => text
+                .parse::<Keys>()
+                .map_err(|_| vec![error_at(start, end, "invalid keys")])?,
+        };
+        self.pos = end;
+        self.skip_ws();
+        let op = if self.eat_str("==") {
+            CompareOp::Eq
+        } else if self.eat_str("!=") {
+            CompareOp::Ne
+        } else if self.eat_str("<=") {
+            CompareOp::Le
+        } else if self.eat_str(">=") {
+            CompareOp::Ge
+        } else if self.eat('<') {
+            CompareOp::Lt
+        } else if self.eat('>') {
+            CompareOp::Gt
+        } else {
+            return Ok(Filter::Exists(keys));
+        };
+        self.skip_ws();
+        let start = self.pos;
+        match self.peek() {
+            Some(c @ ('"' | '\'' | '`')) => self.pos = skip_string(self.src, self.pos, c),
+            _ => {
+                self.take_while(|c| !c.is_whitespace() && !"&|)".contains(c));
+            }
+        }
+        let text = &self.src[start..self.pos];
+        let value = match Node::from_str(text) {
+            Ok(node) if !text.is_empty() && node.is_scalar() => node.to_plain_json(),
+            _ => return Err(vec![error_at(start, self.pos, "expected scalar value")]),
+        };
+        Ok(Filter::Compare { keys, op, value })
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.eat_str(c.encode_utf8(&mut [0; 4]))
+    }
+
+    fn eat_str(&mut self, s: &str) -> bool {
+        if self.src[self.pos..].starts_with(s) {
+            self.pos += s.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), Vec<ParserError>> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(vec![self.error_here(&format!(r#"expected "{c}""#))])
+        }
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if !f(c) {
+                break;
+            }
+            self.pos += c.len_utf8();
+        }
+        &self.src[start..self.pos]
+    }
+
+    fn skip_ws(&mut self) {
+        self.take_while(char::is_whitespace);
+    }
+
+    fn error_here(&self, message: &str) -> ParserError {
+        let end = self
+            .peek()
+            .map(|c| self.pos + c.len_utf8())
+            .unwrap_or(self.pos);
+        error_at(self.pos, end, message)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -273,6 +945,17 @@ mod tests {
         assert_parse_query_keys!("*foo", ".*foo");
         assert_parse_query_keys!("**@foo");
         assert_parse_query_keys!("**.*");
+        assert_parse_query_keys!("foo[-1]", ".foo[-1]");
+        assert_parse_query_keys!("[1:5]");
+        assert_parse_query_keys!("[ : -2 : 2 ]", "[:-2:2]");
+        assert_parse_query_keys!("[/^a\\/b$/]");
+        assert_parse_query_keys!(r#"[0, -1, "a b", /c/].foo"#, r#"[0,-1,"a b",/c/].foo"#);
+        assert_parse_query_keys!(
+            "**[?(@.enabled == true && !(@@mock || @.n < 1.5))]",
+            "**[?(@.enabled == true && !(@@mock || @.n < 1.5))]"
+        );
+        assert_parse_query_keys!("[?(@ != 'a')]", r#"[?(@ != "a")]"#);
+        assert_parse_query_keys!(r#".foo["[a:b]"]"#, r#".foo."[a:b]""#);
     }
 
     #[test]
@@ -282,6 +965,11 @@ mod tests {
         assert!("foo.".parse::<QueryKeys>().is_err());
         assert!("foo.b-*".parse::<QueryKeys>().is_err());
         assert!("foo.b**".parse::<QueryKeys>().is_err());
+        assert!("foo[1:2]bar".parse::<QueryKeys>().is_err());
+        assert!("foo[a:1]".parse::<QueryKeys>().is_err());
+        assert!("foo[/(/]".parse::<QueryKeys>().is_err());
+        assert!("foo[?(@.a ==)]".parse::<QueryKeys>().is_err());
+        assert!("foo[?(@.a == 1]".parse::<QueryKeys>().is_err());
     }
 
     #[test]
@@ -307,5 +995,53 @@ mod tests {
         assert_match_keys!("@foo", "@foo");
         assert_match_keys!("**@foo", ".a.b@foo");
         assert_match_keys!("**@foo", ".a@foo");
+        assert_match_keys!("[1:3]", "[2]");
+        assert_match_keys!("[1:3]", "[3]", false);
+        assert_match_keys!("[::2]", "[4]");
+        assert_match_keys!("[::2]", "[3]", false);
+        assert_match_keys!("[/^a+$/]", ".aaa");
+        assert_match_keys!("[/^a+$/]", ".ab", false);
+        assert_match_keys!("[0,b]", ".b");
+        assert_match_keys!("[0,b]", "[0]");
+        assert_match_keys!("[-1]", "[0]", false);
+    }
+
+    #[test]
+    fn test_matches_all() {
+        let node: Node = r#"{
+  items: [
+    { name: "a", enabled: true, n: 1 },
+    { name: "b", enabled: false, n: 2 }, @mock
+    { name: "c", enabled: true, n: 3 @mock },
+  ],
+  "x.y": 1
+}"#
+        .parse()
+        .unwrap();
+        let query = |keys: &str| {
+            node.matches_all(keys.parse().unwrap(), false)
+                .unwrap()
+                .map(|(k, _)| k.to_string())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(
+            query("items[?(@.enabled == true)]"),
+            [".items[0]", ".items[2]"]
+        );
+        assert_eq!(
+            query("items[?(@.n >= 2 && @.enabled)]"),
+            [".items[1]", ".items[2]"]
+        );
+        assert_eq!(query("items[?(!@.enabled)].name"), Vec::<String>::new());
+        assert_eq!(
+            query("items[?(@.enabled != true)].name"),
+            [".items[1].name"]
+        );
+        assert_eq!(query("items[-1].name"), [".items[2].name"]);
+        assert_eq!(query("items[-2:].n"), [".items[1].n", ".items[2].n"]);
+        assert_eq!(query("items[::-2]"), [".items[0]", ".items[2]"]);
+        assert_eq!(query("**[?(@@mock)]"), [".items[1]", ".items[2].n"]);
+        assert_eq!(query("items[*][?(@@mock == null)]"), [".items[2].n"]);
+        assert_eq!(query(r#"[/\./]"#), [r#"."x.y""#]);
     }
 }
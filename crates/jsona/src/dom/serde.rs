@@ -1,6 +1,8 @@
 //! Serde for dom node, ignore annotations
 
-use super::node::{self, Node};
+use super::error::ExportError;
+use super::keys::Keys;
+use super::node::{self, Node, NonFinite};
 use crate::dom::node::Key;
 use serde::{
     de::Visitor,
@@ -35,7 +37,10 @@ impl Serialize for Node {
             }
             Node::Bool(v) => ser.serialize_bool(v.value()),
             Node::String(v) => ser.serialize_str(v.value()),
-            Node::Number(v) => v.value().serialize(ser),
+            Node::Number(v) => match v.non_finite() {
+                Some(non_finite) => ser.serialize_f64(non_finite.as_f64()),
+                None => v.value().serialize(ser),
+            },
             Node::Null(_) => ser.serialize_unit(),
         }
     }
@@ -76,16 +81,7 @@ impl<'de> Visitor<'de> for JsonaVisitor {
     where
         E: serde::de::Error,
     {
-        let value = match JsonNumber::from_f64(v) {
-            Some(n) => n,
-            None => {
-                return Err(serde::de::Error::invalid_value(
-                    serde::de::Unexpected::Float(v),
-                    &self,
-                ))
-            }
-        };
-        Ok(node::Number::new(value, None).into())
+        Ok(node::Number::from_f64(v, None).into())
     }
 
     fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
@@ -196,11 +192,94 @@ impl<'de> Deserialize<'de> for Node {
     }
 }
 
+/// How NaN and infinities are exported to JSON.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NonFiniteMode {
+    /// Export as `null`.
+    #[default]
+    Null,
+    /// Export as `"NaN"`, `"Infinity"` or `"-Infinity"`.
+    String,
+    /// Fail with [ExportError::NonFiniteNumber].
+    Error,
+}
+
 impl Node {
+    /// Convert to JSON, NaN and infinities become `null`.
     pub fn to_plain_json(&self) -> Value {
         serde_json::to_value(self).unwrap()
     }
+
+    /// Convert to JSON, NaN and infinities are mapped according to `mode`.
+    pub fn to_json(&self, mode: NonFiniteMode) -> Result<Value, ExportError> {
+        to_json_impl(self, &Keys::default(), mode)
+    }
     pub fn from_plain_json(value: Value) -> Self {
         serde_json::from_value(value).unwrap()
     }
 }
+
+fn to_json_impl(node: &Node, keys: &Keys, mode: NonFiniteMode) -> Result<Value, ExportError> {
+    let value = match node {
+        Node::Number(v) => match (v.non_finite(), mode) {
+            (None, _) | (Some(_), NonFiniteMode::Null) => node.to_plain_json(),
+            (Some(v), NonFiniteMode::String) => {
+                let text = match v {
+                    NonFinite::NaN => "NaN",
+                    NonFinite::Infinity => "Infinity",
+                    NonFinite::NegInfinity => "-Infinity",
+                };
+                Value::String(text.into())
+            }
+            (Some(_), NonFiniteMode::Error) => {
+                return Err(ExportError::NonFiniteNumber { keys: keys.clone() })
+            }
+        },
+        Node::Array(v) => Value::Array(
+            v.value()
+                .read()
+                .iter()
+                .enumerate()
+                .map(|(i, v)| to_json_impl(v, &keys.join(i), mode))
+                .collect::<Result<_, _>>()?,
+        ),
+        Node::Object(v) => Value::Object(
+            v.value()
+                .read()
+                .iter()
+                .map(|(k, v)| {
+                    Ok((
+                        k.value().to_string(),
+                        to_json_impl(v, &keys.join(k.clone()), mode)?,
+                    ))
+                })
+                .collect::<Result<_, _>>()?,
+        ),
+        _ => node.to_plain_json(),
+    };
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_to_json_non_finite() {
+        let node = Node::from_str("{ a: [1, nan], b: inf, c: -inf }").unwrap();
+        assert_eq!(
+            node.to_plain_json(),
+            json!({ "a": [1, null], "b": null, "c": null })
+        );
+        assert_eq!(
+            node.to_json(NonFiniteMode::String).unwrap(),
+            json!({ "a": [1, "NaN"], "b": "Infinity", "c": "-Infinity" })
+        );
+        match node.to_json(NonFiniteMode::Error) {
+            Err(ExportError::NonFiniteNumber { keys }) => assert_eq!(keys.to_string(), ".a[1]"),
+            v => panic!("unexpected {v:?}"),
+        }
+    }
+}
@@ -0,0 +1,277 @@
+//! Plain JSON export with a source map back to the JSONA source.
+//!
+//! Tools consuming the exported JSON report problems against JSON Pointers, paths or
+//! offsets of the JSON text. A [SourceMap] translates them to ranges of the original source.
+//!
+//! ```
+//! use jsona::dom::Node;
+//! use jsona::util::mapper::Mapper;
+//!
+//! let source = "{\n  a: 1, // comment\n  b: [true, 'x'],\n}";
+//! let node: Node = source.parse().unwrap();
+//! let (json, map) = node.to_plain_json_with_source_map(false);
+//! assert_eq!(json, r#"{"a":1,"b":[true,"x"]}"#);
+//!
+//! let mapper = Mapper::new_utf16(source, true);
+//! let entry = map.lookup_pointer("/b/1").unwrap();
+//! assert_eq!(entry.mapper_range(&mapper).unwrap().start.line, 3);
+//!
+//! let offset = json.find("true").unwrap() as u32;
+//! let entry = map.lookup_offset(offset.into()).unwrap();
+//! assert_eq!(entry.keys.to_string(), ".b[0]");
+//! ```
+
+use super::{Keys, Node};
+use crate::util::mapper;
+
+use rowan::{TextRange, TextSize};
+use std::str::FromStr;
+
+/// A value of the exported JSON and where it comes from.
+#[derive(Debug, Clone)]
+pub struct SourceMapEntry {
+    /// Path of the value.
+    pub keys: Keys,
+    /// JSON Pointer of the value.
+    pub pointer: String,
+    /// Range of the value in the JSON text.
+    pub output: TextRange,
+    /// Range of the property key in the JSON text.
+    pub output_key: Option<TextRange>,
+    /// Range of the value in the source, `None` for nodes without syntax.
+    pub source: Option<TextRange>,
+    /// Range of the property key in the source.
+    pub source_key: Option<TextRange>,
+}
+
+impl SourceMapEntry {
+    /// Range of the value in the source, as line and column.
+    pub fn mapper_range(&self, mapper: &mapper::Mapper) -> Option<mapper::Range> {
+        self.source.and_then(|v| mapper.range(v))
+    }
+
+    /// Range of the property key in the source, as line and column.
+    pub fn mapper_key_range(&self, mapper: &mapper::Mapper) -> Option<mapper::Range> {
+        self.source_key.and_then(|v| mapper.range(v))
+    }
+}
+
+/// Maps the values of an exported JSON text to their source.
+///
+/// Entries are ordered by their position in the JSON text, parents before children.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    entries: Vec<SourceMapEntry>,
+}
+
+impl SourceMap {
+    pub fn entries(&self) -> &[SourceMapEntry] {
+        &self.entries
+    }
+
+    /// Find the value with the given JSON Pointer.
+    pub fn lookup_pointer(&self, pointer: &str) -> Option<&SourceMapEntry> {
+        self.entries.iter().find(|v| v.pointer == pointer)
+    }
+
+    /// Find the value at the given keys.
+    pub fn lookup_keys(&self, keys: &Keys) -> Option<&SourceMapEntry> {
+        self.lookup_pointer(&keys.to_pointer())
+    }
+
+    /// Find the value at a path like `.a.b[0]`, a leading `$` as in `$.a.b[0]` is allowed.
+    pub fn lookup_path(&self, path: &str) -> Option<&SourceMapEntry> {
+        let path = path.strip_prefix('$').unwrap_or(path);
+        if path.is_empty() {
+            return self.entries.first();
+        }
+        let keys = Keys::from_str(path).ok()?;
+        self.lookup_keys(&keys)
+    }
+
+    /// Find the innermost value whose text or property key contains the offset of the JSON text.
+    ///
+    /// Offsets reported as line and column can be converted with a [Mapper](mapper::Mapper)
+    /// of the JSON text.
+    pub fn lookup_offset(&self, offset: TextSize) -> Option<&SourceMapEntry> {
+        let end = self.entries.partition_point(|v| entry_start(v) <= offset);
+        self.entries[..end]
+            .iter()
+            .rev()
+            .find(|v| v.output.contains(offset) || v.output_key.is_some_and(|k| k.contains(offset)))
+    }
+}
+
+fn entry_start(entry: &SourceMapEntry) -> TextSize {
+    match entry.output_key {
+        Some(k) => k.start(),
+        None => entry.output.start(),
+    }
+}
+
+impl Node {
+    /// Export as JSON text like [to_plain_json](Node::to_plain_json), together with a
+    /// [SourceMap] of every value. `pretty` indents the text like
+    /// `serde_json::to_string_pretty`.
+    pub fn to_plain_json_with_source_map(&self, pretty: bool) -> (String, SourceMap) {
+        let mut writer = Writer {
+            output: String::new(),
+            entries: vec![],
+            pretty,
+        };
+        writer.write(self, Keys::default(), None, 0);
+        (
+            writer.output,
+            SourceMap {
+                entries: writer.entries,
+            },
+        )
+    }
+}
+
+struct Writer {
+    output: String,
+    entries: Vec<SourceMapEntry>,
+    pretty: bool,
+}
+
+impl Writer {
+    fn offset(&self) -> TextSize {
+        TextSize::from(self.output.len() as u32)
+    }
+
+    fn write(&mut self, node: &Node, keys: Keys, output_key: Option<TextRange>, depth: usize) {
+        let index = self.entries.len();
+        let start = self.offset();
+        self.entries.push(SourceMapEntry {
+            pointer: keys.to_pointer(),
+            output: TextRange::empty(start),
+            output_key,
+            source: node.text_range(),
+            source_key: keys.last_text_range(),
+            keys: keys.clone(),
+        });
+        match node {
+            Node::Array(v) => {
+                let items = v.value().read();
+                self.output.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    self.write_separator(i, depth + 1);
+                    self.write(item, keys.join(i), None, depth + 1);
+                }
+                self.write_close(']', items.is_empty(), depth);
+            }
+            Node::Object(v) => {
+                let properties = v.value().read();
+                self.output.push('{');
+                for (i, (key, value)) in properties.iter().enumerate() {
+                    self.write_separator(i, depth + 1);
+                    let key_start = self.offset();
+                    self.output
+                        .push_str(&serde_json::to_string(key.value()).unwrap());
+                    let key_range = TextRange::new(key_start, self.offset());
+                    self.output.push(':');
+                    if self.pretty {
+                        self.output.push(' ');
+                    }
+                    self.write(value, keys.join(key.clone()), Some(key_range), depth + 1);
+                }
+                self.write_close('}', properties.is_empty(), depth);
+            }
+            _ => self.output.push_str(&serde_json::to_string(node).unwrap()),
+        }
+        self.entries[index].output = TextRange::new(start, self.offset());
+    }
+
+    fn write_separator(&mut self, index: usize, depth: usize) {
+        if index > 0 {
+            self.output.push(',');
+        }
+        self.write_indent(depth);
+    }
+
+    fn write_close(&mut self, c: char, empty: bool, depth: usize) {
+        if !empty {
+            self.write_indent(depth);
+        }
+        self.output.push(c);
+    }
+
+    fn write_indent(&mut self, depth: usize) {
+        if self.pretty {
+            self.output.push('\n');
+            self.output.push_str(&"  ".repeat(depth));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"{
+  a: 1, @mock
+  b: {
+    "c d": [nan, 'x', {}],
+  },
+  e: [],
+}"#;
+
+    #[test]
+    fn test_source_map_output() {
+        let node: Node = SOURCE.parse().unwrap();
+        let (json, map) = node.to_plain_json_with_source_map(false);
+        assert_eq!(json, serde_json::to_string(&node.to_plain_json()).unwrap());
+        let (json, _) = node.to_plain_json_with_source_map(true);
+        assert_eq!(
+            json,
+            serde_json::to_string_pretty(&node.to_plain_json()).unwrap()
+        );
+
+        let pointers: Vec<&str> = map.entries().iter().map(|v| v.pointer.as_str()).collect();
+        assert_eq!(
+            pointers,
+            ["", "/a", "/b", "/b/c d", "/b/c d/0", "/b/c d/1", "/b/c d/2", "/e"]
+        );
+    }
+
+    #[test]
+    fn test_source_map_lookup() {
+        let node: Node = SOURCE.parse().unwrap();
+        let (json, map) = node.to_plain_json_with_source_map(true);
+        let source_text = |entry: &SourceMapEntry| &SOURCE[entry.source.unwrap()];
+
+        assert_eq!(source_text(map.lookup_pointer("/b/c d/1").unwrap()), "'x'");
+        assert_eq!(
+            source_text(map.lookup_path("$.b[\"c d\"][0]").unwrap()),
+            "nan"
+        );
+        assert_eq!(map.lookup_path("$").unwrap().pointer, "");
+        assert!(map.lookup_pointer("/a/0").is_none());
+
+        let offset = |text: &str| TextSize::from(json.find(text).unwrap() as u32);
+        let entry = map.lookup_offset(offset("\"x\"")).unwrap();
+        assert_eq!(entry.keys.to_string(), r#".b."c d"[1]"#);
+        let entry = map.lookup_offset(offset("\"c d\"")).unwrap();
+        assert_eq!(entry.pointer, "/b/c d");
+        assert_eq!(&SOURCE[entry.source_key.unwrap()], "\"c d\"");
+        let entry = map.lookup_offset(offset("{}")).unwrap();
+        assert_eq!(entry.pointer, "/b/c d/2");
+        let entry = map.lookup_offset(offset("[]") + TextSize::from(1)).unwrap();
+        assert_eq!(entry.pointer, "/e");
+        assert_eq!(map.lookup_offset(0.into()).unwrap().pointer, "");
+
+        let mapper = mapper::Mapper::new_utf16(SOURCE, false);
+        let range = map
+            .lookup_pointer("/e")
+            .unwrap()
+            .mapper_range(&mapper)
+            .unwrap();
+        assert_eq!((range.start.line, range.start.column), (5, 5));
+
+        let node: Node = r#"{ "@id": 1, "a/b": 2 }"#.parse().unwrap();
+        let (_, map) = node.to_plain_json_with_source_map(false);
+        assert!(map.lookup_pointer("/@id").is_some());
+        assert!(map.lookup_pointer("/a~1b").is_some());
+    }
+}
@@ -30,9 +30,9 @@ fn write_value(scope: Scope, value: &Node) {
             } else {
                 scope.write("[");
             }
-            if v.inner.items.read().len() == 0
+            if v.inner.items.read().is_empty()
                 && v.annotations()
-                    .map(|v| v.value().read().len() == 0)
+                    .map(|v| v.value().read().is_empty())
                     .unwrap_or(true)
             {
                 scope.write("],");
@@ -57,12 +57,12 @@ fn write_value(scope: Scope, value: &Node) {
             } else {
                 scope.write("{");
             }
-            if v.inner.properties.read().len() == 0
+            if v.inner.properties.read().is_empty()
                 && v.annotations()
-                    .map(|v| v.value().read().len() == 0)
+                    .map(|v| v.value().read().is_empty())
                     .unwrap_or(true)
             {
-                scope.write("],");
+                scope.write("},");
             } else {
                 let scope = scope.enter(ScopeKind::Object);
                 write_annotations(scope.clone(), v.annotations());
@@ -134,3 +134,21 @@ fn write_annotations(scope: Scope, annotations: Option<&Annotations>) {
 fn write_scalar_annotation<T: Display>(scope: Scope, key: &Key, value: T) {
     scope.write(format!(" {}({})", key, value));
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::dom::Node;
+
+    fn to_string(s: &str) -> String {
+        s.parse::<Node>().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_empty_object() {
+        assert_eq!(to_string("{}"), "{}");
+        assert_eq!(to_string("{ a: {} }"), "{\n  a: {},\n}");
+        assert_eq!(to_string("[{}, []]"), "[\n  {},\n  [],\n]");
+        assert_eq!(to_string("{} @foo"), "{ @foo\n}");
+        assert_eq!(to_string("{ a: {} @bar(1) }"), "{\n  a: { @bar(1)\n  },\n}");
+    }
+}
@@ -1,3 +1,30 @@
+//! Walking DOM trees.
+//!
+//! A [Visitor] yields the nodes of a tree depth-first and lazily, so the walk stops as soon
+//! as the iterator is dropped. [transform] rebuilds a tree, rewriting or dropping nodes.
+//!
+//! ```
+//! use jsona::dom::{transform, Node, Number, TransformControl, VisitControl, Visitor};
+//!
+//! let node: Node = "{ a: 1, b: [2, 3] @deprecated }".parse().unwrap();
+//!
+//! let found = Visitor::new(&node, &(), |_, _, _| VisitControl::AddIter)
+//!     .into_iter()
+//!     .find(|(_, v)| v.is_integer());
+//! assert_eq!(found.unwrap().0.to_string(), ".a");
+//!
+//! let node = transform(&node, |keys, node| match node.as_number() {
+//!     Some(v) => TransformControl::Replace(Number::from_f64(v.as_f64() * 10.0, None).into()),
+//!     None if keys.to_string() == ".b@deprecated" => TransformControl::Remove,
+//!     None => TransformControl::Keep,
+//! })
+//! .unwrap();
+//! assert_eq!(node.to_canonical_string(), r#"{"a":10,"b":[20,30]}"#);
+//! ```
+
+use super::node::{
+    Annotations, ArrayInner, BoolInner, Map, NullInner, NumberInner, ObjectInner, StringInner,
+};
 use super::{DomNode, Keys, Node};
 
 type VisitFn<'a, T> = Box<dyn Fn(&Keys, &Node, &T) -> VisitControl + 'a>;
@@ -32,60 +59,240 @@ pub enum VisitControl {
 
 impl<'a, T> IntoIterator for Visitor<'a, T> {
     type Item = (Keys, Node);
-    type IntoIter = std::vec::IntoIter<(Keys, Node)>;
+    type IntoIter = VisitIter<'a, T>;
 
     fn into_iter(self) -> Self::IntoIter {
-        let mut all = vec![];
-        collect(Keys::default(), self.node, self.state, &mut all, &self.f);
-        all.into_iter()
+        VisitIter {
+            stack: vec![(Keys::default(), self.node.clone())],
+            state: self.state,
+            f: self.f,
+        }
     }
 }
 
-fn collect<T>(
-    keys: Keys,
-    node: &Node,
-    state: &T,
-    all: &mut Vec<(Keys, Node)>,
-    f: &dyn Fn(&Keys, &Node, &T) -> VisitControl,
-) {
-    match f(&keys, node, state) {
-        VisitControl::AddIter => {
-            all.push((keys.clone(), node.clone()));
+/// Depth-first iterator over the nodes accepted by a [Visitor].
+///
+/// Nodes are visited on demand, a node comes before its children,
+/// which come before its annotations.
+pub struct VisitIter<'a, T> {
+    stack: Vec<(Keys, Node)>,
+    state: &'a T,
+    f: VisitFn<'a, T>,
+}
+
+impl<'a, T> Iterator for VisitIter<'a, T> {
+    type Item = (Keys, Node);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((keys, node)) = self.stack.pop() {
+            let (add, iter) = match (self.f)(&keys, &node, self.state) {
+                VisitControl::AddIter => (true, true),
+                VisitControl::NotAddIter => (false, true),
+                VisitControl::AddNotIter => (true, false),
+                VisitControl::NotAddNotIter => (false, false),
+            };
+            if iter {
+                self.push_children(&keys, &node);
+            }
+            if add {
+                return Some((keys, node));
+            }
         }
-        VisitControl::NotAddIter => {}
-        VisitControl::AddNotIter => {
-            all.push((keys.clone(), node.clone()));
-            return;
+        None
+    }
+}
+
+impl<'a, T> VisitIter<'a, T> {
+    fn push_children(&mut self, keys: &Keys, node: &Node) {
+        let start = self.stack.len();
+        match node {
+            Node::Object(obj) => {
+                let props = obj.inner.properties.read();
+                for (key, node) in props.iter() {
+                    self.stack.push((keys.join(key.clone()), node.clone()));
+                }
+            }
+            Node::Array(arr) => {
+                let items = arr.inner.items.read();
+                for (idx, node) in items.iter().enumerate() {
+                    self.stack.push((keys.join(idx), node.clone()));
+                }
+            }
+            _ => {}
         }
-        VisitControl::NotAddNotIter => {
-            return;
+        if let Some(annotations) = node.annotations() {
+            let map = annotations.value().read();
+            for (key, node) in map.iter() {
+                self.stack.push((keys.join(key.clone()), node.clone()));
+            }
         }
+        self.stack[start..].reverse();
     }
-    match node {
-        Node::Object(obj) => {
-            let props = obj.inner.properties.read();
-            for (key, node) in props.iter() {
-                collect(keys.join(key.clone()), node, state, all, &f);
+}
+
+/// What [transform] does with a node.
+#[derive(Debug, Clone)]
+pub enum TransformControl {
+    /// Keep the node.
+    Keep,
+    /// Replace the node, the replacement is not transformed again.
+    Replace(Node),
+    /// Drop the node, or the property, item or annotation holding it.
+    Remove,
+}
+
+/// Build a new tree by rewriting or dropping the nodes of the given one.
+///
+/// The nodes are visited depth-first after their children and annotations,
+/// so `f` sees them already transformed. Keys refer to the original tree.
+/// Returns `None` if the root is removed.
+///
+/// Unchanged subtrees are shared with the original tree, rebuilt nodes have no syntax.
+pub fn transform(node: &Node, mut f: impl FnMut(&Keys, &Node) -> TransformControl) -> Option<Node> {
+    transform_node(&Keys::default(), node, &mut f)
+}
+
+impl Node {
+    /// Build a new tree with [transform].
+    pub fn transform(&self, f: impl FnMut(&Keys, &Node) -> TransformControl) -> Option<Node> {
+        transform(self, f)
+    }
+}
+
+fn transform_node(
+    keys: &Keys,
+    node: &Node,
+    f: &mut dyn FnMut(&Keys, &Node) -> TransformControl,
+) -> Option<Node> {
+    let mut changed = false;
+    let mut transform_map = |map: &Map, changed: &mut bool| {
+        let mut output = Map::default();
+        for (key, (value, syntax)) in map.value.iter() {
+            match transform_node(&keys.join(key.clone()), value, f) {
+                Some(new_value) => {
+                    *changed |= !is_same(value, &new_value);
+                    output.add(key.clone(), new_value, syntax.clone());
+                }
+                None => *changed = true,
             }
         }
-        Node::Array(arr) => {
-            let items = arr.inner.items.read();
-            for (idx, node) in items.iter().enumerate() {
-                collect(keys.join(idx), node, state, all, &f);
+        output
+    };
+    let annotations = node
+        .annotations()
+        .map(|v| transform_map(&v.value().read(), &mut changed));
+    let properties = node
+        .as_object()
+        .map(|v| transform_map(&v.value().read(), &mut changed));
+    let items = node.as_array().map(|v| {
+        let mut output = vec![];
+        for (idx, value) in v.value().read().iter().enumerate() {
+            match transform_node(&keys.join(idx), value, f) {
+                Some(new_value) => {
+                    changed |= !is_same(value, &new_value);
+                    output.push(new_value);
+                }
+                None => changed = true,
             }
         }
-        _ => {}
+        output
+    });
+
+    let node = if changed {
+        let annotations = annotations.and_then(|v| (!v.is_empty()).then(|| Annotations::new(v)));
+        rebuild(node, annotations, properties, items)
+    } else {
+        node.clone()
+    };
+
+    match f(keys, &node) {
+        TransformControl::Keep => Some(node),
+        TransformControl::Replace(v) => Some(v),
+        TransformControl::Remove => None,
     }
+}
 
-    if let Some(annotations) = node.annotations() {
-        let map = annotations.value().read();
-        for (key, node) in map.iter() {
-            collect(keys.join(key.clone()), node, state, all, &f);
+/// Whether both nodes share the same data.
+pub(crate) fn is_same(a: &Node, b: &Node) -> bool {
+    macro_rules! same {
+        ($($elm:ident,)*) => {
+            match (a, b) {
+                $((Node::$elm(a), Node::$elm(b)) => std::rc::Rc::ptr_eq(&a.inner, &b.inner),)*
+                _ => false,
+            }
+        };
+    }
+    same!(Null, Bool, Number, String, Array, Object,)
+}
+
+/// Create a detached copy of the node with other annotations.
+pub(crate) fn with_annotations(node: &Node, annotations: Option<Annotations>) -> Node {
+    let properties = node.as_object().map(|v| (**v.value().read()).clone());
+    let items = node.as_array().map(|v| (**v.value().read()).clone());
+    rebuild(node, annotations, properties, items)
+}
+
+/// Create a detached copy of the node with new annotations and children.
+pub(crate) fn rebuild(
+    node: &Node,
+    annotations: Option<Annotations>,
+    properties: Option<Map>,
+    items: Option<Vec<Node>>,
+) -> Node {
+    match node {
+        Node::Null(_) => NullInner {
+            errors: Default::default(),
+            syntax: None,
+            node_syntax: None,
+            annotations,
         }
+        .into_node(),
+        Node::Bool(v) => BoolInner {
+            errors: Default::default(),
+            syntax: None,
+            node_syntax: None,
+            annotations,
+            value: v.value().into(),
+        }
+        .into_node(),
+        Node::Number(v) => NumberInner {
+            errors: Default::default(),
+            syntax: None,
+            node_syntax: None,
+            annotations,
+            repr: v.repr(),
+            non_finite: v.non_finite(),
+            value: v.value().clone().into(),
+        }
+        .into_node(),
+        Node::String(v) => StringInner {
+            errors: Default::default(),
+            syntax: None,
+            node_syntax: None,
+            annotations,
+            value: v.value().to_string().into(),
+        }
+        .into_node(),
+        Node::Array(_) => ArrayInner {
+            errors: Default::default(),
+            syntax: None,
+            node_syntax: None,
+            annotations,
+            items: items.unwrap_or_default().into(),
+        }
+        .into_node(),
+        Node::Object(_) => ObjectInner {
+            errors: Default::default(),
+            syntax: None,
+            node_syntax: None,
+            annotations,
+            properties: properties.unwrap_or_default().into(),
+        }
+        .into_node(),
     }
 }
 
-pub fn visit_annotations(node: &Node) -> Visitor<()> {
+pub fn visit_annotations(node: &Node) -> Visitor<'_, ()> {
     Visitor::new(node, &(), |keys, _, _| {
         if keys
             .last()
@@ -98,3 +305,57 @@ pub fn visit_annotations(node: &Node) -> Visitor<()> {
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::Bool;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_visit_lazy() {
+        let node: Node = "{ a: [1, { b: 2 }] @x(3), c: 4 } @y".parse().unwrap();
+        let all: Vec<String> = Visitor::new(&node, &(), |_, _, _| VisitControl::AddIter)
+            .into_iter()
+            .map(|(keys, _)| keys.to_string())
+            .collect();
+        assert_eq!(
+            all,
+            ["", ".a", ".a[0]", ".a[1]", ".a[1].b", ".a@x", ".c", "@y"]
+        );
+
+        let calls = Cell::new(0);
+        let found = Visitor::new(&node, &calls, |_, _, calls| {
+            calls.set(calls.get() + 1);
+            VisitControl::AddIter
+        })
+        .into_iter()
+        .find(|(_, v)| v.is_integer());
+        assert_eq!(found.unwrap().0.to_string(), ".a[0]");
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[test]
+    fn test_transform() {
+        let node: Node = "{ a: [1, 2, 3] @x(1), b: { c: 'x' @y }, d: null }"
+            .parse()
+            .unwrap();
+        let output = node
+            .transform(|keys, node| match node {
+                Node::Number(v) if v.as_i128() == Some(2) => TransformControl::Remove,
+                Node::Null(_) if keys.to_string() == ".d" => TransformControl::Remove,
+                Node::Null(_) => TransformControl::Replace(Bool::new(true, None).into()),
+                _ => TransformControl::Keep,
+            })
+            .unwrap();
+        assert_eq!(
+            output.to_canonical_string(),
+            r#"{"a":[@x(1) 1,3],"b":{"c":"x" @y(true)}}"#
+        );
+
+        let unchanged = node.transform(|_, _| TransformControl::Keep).unwrap();
+        assert!(is_same(&node, &unchanged));
+        assert!(unchanged.syntax().is_some());
+        assert!(node.transform(|_, _| TransformControl::Remove).is_none());
+    }
+}
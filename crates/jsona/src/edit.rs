@@ -0,0 +1,898 @@
+//! Format-preserving editing of JSONA documents.
+//!
+//! A [Document] applies every change as a small [TextEdit] to the source
+//! and reparses only the affected block with [reparse], so comments, whitespace,
+//! quote styles and number representations outside of the change are kept as is.
+//!
+//! ```
+//! use jsona::dom::{Keys, Node};
+//! use jsona::edit::Document;
+//! use std::str::FromStr;
+//!
+//! let mut doc = Document::from_str(r#"{
+//!   name: 'foo', // the name
+//!   version: "1.0.0", @mock
+//! }"#).unwrap();
+//!
+//! doc.set_value(&Keys::from_str("version").unwrap(), &Node::from_str(r#""1.1.0""#).unwrap())
+//!     .unwrap();
+//! doc.insert_property(&Keys::default(), "private", &Node::from_str("true").unwrap())
+//!     .unwrap();
+//!
+//! assert_eq!(doc.to_string(), r#"{
+//!   name: 'foo', // the name
+//!   version: "1.1.0", @mock
+//!   private: true,
+//! }"#);
+//! ```
+
+use crate::dom::{DomNode, Key, Keys, Node};
+use crate::parser::{self, reparse_with_options, Parse, ParseOptions, TextEdit};
+use crate::ser::{write_compact, write_compact_annotations};
+use crate::syntax::{SyntaxElement, SyntaxKind::*, SyntaxNode};
+use once_cell::unsync::OnceCell;
+use rowan::{TextRange, TextSize};
+use std::str::FromStr;
+use thiserror::Error;
+
+const DEFAULT_INDENT: &str = "  ";
+
+#[derive(Debug, Clone, Error)]
+pub enum Error {
+    #[error("the document has syntax errors")]
+    InvalidSyntax { errors: Vec<parser::Error> },
+    #[error("no node at {0}")]
+    NotFound(Keys),
+    #[error("the node at {0} is not an object")]
+    NotObject(Keys),
+    #[error("the node at {0} is not an array")]
+    NotArray(Keys),
+    #[error("the node at {0} already exists")]
+    AlreadyExists(Keys),
+    #[error("the index {index} is out of bounds at {keys}")]
+    OutOfBounds { keys: Keys, index: usize },
+    #[error("the edit at {0} produced invalid syntax")]
+    InvalidEdit(Keys),
+}
+
+/// An editable JSONA document.
+#[derive(Debug, Clone)]
+pub struct Document {
+    parse: Parse,
+    options: ParseOptions,
+    dom: OnceCell<Node>,
+}
+
+impl Document {
+    /// Create a document from a parse, which must not have any syntax errors.
+    pub fn new(parse: Parse) -> Result<Self, Error> {
+        Self::with_options(parse, ParseOptions::default())
+    }
+
+    /// Create a document from a parse with the options it was parsed with,
+    /// edits are reparsed with the same options.
+    pub fn with_options(parse: Parse, options: ParseOptions) -> Result<Self, Error> {
+        if !parse.errors.is_empty() {
+            return Err(Error::InvalidSyntax {
+                errors: parse.errors,
+            });
+        }
+        Ok(Self {
+            parse,
+            options,
+            dom: Default::default(),
+        })
+    }
+
+    /// The current parse of the document.
+    pub fn parse(&self) -> &Parse {
+        &self.parse
+    }
+
+    pub fn syntax(&self) -> SyntaxNode {
+        self.parse.clone().into_syntax()
+    }
+
+    /// The DOM of the current document.
+    pub fn root(&self) -> Node {
+        self.dom
+            .get_or_init(|| self.parse.clone().into_dom())
+            .clone()
+    }
+
+    /// Replace the value at `keys`.
+    ///
+    /// Annotations of an object or array are written inside of its brackets, so they
+    /// are replaced together with it. Annotations following any other value are kept,
+    /// the annotations of `value` itself are ignored in that case.
+    pub fn set_value(&mut self, keys: &Keys, value: &Node) -> Result<(), Error> {
+        let node = self.node(keys)?;
+        let text = self.text();
+        let range = match node.syntax() {
+            Some(syntax) => syntax.text_range(),
+            None => node
+                .node_syntax()
+                .and_then(|v| v.as_node().cloned())
+                .and_then(|v| significant_range(&v))
+                .ok_or_else(|| Error::NotFound(keys.clone()))?,
+        };
+        let indent = line_indent(&text, range.start());
+        let value = render_value(value, indent, &indent_unit(&text));
+        self.apply(keys, TextEdit::new(range, value))
+    }
+
+    /// Append the property to the object at `keys`.
+    pub fn insert_property(&mut self, keys: &Keys, key: &str, value: &Node) -> Result<(), Error> {
+        let object = self.node(keys)?;
+        let object = object
+            .as_object()
+            .ok_or_else(|| Error::NotObject(keys.clone()))?;
+        let key = Key::property(key);
+        if object.get(&key).is_some() {
+            return Err(Error::AlreadyExists(keys.join(key)));
+        }
+        let syntax = compose_syntax(object.syntax(), keys)?;
+        let key = key.to_string();
+        self.insert_element(
+            keys,
+            &syntax,
+            None,
+            |value_text| format!("{key}: {value_text}"),
+            value,
+        )
+    }
+
+    /// Remove the property from the object at `keys`, returns the removed value.
+    pub fn remove_property(&mut self, keys: &Keys, key: &str) -> Result<Node, Error> {
+        let object = self.node(keys)?;
+        let object = object
+            .as_object()
+            .ok_or_else(|| Error::NotObject(keys.clone()))?;
+        let key = Key::property(key);
+        let value = object
+            .get(&key)
+            .ok_or_else(|| Error::NotFound(keys.join(key.clone())))?;
+        let property = object
+            .value()
+            .read()
+            .syntax(&key)
+            .and_then(|v| v.into_node())
+            .ok_or_else(|| Error::NotFound(keys.join(key.clone())))?;
+        self.remove_element(keys, &property)?;
+        Ok(value)
+    }
+
+    /// Insert the item into the array at `keys` before `index`,
+    /// the item is appended if `index` equals the length of the array.
+    pub fn insert_item(&mut self, keys: &Keys, index: usize, value: &Node) -> Result<(), Error> {
+        let array = self.node(keys)?;
+        let array = array
+            .as_array()
+            .ok_or_else(|| Error::NotArray(keys.clone()))?;
+        let len = array.value().read().len();
+        if index > len {
+            return Err(Error::OutOfBounds {
+                keys: keys.clone(),
+                index,
+            });
+        }
+        let syntax = compose_syntax(array.syntax(), keys)?;
+        let before = if index < len {
+            array
+                .get(index)
+                .and_then(|v| v.node_syntax().cloned())
+                .and_then(|v| v.into_node())
+        } else {
+            None
+        };
+        self.insert_element(keys, &syntax, before, |value_text| value_text, value)
+    }
+
+    /// Remove the item at `index` from the array at `keys`, returns the removed value.
+    pub fn remove_item(&mut self, keys: &Keys, index: usize) -> Result<Node, Error> {
+        let array = self.node(keys)?;
+        let array = array
+            .as_array()
+            .ok_or_else(|| Error::NotArray(keys.clone()))?;
+        let value = array.get(index).ok_or_else(|| Error::OutOfBounds {
+            keys: keys.clone(),
+            index,
+        })?;
+        let item = value
+            .node_syntax()
+            .cloned()
+            .and_then(|v| v.into_node())
+            .ok_or_else(|| Error::NotFound(keys.join(index)))?;
+        self.remove_element(keys, &item)?;
+        Ok(value)
+    }
+
+    /// Add the annotation `name` to the node at `keys`.
+    ///
+    /// The annotation is written without value if `value` is null.
+    pub fn add_annotation(&mut self, keys: &Keys, name: &str, value: &Node) -> Result<(), Error> {
+        let node = self.node(keys)?;
+        let key = annotation_key(name);
+        if node.annotations().and_then(|v| v.get(&key)).is_some() {
+            return Err(Error::AlreadyExists(keys.join(key)));
+        }
+        let mut annotation = format!(" {key}");
+        if !value.is_null() {
+            annotation.push('(');
+            write_compact(&mut annotation, value);
+            annotation.push(')');
+        }
+        let value_syntax = node
+            .node_syntax()
+            .cloned()
+            .and_then(|v| v.into_node())
+            .ok_or_else(|| Error::NotFound(keys.clone()))?;
+        let offset = match node.syntax().and_then(|v| v.as_node()) {
+            // Objects and arrays keep annotations after the opening bracket.
+            Some(compose) if compose.kind().is_compose() => {
+                match compose.children().find(|v| v.kind() == ANNOTATIONS) {
+                    Some(annotations) => significant_end(&annotations),
+                    None => compose
+                        .first_token()
+                        .map(|v| v.text_range().end())
+                        .ok_or_else(|| Error::NotFound(keys.clone()))?,
+                }
+            }
+            _ => significant_end(&value_syntax),
+        };
+        self.apply(keys, TextEdit::insert(offset, annotation))
+    }
+
+    /// Remove the annotation `name` from the node at `keys`, returns the removed value.
+    pub fn remove_annotation(&mut self, keys: &Keys, name: &str) -> Result<Node, Error> {
+        let node = self.node(keys)?;
+        let key = annotation_key(name);
+        let annotations = node
+            .annotations()
+            .ok_or_else(|| Error::NotFound(keys.join(key.clone())))?;
+        let value = annotations
+            .get(&key)
+            .ok_or_else(|| Error::NotFound(keys.join(key.clone())))?;
+        let syntax = annotations
+            .annotation_syntax(&key)
+            .and_then(|v| v.into_node())
+            .ok_or_else(|| Error::NotFound(keys.join(key.clone())))?;
+        let text = self.text();
+        let range = significant_range(&syntax).unwrap();
+        let start = usize::from(range.start());
+        let end = usize::from(range.end());
+        let edit = if starts_line(&text, start)
+            && text[end..]
+                .trim_start_matches([' ', '\t'])
+                .starts_with('\n')
+        {
+            // Remove the whole line.
+            let end = skip_line_end(&text, end);
+            TextEdit::delete(text_range(line_start(&text, start), end))
+        } else {
+            let start =
+                start - (text[..start].len() - text[..start].trim_end_matches([' ', '\t']).len());
+            TextEdit::delete(text_range(start, end))
+        };
+        self.apply(keys, edit)?;
+        Ok(value)
+    }
+
+    fn node(&self, keys: &Keys) -> Result<Node, Error> {
+        self.root()
+            .path(keys)
+            .ok_or_else(|| Error::NotFound(keys.clone()))
+    }
+
+    fn text(&self) -> String {
+        self.syntax().to_string()
+    }
+
+    /// Apply the edit if the document stays valid.
+    ///
+    /// Only the affected block is reparsed, debug builds check that the result
+    /// is the same as a full parse of the new text.
+    fn apply(&mut self, keys: &Keys, edit: TextEdit) -> Result<(), Error> {
+        let parse = reparse_with_options(&self.parse, &edit, &self.options);
+        if !parse.errors.is_empty() {
+            return Err(Error::InvalidEdit(keys.clone()));
+        }
+        debug_assert!(
+            {
+                let mut text = self.text();
+                edit.apply(&mut text);
+                parser::parse_with_options(&text, &self.options).green_node == parse.green_node
+            },
+            "the reparsed tree differs from a full parse"
+        );
+        self.parse = parse;
+        self.dom = Default::default();
+        Ok(())
+    }
+
+    /// Insert an element before `before`, or append it if `before` is `None`.
+    fn insert_element<F>(
+        &mut self,
+        keys: &Keys,
+        compose: &SyntaxNode,
+        before: Option<SyntaxNode>,
+        element: F,
+        value: &Node,
+    ) -> Result<(), Error>
+    where
+        F: Fn(String) -> String,
+    {
+        let text = self.text();
+        let unit = indent_unit(&text);
+        let elements = compose_elements(compose);
+        let (open, close) = brackets(compose).ok_or_else(|| Error::NotFound(keys.clone()))?;
+        let multiline = match elements.first() {
+            Some(first) => !same_line(&text, open.end(), first.text_range().start()),
+            None => text[usize::from(open.end())..usize::from(close.start())].contains('\n'),
+        };
+        let indent = match elements.first() {
+            Some(first) if multiline => line_indent(&text, first.text_range().start()).to_string(),
+            _ => format!("{}{}", line_indent(&text, close.start()), unit),
+        };
+        let (value_text, annotations) = render_element(value, &indent, &unit);
+        let element_text =
+            |comma: &str| format!("{}{comma}{annotations}", element(value_text.clone()));
+
+        let edit = match (before, elements.last()) {
+            (Some(before), _) => {
+                let offset = before.text_range().start();
+                if multiline {
+                    TextEdit::insert(offset, format!("{}\n{indent}", element_text(",")))
+                } else {
+                    TextEdit::insert(offset, format!("{} ", element_text(",")))
+                }
+            }
+            (None, Some(last)) => {
+                let value_end = element_value(last).map(|v| v.text_range().end());
+                let has_comma = element_comma(last).is_some();
+                let end = significant_end(last);
+                if multiline {
+                    let offset = skip_trailing_comment(&text, usize::from(end));
+                    // Keep the trailing comma style of the last element.
+                    let comma = if has_comma { "," } else { "" };
+                    let insert = format!("\n{indent}{}", element_text(comma));
+                    match value_end {
+                        Some(value_end) if !has_comma => {
+                            let start = usize::from(value_end);
+                            TextEdit::new(
+                                text_range(start, offset),
+                                format!(",{}{insert}", &text[start..offset]),
+                            )
+                        }
+                        _ => TextEdit::insert(text_size(offset), insert),
+                    }
+                } else if has_comma {
+                    TextEdit::insert(end, format!(" {}", element_text(",")))
+                } else {
+                    TextEdit::insert(end, format!(", {}", element_text("")))
+                }
+            }
+            (None, None) => {
+                let offset = compose
+                    .children()
+                    .find(|v| v.kind() == ANNOTATIONS)
+                    .map(|v| significant_end(&v))
+                    .unwrap_or_else(|| open.end());
+                let has_annotations = offset != open.end();
+                if multiline {
+                    TextEdit::insert(offset, format!("\n{indent}{}", element_text("")))
+                } else {
+                    let close_start = usize::from(close.start());
+                    let sep = if has_annotations { " " } else { "" };
+                    TextEdit::new(
+                        text_range(usize::from(offset), close_start),
+                        format!("{sep}{}", element_text("")),
+                    )
+                }
+            }
+        };
+        self.apply(keys, edit)
+    }
+
+    fn remove_element(&mut self, keys: &Keys, element: &SyntaxNode) -> Result<(), Error> {
+        let text = self.text();
+        let compose = element
+            .parent()
+            .ok_or_else(|| Error::NotFound(keys.clone()))?;
+        let elements = compose_elements(&compose);
+        let index = elements
+            .iter()
+            .position(|v| v == element)
+            .ok_or_else(|| Error::NotFound(keys.clone()))?;
+        let range = significant_range(element).ok_or_else(|| Error::NotFound(keys.clone()))?;
+        let start = usize::from(range.start());
+        let end = usize::from(range.end());
+        let next = elements.get(index + 1);
+
+        let next_start = next.map(|v| usize::from(v.text_range().start()));
+        let line_end = skip_trailing_comment(&text, end);
+        let edit = if starts_line(&text, start) && ends_line(&text, line_end) {
+            // The element is alone on its line.
+            TextEdit::delete(text_range(
+                line_start(&text, start),
+                skip_line_end(&text, line_end),
+            ))
+        } else if let Some(next_start) = next_start.filter(|v| !text[end..*v].contains('\n')) {
+            // Another element follows on the same line.
+            TextEdit::delete(text_range(start, next_start))
+        } else if next_start.is_some() {
+            // The element ends its line, keep the line break.
+            let start = start - (text[..start].len() - text[..start].trim_end().len());
+            let start = start.max(line_start(&text, usize::from(range.start())));
+            TextEdit::delete(text_range(start, line_end))
+        } else if let Some(prev) = index.checked_sub(1).and_then(|i| elements.get(i)) {
+            let prev_end = match element_comma(prev) {
+                Some(comma) if comma.text_range().end() == significant_end(prev) => {
+                    comma.text_range().start()
+                }
+                _ => significant_end(prev),
+            };
+            TextEdit::delete(text_range(usize::from(prev_end), end))
+        } else {
+            let offset = compose
+                .children()
+                .find(|v| v.kind() == ANNOTATIONS)
+                .map(|v| significant_end(&v))
+                .or_else(|| compose.first_token().map(|v| v.text_range().end()))
+                .ok_or_else(|| Error::NotFound(keys.clone()))?;
+            TextEdit::delete(text_range(usize::from(offset), end))
+        };
+        self.apply(keys, edit)
+    }
+}
+
+impl TryFrom<Parse> for Document {
+    type Error = Error;
+
+    fn try_from(parse: Parse) -> Result<Self, Self::Error> {
+        Self::new(parse)
+    }
+}
+
+impl FromStr for Document {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(parser::parse(s))
+    }
+}
+
+impl core::fmt::Display for Document {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.syntax().fmt(f)
+    }
+}
+
+fn annotation_key(name: &str) -> Key {
+    if name.starts_with('@') {
+        Key::annotation(name)
+    } else {
+        Key::annotation(format!("@{name}"))
+    }
+}
+
+fn compose_syntax(syntax: Option<&SyntaxElement>, keys: &Keys) -> Result<SyntaxNode, Error> {
+    syntax
+        .and_then(|v| v.as_node())
+        .cloned()
+        .ok_or_else(|| Error::NotFound(keys.clone()))
+}
+
+/// The properties of an object or the items of an array.
+fn compose_elements(compose: &SyntaxNode) -> Vec<SyntaxNode> {
+    compose
+        .children()
+        .filter(|v| matches!(v.kind(), PROPERTY | VALUE))
+        .collect()
+}
+
+fn brackets(compose: &SyntaxNode) -> Option<(TextRange, TextRange)> {
+    let open = compose.first_token()?;
+    let close = compose.last_token()?;
+    if !matches!(close.kind(), BRACE_END | BRACKET_END) {
+        return None;
+    }
+    Some((open.text_range(), close.text_range()))
+}
+
+/// The `VALUE` node of a property or array item.
+fn element_value_node(element: &SyntaxNode) -> Option<SyntaxNode> {
+    if element.kind() == VALUE {
+        Some(element.clone())
+    } else {
+        element.children().find(|v| v.kind() == VALUE)
+    }
+}
+
+/// The value of a property or array item, without comma and annotations.
+fn element_value(element: &SyntaxNode) -> Option<SyntaxNode> {
+    element_value_node(element)?
+        .children()
+        .find(|v| matches!(v.kind(), SCALAR | OBJECT | ARRAY))
+}
+
+fn element_comma(element: &SyntaxNode) -> Option<SyntaxElement> {
+    element_value_node(element)?
+        .children_with_tokens()
+        .find(|v| v.kind() == COMMA)
+}
+
+/// The range from the first to the last token that is not whitespace or a comment.
+fn significant_range(node: &SyntaxNode) -> Option<TextRange> {
+    let mut tokens = node
+        .descendants_with_tokens()
+        .filter_map(|v| v.into_token())
+        .filter(|v| !v.kind().is_ws_or_comment());
+    let first = tokens.next()?.text_range();
+    let last = tokens.last().map(|v| v.text_range()).unwrap_or(first);
+    Some(first.cover(last))
+}
+
+fn significant_end(node: &SyntaxNode) -> TextSize {
+    significant_range(node)
+        .map(|v| v.end())
+        .unwrap_or_else(|| node.text_range().start())
+}
+
+fn text_size(offset: usize) -> TextSize {
+    TextSize::from(offset as u32)
+}
+
+fn text_range(start: usize, end: usize) -> TextRange {
+    TextRange::new(text_size(start), text_size(end))
+}
+
+fn line_start(text: &str, offset: usize) -> usize {
+    text[..offset].rfind('\n').map(|v| v + 1).unwrap_or(0)
+}
+
+fn starts_line(text: &str, offset: usize) -> bool {
+    text[line_start(text, offset)..offset].trim().is_empty()
+}
+
+/// Whether only whitespace follows `offset` until the end of the line or text.
+fn ends_line(text: &str, offset: usize) -> bool {
+    let rest = text[offset..].trim_start_matches([' ', '\t']);
+    rest.is_empty() || rest.starts_with(['\r', '\n'])
+}
+
+fn same_line(text: &str, start: TextSize, end: TextSize) -> bool {
+    !text[usize::from(start)..usize::from(end)].contains('\n')
+}
+
+/// The leading whitespace of the line containing `offset`.
+fn line_indent(text: &str, offset: TextSize) -> &str {
+    let line = &text[line_start(text, usize::from(offset))..];
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+/// The indentation of the first indented line, or two spaces.
+fn indent_unit(text: &str) -> String {
+    text.lines()
+        .map(|line| &line[..line.len() - line.trim_start_matches([' ', '\t']).len()])
+        .find(|indent| !indent.is_empty())
+        .unwrap_or(DEFAULT_INDENT)
+        .to_string()
+}
+
+/// Skip whitespace and a comment until the end of the line.
+fn skip_trailing_comment(text: &str, offset: usize) -> usize {
+    let rest = &text[offset..];
+    let trimmed = rest.trim_start_matches([' ', '\t']);
+    let offset = offset + rest.len() - trimmed.len();
+    if trimmed.starts_with("//") {
+        offset + trimmed.find(['\r', '\n']).unwrap_or(trimmed.len())
+    } else if trimmed.starts_with("/*") {
+        match trimmed.find("*/") {
+            Some(end) if !trimmed[..end].contains('\n') => offset + end + 2,
+            _ => offset,
+        }
+    } else {
+        offset
+    }
+}
+
+/// Skip whitespace and the line break after it, if any.
+fn skip_line_end(text: &str, offset: usize) -> usize {
+    let rest = &text[offset..];
+    let trimmed = rest.trim_start_matches([' ', '\t']);
+    let offset = offset + rest.len() - trimmed.len();
+    if trimmed.starts_with("\r\n") {
+        offset + 2
+    } else if trimmed.starts_with('\n') {
+        offset + 1
+    } else {
+        offset
+    }
+}
+
+/// Render a value as an element, returns the value and the annotations following it.
+fn render_element(node: &Node, indent: &str, unit: &str) -> (String, String) {
+    let value = render_value(node, indent, unit);
+    let mut annotations = String::new();
+    if !matches!(node, Node::Object(_) | Node::Array(_)) {
+        write_compact_annotations(&mut annotations, node, false);
+    }
+    (value, annotations)
+}
+
+/// Render a value, annotations of an object or array are rendered inside of its brackets.
+fn render_value(node: &Node, indent: &str, unit: &str) -> String {
+    let mut output = String::new();
+    write_value(&mut output, node, indent, unit);
+    output
+}
+
+fn write_value(output: &mut String, node: &Node, indent: &str, unit: &str) {
+    let (open, close) = match node {
+        Node::Object(_) => ("{", "}"),
+        Node::Array(_) => ("[", "]"),
+        _ => {
+            output.push_str(&node.scalar_text().unwrap_or_default());
+            return;
+        }
+    };
+    let inner_indent = format!("{indent}{unit}");
+    output.push_str(open);
+    write_compact_annotations(output, node, false);
+    let mut elements: Vec<(Option<Key>, Node)> = vec![];
+    match node {
+        Node::Object(v) => elements.extend(
+            v.value()
+                .read()
+                .iter()
+                .map(|(k, v)| (Some(k.clone()), v.clone())),
+        ),
+        Node::Array(v) => elements.extend(v.value().read().iter().map(|v| (None, v.clone()))),
+        _ => {}
+    }
+    if elements.is_empty() {
+        if output.ends_with(open) {
+            output.push_str(close);
+        } else {
+            output.push_str(&format!("\n{indent}{close}"));
+        }
+        return;
+    }
+    let len = elements.len();
+    for (i, (key, value)) in elements.into_iter().enumerate() {
+        output.push('\n');
+        output.push_str(&inner_indent);
+        if let Some(key) = key {
+            output.push_str(&format!("{key}: "));
+        }
+        let (value, annotations) = render_element(&value, &inner_indent, unit);
+        output.push_str(&value);
+        if i + 1 < len {
+            output.push(',');
+        }
+        output.push_str(&annotations);
+    }
+    output.push('\n');
+    output.push_str(indent);
+    output.push_str(close);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"{ @describe("package")
+  name: 'foo', // the name
+  version: 0x10, @mock
+  tags: [
+    "a", // first
+    "b"
+  ],
+  inline: [1, 2 @x],
+  empty: {},
+  emptyMultiline: {
+  },
+  last: true
+}
+"#;
+
+    fn keys(s: &str) -> Keys {
+        Keys::from_str(s).unwrap()
+    }
+
+    fn node(s: &str) -> Node {
+        Node::from_str(s).unwrap()
+    }
+
+    macro_rules! assert_edit {
+        ($edit:expr, $expect:expr) => {
+            let mut doc = Document::from_str(SOURCE).unwrap();
+            #[allow(clippy::redundant_closure_call)]
+            ($edit)(&mut doc).unwrap();
+            let expect = $expect;
+            assert_eq!(doc.to_string(), expect);
+            let parse = parser::parse(&expect);
+            assert!(parse.errors.is_empty(), "{:?}", parse.errors);
+            assert_eq!(doc.parse().green_node, parse.green_node);
+        };
+    }
+
+    #[test]
+    fn test_set_value() {
+        assert_edit!(
+            |doc: &mut Document| doc.set_value(&keys("version"), &node("17")),
+            SOURCE.replace("0x10", "17")
+        );
+        assert_edit!(
+            |doc: &mut Document| doc.set_value(&keys("tags[1]"), &node("{ x: [1, 2], y: {} }")),
+            SOURCE.replace(
+                r#"    "b""#,
+                "    {\n      x: [\n        1,\n        2\n      ],\n      y: {}\n    }"
+            )
+        );
+        assert_edit!(
+            |doc: &mut Document| doc.set_value(&keys("@describe"), &node("`package`")),
+            SOURCE.replace(r#"@describe("package")"#, "@describe(`package`)")
+        );
+    }
+
+    #[test]
+    fn test_insert_property() {
+        assert_edit!(
+            |doc: &mut Document| doc.insert_property(&Keys::default(), "new key", &node("1 @x")),
+            SOURCE.replace("  last: true\n", "  last: true,\n  \"new key\": 1 @x\n")
+        );
+        assert_edit!(
+            |doc: &mut Document| doc.insert_property(&keys("empty"), "a", &node("[]")),
+            SOURCE.replace("empty: {}", "empty: {a: []}")
+        );
+        assert_edit!(
+            |doc: &mut Document| doc.insert_property(&keys("emptyMultiline"), "a", &node("1")),
+            SOURCE.replace("emptyMultiline: {\n", "emptyMultiline: {\n    a: 1\n")
+        );
+        let mut doc = Document::from_str(SOURCE).unwrap();
+        assert!(matches!(
+            doc.insert_property(&Keys::default(), "name", &node("1")),
+            Err(Error::AlreadyExists(_))
+        ));
+        assert!(matches!(
+            doc.insert_property(&keys("name"), "a", &node("1")),
+            Err(Error::NotObject(_))
+        ));
+    }
+
+    #[test]
+    fn test_remove_property() {
+        assert_edit!(
+            |doc: &mut Document| doc.remove_property(&Keys::default(), "name").map(|_| ()),
+            SOURCE.replace("  name: 'foo', // the name\n", "")
+        );
+        assert_edit!(
+            |doc: &mut Document| doc.remove_property(&Keys::default(), "last").map(|_| ()),
+            SOURCE.replace("  last: true\n", "")
+        );
+        let mut doc = Document::from_str(SOURCE).unwrap();
+        let value = doc.remove_property(&Keys::default(), "version").unwrap();
+        assert_eq!(value.to_plain_json(), serde_json::json!(16));
+    }
+
+    #[test]
+    fn test_remove_shared_line() {
+        let source = "{\n  a: 1, b: 2,\n  c: 3\n}";
+        let mut doc = Document::from_str(source).unwrap();
+        doc.remove_property(&Keys::default(), "a").unwrap();
+        assert_eq!(doc.to_string(), "{\n  b: 2,\n  c: 3\n}");
+        let mut doc = Document::from_str(source).unwrap();
+        doc.remove_property(&Keys::default(), "b").unwrap();
+        assert_eq!(doc.to_string(), "{\n  a: 1,\n  c: 3\n}");
+    }
+
+    #[test]
+    fn test_invalid_syntax() {
+        assert!(matches!(
+            Document::new(parser::parse("{ a: }")),
+            Err(Error::InvalidSyntax { .. })
+        ));
+    }
+
+    #[test]
+    fn test_dialect() {
+        let options = ParseOptions {
+            dialect: parser::Dialect::Json,
+        };
+        let source = r#"{"a": [1, 2]}"#;
+        let mut doc =
+            Document::with_options(parser::parse_with_options(source, &options), options).unwrap();
+        doc.insert_item(&keys("a"), 2, &node("3")).unwrap();
+        assert_eq!(doc.to_string(), r#"{"a": [1, 2, 3]}"#);
+        assert!(matches!(
+            doc.insert_item(&keys("a"), 0, &node("0x1")),
+            Err(Error::InvalidEdit(_))
+        ));
+        assert_eq!(doc.to_string(), r#"{"a": [1, 2, 3]}"#);
+
+        let mut doc = Document::from_str(source).unwrap();
+        doc.insert_item(&keys("a"), 0, &node("0x1")).unwrap();
+        assert_eq!(doc.to_string(), r#"{"a": [0x1, 1, 2]}"#);
+    }
+
+    #[test]
+    fn test_insert_item() {
+        assert_edit!(
+            |doc: &mut Document| doc.insert_item(&keys("tags"), 2, &node("'c'")),
+            SOURCE.replace("    \"b\"\n", "    \"b\",\n    'c'\n")
+        );
+        assert_edit!(
+            |doc: &mut Document| doc.insert_item(&keys("tags"), 0, &node("'c'")),
+            SOURCE.replace("    \"a\"", "    'c',\n    \"a\"")
+        );
+        assert_edit!(
+            |doc: &mut Document| doc.insert_item(&keys("inline"), 2, &node("3")),
+            SOURCE.replace("[1, 2 @x]", "[1, 2 @x, 3]")
+        );
+        assert_edit!(
+            |doc: &mut Document| doc.insert_item(&keys("inline"), 1, &node("3")),
+            SOURCE.replace("[1, 2 @x]", "[1, 3, 2 @x]")
+        );
+        let mut doc = Document::from_str(SOURCE).unwrap();
+        assert!(matches!(
+            doc.insert_item(&keys("inline"), 3, &node("1")),
+            Err(Error::OutOfBounds { index: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn test_remove_item() {
+        assert_edit!(
+            |doc: &mut Document| doc.remove_item(&keys("tags"), 0).map(|_| ()),
+            SOURCE.replace("    \"a\", // first\n", "")
+        );
+        assert_edit!(
+            |doc: &mut Document| doc.remove_item(&keys("inline"), 1).map(|_| ()),
+            SOURCE.replace("[1, 2 @x]", "[1]")
+        );
+        assert_edit!(
+            |doc: &mut Document| doc.remove_item(&keys("inline"), 0).map(|_| ()),
+            SOURCE.replace("[1, 2 @x]", "[2 @x]")
+        );
+    }
+
+    #[test]
+    fn test_annotations() {
+        assert_edit!(
+            |doc: &mut Document| doc.add_annotation(&keys("name"), "example", &node("'bar'")),
+            SOURCE.replace("'foo',", "'foo', @example('bar')")
+        );
+        assert_edit!(
+            |doc: &mut Document| doc.add_annotation(&keys("last"), "@mock", &node("null")),
+            SOURCE.replace("last: true", "last: true @mock")
+        );
+        assert_edit!(
+            |doc: &mut Document| doc.add_annotation(&keys("tags"), "unique", &node("null")),
+            SOURCE.replace("tags: [", "tags: [ @unique")
+        );
+        assert_edit!(
+            |doc: &mut Document| doc.add_annotation(&Keys::default(), "x", &node("{a: [1]}")),
+            SOURCE.replace(
+                r#"@describe("package")"#,
+                r#"@describe("package") @x({a:[1]})"#
+            )
+        );
+        assert_edit!(
+            |doc: &mut Document| doc.remove_annotation(&keys("version"), "mock").map(|_| ()),
+            SOURCE.replace("0x10, @mock", "0x10,")
+        );
+        assert_edit!(
+            |doc: &mut Document| doc
+                .remove_annotation(&Keys::default(), "@describe")
+                .map(|_| ()),
+            SOURCE.replace(r#"{ @describe("package")"#, "{")
+        );
+        let mut doc = Document::from_str(SOURCE).unwrap();
+        assert!(matches!(
+            doc.add_annotation(&keys("version"), "mock", &node("null")),
+            Err(Error::AlreadyExists(_))
+        ));
+    }
+}
@@ -29,22 +29,17 @@ impl Error {
                 .flat_map(|err| {
                     let message = err.to_string();
                     match err {
-                        DomError::ConflictingKeys { key, other_key } => {
+                        DomError::ConflictingKeys { key, other_key }
+                        | DomError::DuplicateKeys { key, other_key } => {
+                            let kind = match err {
+                                DomError::ConflictingKeys { .. } => "ConflictingKeys",
+                                _ => "DuplicateKeys",
+                            };
                             let key_range = key.mapper_range(mapper);
                             let other_key_range = other_key.mapper_range(mapper);
                             vec![
-                                ErrorObject::new(
-                                    ERROR_SOURCE,
-                                    "ConflictingKeys",
-                                    message.clone(),
-                                    key_range,
-                                ),
-                                ErrorObject::new(
-                                    ERROR_SOURCE,
-                                    "ConflictingKeys",
-                                    message,
-                                    other_key_range,
-                                ),
+                                ErrorObject::new(ERROR_SOURCE, kind, message.clone(), key_range),
+                                ErrorObject::new(ERROR_SOURCE, kind, message, other_key_range),
                             ]
                         }
                         DomError::InvalidNode { syntax } => {
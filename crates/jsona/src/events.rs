@@ -0,0 +1,656 @@
+//! Pull parser that reports JSONA documents as a stream of events.
+//!
+//! Unlike [parse](crate::parser::parse), the [EventReader] does not build a syntax tree,
+//! it reads the input in chunks and only keeps the current token in memory,
+//! so it can process inputs that are much larger than the available memory.
+//!
+//! ```
+//! use jsona::events::{Event, EventReader};
+//!
+//! let source = r#"{ a: 1, @foo("bar") }"#;
+//! let events: Vec<_> = EventReader::new(source.as_bytes())
+//!     .collect::<Result<_, _>>()
+//!     .unwrap();
+//!
+//! assert!(matches!(&events[0], Event::StartObject { .. }));
+//! assert!(matches!(&events[1], Event::Key { text, .. } if text == "a"));
+//! assert!(matches!(&events[2], Event::Scalar { text, .. } if text == "1"));
+//! assert!(matches!(&events[3], Event::StartAnnotation { name, .. } if name == "@foo"));
+//! assert!(matches!(&events[4], Event::Scalar { text, .. } if text == r#""bar""#));
+//! assert!(matches!(&events[5], Event::EndAnnotation { .. }));
+//! assert!(matches!(&events[6], Event::EndObject { .. }));
+//! ```
+
+use crate::parser::{validates, Dialect};
+use crate::syntax::{SyntaxKind, SyntaxKind::*};
+use crate::util::validate_quote;
+use logos::Logos;
+use std::io::Read;
+use std::ops::Range;
+use thiserror::Error;
+
+const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Tokens ending closer than this to the end of the buffer are lexed again
+/// after more input is read, since the lexer may need to look ahead, e.g. `1.5e+5`.
+const LOOKAHEAD: usize = 4;
+
+/// An event emitted by the [EventReader].
+///
+/// Spans are byte offsets in the whole input.
+///
+/// Annotations are reported between [Event::StartAnnotation] and [Event::EndAnnotation],
+/// with the events of the annotation value in between if it has one.
+/// An annotation directly following [Event::StartObject] or [Event::StartArray]
+/// belongs to that object or array, otherwise it belongs to the value before it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    StartObject {
+        span: Range<u64>,
+    },
+    EndObject {
+        span: Range<u64>,
+    },
+    StartArray {
+        span: Range<u64>,
+    },
+    EndArray {
+        span: Range<u64>,
+    },
+    /// An object key, `text` is the raw text of the key including quotes.
+    Key {
+        kind: SyntaxKind,
+        text: String,
+        span: Range<u64>,
+    },
+    /// An annotation key, `name` includes the leading `@`.
+    StartAnnotation {
+        name: String,
+        span: Range<u64>,
+    },
+    /// End of an annotation, the span is empty if the annotation has no value.
+    EndAnnotation {
+        span: Range<u64>,
+    },
+    /// A scalar value, `text` is the raw text of the value including quotes.
+    Scalar {
+        kind: SyntaxKind,
+        text: String,
+        span: Range<u64>,
+    },
+}
+
+impl Event {
+    pub fn span(&self) -> Range<u64> {
+        match self {
+            Event::StartObject { span }
+            | Event::EndObject { span }
+            | Event::StartArray { span }
+            | Event::EndArray { span }
+            | Event::Key { span, .. }
+            | Event::StartAnnotation { span, .. }
+            | Event::EndAnnotation { span }
+            | Event::Scalar { span, .. } => span.clone(),
+        }
+    }
+}
+
+/// An error that can occur while reading events.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("{message} ({span:?})")]
+    InvalidSyntax { span: Range<u64>, message: String },
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+impl Error {
+    fn syntax(span: Range<u64>, message: &str) -> Self {
+        Error::InvalidSyntax {
+            span,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Expect a value.
+    Value,
+    /// After a value, `comma` tells if the separating comma was found.
+    AfterValue {
+        comma: bool,
+    },
+    /// Expect an object key or the end of the object.
+    Key {
+        first: bool,
+    },
+    /// Expect an array item or the end of the array.
+    Item {
+        first: bool,
+    },
+    /// Expect the annotation value or `)`.
+    AnnotationValue,
+    Finished,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Scope {
+    Object,
+    Array,
+    /// The state to resume after the annotation.
+    Annotation(State),
+}
+
+#[derive(Debug)]
+struct Token {
+    kind: SyntaxKind,
+    text: String,
+    span: Range<u64>,
+}
+
+/// A pull parser yielding [Event]s from any [Read] source.
+///
+/// The syntax is validated as the input is read, the iterator
+/// ends after the first error.
+pub struct EventReader<R> {
+    input: R,
+    chunk_size: usize,
+    buf: String,
+    pending: Vec<u8>,
+    pos: usize,
+    offset: u64,
+    eof: bool,
+    peeked: Option<Token>,
+    queued: Option<Event>,
+    stack: Vec<Scope>,
+    state: State,
+}
+
+impl<R: Read> EventReader<R> {
+    pub fn new(input: R) -> Self {
+        Self {
+            input,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            buf: String::new(),
+            pending: Vec::new(),
+            pos: 0,
+            offset: 0,
+            eof: false,
+            peeked: None,
+            queued: None,
+            stack: Vec::new(),
+            state: State::Value,
+        }
+    }
+
+    /// Set the number of bytes read from the input at a time.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Read the next event, `None` is returned at the end of the document.
+    pub fn next_event(&mut self) -> Result<Option<Event>, Error> {
+        if let Some(event) = self.queued.take() {
+            return Ok(Some(event));
+        }
+        let ret = self.read_event();
+        if !matches!(ret, Ok(Some(_))) {
+            self.state = State::Finished;
+        }
+        ret
+    }
+
+    fn read_event(&mut self) -> Result<Option<Event>, Error> {
+        loop {
+            match self.state {
+                State::Finished => return Ok(None),
+                State::Value => {
+                    let token = match self.next_token()? {
+                        Some(token) => token,
+                        None if self.stack.is_empty() => return Ok(None),
+                        None => return Err(self.eof_error()),
+                    };
+                    return self.read_value(token).map(Some);
+                }
+                State::AfterValue { comma } => {
+                    let (kind, span) = match self.peek_token()? {
+                        Some(token) => token,
+                        None if self.stack.is_empty() => return Ok(None),
+                        None => return Err(self.eof_error()),
+                    };
+                    if kind == ANNOTATION_KEY {
+                        return self.read_annotation().map(Some);
+                    }
+                    match self.stack.last().copied() {
+                        None => {
+                            return Err(Error::syntax(span, "expect EOF"));
+                        }
+                        Some(Scope::Annotation(resume)) => {
+                            let token = self.next_token()?.unwrap();
+                            if kind != PARENTHESES_END {
+                                return Err(Error::syntax(token.span, r#"expected ")""#));
+                            }
+                            self.stack.pop();
+                            self.state = resume;
+                            return Ok(Some(Event::EndAnnotation { span: token.span }));
+                        }
+                        Some(Scope::Object) => match kind {
+                            COMMA if !comma => {
+                                self.next_token()?;
+                                self.state = State::AfterValue { comma: true };
+                            }
+                            BRACE_END => {
+                                let token = self.next_token()?.unwrap();
+                                return Ok(Some(
+                                    self.end_compose(Event::EndObject { span: token.span }),
+                                ));
+                            }
+                            _ if comma => self.state = State::Key { first: false },
+                            COMMA => return Err(Error::syntax(span, r#"unexpected ",""#)),
+                            _ => return Err(Error::syntax(span, r#"expected ",""#)),
+                        },
+                        Some(Scope::Array) => match kind {
+                            COMMA if !comma => {
+                                self.next_token()?;
+                                self.state = State::AfterValue { comma: true };
+                            }
+                            BRACKET_END => {
+                                let token = self.next_token()?.unwrap();
+                                return Ok(Some(
+                                    self.end_compose(Event::EndArray { span: token.span }),
+                                ));
+                            }
+                            _ if comma => self.state = State::Item { first: false },
+                            COMMA => return Err(Error::syntax(span, r#"unexpected ",""#)),
+                            _ => return Err(Error::syntax(span, r#"expected ",""#)),
+                        },
+                    }
+                }
+                State::Key { first } => {
+                    let token = match self.next_token()? {
+                        Some(token) => token,
+                        None => return Err(self.eof_error()),
+                    };
+                    match token.kind {
+                        ANNOTATION_KEY if first => {
+                            self.peeked = Some(token);
+                            return self.read_annotation().map(Some);
+                        }
+                        BRACE_END => {
+                            return Ok(Some(
+                                self.end_compose(Event::EndObject { span: token.span }),
+                            ));
+                        }
+                        COMMA => return Err(Error::syntax(token.span, r#"unexpected ",""#)),
+                        kind => {
+                            validate_key(&token)?;
+                            self.expect_colon()?;
+                            self.state = State::Value;
+                            return Ok(Some(Event::Key {
+                                kind,
+                                text: token.text,
+                                span: token.span,
+                            }));
+                        }
+                    }
+                }
+                State::Item { first } => {
+                    let (kind, span) = match self.peek_token()? {
+                        Some(token) => token,
+                        None => return Err(self.eof_error()),
+                    };
+                    match kind {
+                        ANNOTATION_KEY if first => return self.read_annotation().map(Some),
+                        BRACKET_END => {
+                            let token = self.next_token()?.unwrap();
+                            return Ok(Some(
+                                self.end_compose(Event::EndArray { span: token.span }),
+                            ));
+                        }
+                        COMMA => return Err(Error::syntax(span, r#"unexpected ",""#)),
+                        _ => self.state = State::Value,
+                    }
+                }
+                State::AnnotationValue => {
+                    let kind = match self.peek_token()? {
+                        Some((kind, _)) => kind,
+                        None => return Err(self.eof_error()),
+                    };
+                    if kind == PARENTHESES_END {
+                        let token = self.next_token()?.unwrap();
+                        if let Some(Scope::Annotation(resume)) = self.stack.pop() {
+                            self.state = resume;
+                        }
+                        return Ok(Some(Event::EndAnnotation { span: token.span }));
+                    }
+                    if kind == ANNOTATION_KEY {
+                        return self.read_annotation().map(Some);
+                    }
+                    self.state = State::Value;
+                }
+            }
+        }
+    }
+
+    fn read_value(&mut self, token: Token) -> Result<Event, Error> {
+        let Token { kind, text, span } = token;
+        match kind {
+            BRACE_START => {
+                self.stack.push(Scope::Object);
+                self.state = State::Key { first: true };
+                Ok(Event::StartObject { span })
+            }
+            BRACKET_START => {
+                self.stack.push(Scope::Array);
+                self.state = State::Item { first: true };
+                Ok(Event::StartArray { span })
+            }
+            NULL | BOOL | INTEGER | INTEGER_BIN | INTEGER_HEX | INTEGER_OCT | FLOAT
+            | DOUBLE_QUOTE | SINGLE_QUOTE | BACKTICK_QUOTE => {
+                validate_scalar(kind, &text, &span)?;
+                self.state = State::AfterValue { comma: false };
+                Ok(Event::Scalar { kind, text, span })
+            }
+            _ => Err(Error::syntax(span, "expected value")),
+        }
+    }
+
+    fn read_annotation(&mut self) -> Result<Event, Error> {
+        let token = self.next_token()?.unwrap();
+        if self.stack.iter().any(|v| matches!(v, Scope::Annotation(_))) {
+            return Err(Error::syntax(token.span, "nested annotation"));
+        }
+        if token.text.len() == 1 {
+            return Err(Error::syntax(token.span, "invalid annotation key"));
+        }
+        let end = token.span.end;
+        if let Some(PARENTHESES_START) = self.peek_token()?.map(|v| v.0) {
+            self.next_token()?;
+            self.stack.push(Scope::Annotation(self.state));
+            self.state = State::AnnotationValue;
+        } else {
+            self.queued = Some(Event::EndAnnotation { span: end..end });
+        }
+        Ok(Event::StartAnnotation {
+            name: token.text,
+            span: token.span,
+        })
+    }
+
+    fn end_compose(&mut self, event: Event) -> Event {
+        self.stack.pop();
+        self.state = State::AfterValue { comma: false };
+        event
+    }
+
+    fn expect_colon(&mut self) -> Result<(), Error> {
+        match self.next_token()? {
+            Some(token) if token.kind == COLON => Ok(()),
+            Some(token) => Err(Error::syntax(token.span, r#"expected ":""#)),
+            None => Err(self.eof_error()),
+        }
+    }
+
+    fn eof_error(&self) -> Error {
+        let end = self.offset + self.buf.len() as u64;
+        Error::syntax(end..end, "unexpected EOF")
+    }
+
+    /// Peek the kind and span of the next token.
+    fn peek_token(&mut self) -> Result<Option<(SyntaxKind, Range<u64>)>, Error> {
+        if self.peeked.is_none() {
+            self.peeked = self.lex_token()?;
+        }
+        Ok(self.peeked.as_ref().map(|v| (v.kind, v.span.clone())))
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>, Error> {
+        match self.peeked.take() {
+            Some(token) => Ok(Some(token)),
+            None => self.lex_token(),
+        }
+    }
+
+    /// Lex the next token that is not whitespace or a comment.
+    fn lex_token(&mut self) -> Result<Option<Token>, Error> {
+        loop {
+            let rest = &self.buf[self.pos..];
+            let mut lexer = SyntaxKind::lexer(rest);
+            let token = lexer.next();
+            let local = lexer.span();
+            if !self.eof && (token.is_none() || local.end + LOOKAHEAD > rest.len()) {
+                // The token may continue in the unread input.
+                self.fill()?;
+                continue;
+            }
+            let token = match token {
+                Some(token) => token,
+                None => return Ok(None),
+            };
+            let text = &rest[local.clone()];
+            let start = self.offset + (self.pos + local.start) as u64;
+            let span = start..start + text.len() as u64;
+            self.pos += local.end;
+            match token {
+                Ok(LINE_COMMENT | BLOCK_COMMENT) => {
+                    let multiline = token == Ok(BLOCK_COMMENT);
+                    if let Err(err_indices) = validates::comment(text, multiline) {
+                        let point = start + err_indices[0] as u64;
+                        return Err(Error::syntax(point..point, "invalid character in comment"));
+                    }
+                }
+                Ok(WHITESPACE | NEWLINE) => {}
+                Ok(kind) => {
+                    return Ok(Some(Token {
+                        kind,
+                        text: text.to_string(),
+                        span,
+                    }))
+                }
+                Err(_) => return Err(Error::syntax(span, "unexpected token")),
+            }
+        }
+    }
+
+    /// Drop the consumed text and read more input into the buffer.
+    ///
+    /// At least as many bytes as are already buffered are read,
+    /// so a long token is lexed again only a logarithmic number of times.
+    fn fill(&mut self) -> Result<(), Error> {
+        self.buf.drain(..self.pos);
+        self.offset += self.pos as u64;
+        self.pos = 0;
+
+        let want = self.chunk_size.max(self.buf.len());
+        let mut chunk = vec![0; want];
+        let mut len = 0;
+        while len < want {
+            match self.input.read(&mut chunk[len..]) {
+                Ok(0) => {
+                    self.eof = true;
+                    break;
+                }
+                Ok(n) => len += n,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        self.pending.extend_from_slice(&chunk[..len]);
+
+        let valid = match std::str::from_utf8(&self.pending) {
+            Ok(s) => s.len(),
+            Err(err) if err.error_len().is_none() && !self.eof => err.valid_up_to(),
+            Err(err) => {
+                let point = self.offset + (self.buf.len() + err.valid_up_to()) as u64;
+                return Err(Error::syntax(point..point, "invalid UTF-8"));
+            }
+        };
+        self.buf
+            .push_str(std::str::from_utf8(&self.pending[..valid]).unwrap());
+        self.pending.drain(..valid);
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for EventReader<R> {
+    type Item = Result<Event, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
+
+fn validate_key(token: &Token) -> Result<(), Error> {
+    let text = token.text.as_str();
+    match token.kind {
+        IDENT | NULL | BOOL | INTEGER_HEX | INTEGER_BIN | INTEGER_OCT => Ok(()),
+        INTEGER | FLOAT if text.starts_with('+') => {
+            Err(Error::syntax(token.span.clone(), "expect identifier"))
+        }
+        INTEGER => Ok(()),
+        FLOAT if text.starts_with('0') => Err(Error::syntax(
+            token.span.clone(),
+            "zero-padded numbers are not allowed",
+        )),
+        FLOAT => Ok(()),
+        SINGLE_QUOTE | DOUBLE_QUOTE | BACKTICK_QUOTE => {
+            validate_scalar(token.kind, text, &token.span)
+        }
+        _ => Err(Error::syntax(token.span.clone(), "expect identifier")),
+    }
+}
+
+fn validate_scalar(kind: SyntaxKind, text: &str, span: &Range<u64>) -> Result<(), Error> {
+    let invalid = match kind {
+        SINGLE_QUOTE | DOUBLE_QUOTE => validates::string(text, Dialect::Jsona)
+            .and_then(|_| validate_quote(text))
+            .err(),
+        BACKTICK_QUOTE => validates::backtick_string(text).err(),
+        _ => {
+            return validates::number(kind, text)
+                .map_err(|message| Error::syntax(span.clone(), message))
+        }
+    };
+    match invalid {
+        Some(indexes) => {
+            let point = span.start + indexes[0] as u64;
+            Err(Error::syntax(
+                point..point + 1,
+                "invalid character in string",
+            ))
+        }
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"{ @foo /* comment */
+  a: { @bar(1)
+    b: [1, 0x2_0, "3é"], @baz
+    "c": `multi
+line`,
+  }, // comment
+  d: [ @x({ y: null }) ],
+  e: 1.5e+5,
+}
+@root
+"#;
+
+    fn read(source: &str, chunk_size: usize) -> Result<Vec<Event>, Error> {
+        EventReader::new(source.as_bytes())
+            .with_chunk_size(chunk_size)
+            .collect()
+    }
+
+    #[test]
+    fn test_events() {
+        let events = read(SOURCE, DEFAULT_CHUNK_SIZE).unwrap();
+        let summary: Vec<String> = events
+            .iter()
+            .map(|event| {
+                let span = event.span();
+                let text = &SOURCE[span.start as usize..span.end as usize];
+                match event {
+                    Event::Key { text, .. } => format!("Key({text})"),
+                    Event::Scalar { text, .. } => format!("Scalar({text})"),
+                    Event::StartAnnotation { name, .. } => format!("StartAnnotation({name})"),
+                    _ => format!("{event:?}").split(' ').next().unwrap().to_string() + text,
+                }
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                "StartObject{",
+                "StartAnnotation(@foo)",
+                "EndAnnotation",
+                "Key(a)",
+                "StartObject{",
+                "StartAnnotation(@bar)",
+                "Scalar(1)",
+                "EndAnnotation)",
+                "Key(b)",
+                "StartArray[",
+                "Scalar(1)",
+                "Scalar(0x2_0)",
+                "Scalar(\"3é\")",
+                "EndArray]",
+                "StartAnnotation(@baz)",
+                "EndAnnotation",
+                "Key(\"c\")",
+                "Scalar(`multi\nline`)",
+                "EndObject}",
+                "Key(d)",
+                "StartArray[",
+                "StartAnnotation(@x)",
+                "StartObject{",
+                "Key(y)",
+                "Scalar(null)",
+                "EndObject}",
+                "EndAnnotation)",
+                "EndArray]",
+                "Key(e)",
+                "Scalar(1.5e+5)",
+                "EndObject}",
+                "StartAnnotation(@root)",
+                "EndAnnotation",
+            ]
+        );
+        for chunk_size in 1..8 {
+            assert_eq!(read(SOURCE, chunk_size).unwrap(), events);
+        }
+        assert_eq!(read("", 1).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_events_error() {
+        macro_rules! assert_error {
+            ($source:literal, $span:expr, $message:literal) => {
+                for chunk_size in [1, DEFAULT_CHUNK_SIZE] {
+                    match read($source, chunk_size) {
+                        Err(Error::InvalidSyntax { span, message }) => {
+                            assert_eq!((span, message.as_str()), ($span, $message));
+                        }
+                        v => panic!("unexpected {v:?}"),
+                    }
+                }
+            };
+        }
+        assert_error!("{ a: 1", 6..6, "unexpected EOF");
+        assert_error!("{ a: 1 b: 2 }", 7..8, r#"expected ",""#);
+        assert_error!("{ a 1 }", 4..5, r#"expected ":""#);
+        assert_error!("[1,,]", 3..4, r#"unexpected ",""#);
+        assert_error!("[01]", 1..3, "zero-padded integers are not allowed");
+        assert_error!("@a(1)", 0..2, "expected value");
+        assert_error!("{} 1", 3..4, "expect EOF");
+        assert_error!("[1 @a(@b)]", 6..8, "nested annotation");
+        assert_error!("\"abc", 0..4, "unexpected token");
+        assert_error!(r#"["a\/b"]"#, 3..4, "invalid character in string");
+        assert_error!("[NaN]", 1..4, "expected value");
+    }
+}
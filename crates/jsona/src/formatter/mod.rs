@@ -10,7 +10,7 @@ use std::{cell::RefCell, rc::Rc};
 
 use crate::{
     dom::from_syntax::key_from_syntax,
-    parser,
+    parser::{self, Dialect, ParseOptions},
     syntax::{SyntaxKind::*, SyntaxNode, SyntaxToken},
 };
 
@@ -120,10 +120,8 @@ impl Context {
 
     fn comma(&mut self, scope: &Scope) {
         match self.comma_modes.last() {
-            Some(CommaMode::Auto) => {
-                if !self.compact() && scope.options.trailing_comma {
-                    self.write(scope, ",")
-                }
+            Some(CommaMode::Auto) if !self.compact() && scope.options.trailing_comma => {
+                self.write(scope, ",")
             }
             Some(CommaMode::ForceYes) => self.write(scope, ","),
             _ => {}
@@ -162,6 +160,27 @@ pub fn format(src: &str, options: Options) -> String {
     scope.output()
 }
 
+/// Same as [format], but parses the document with the given options and
+/// keeps the output in the chosen dialect.
+pub fn format_with_options(src: &str, options: Options, parse_options: &ParseOptions) -> String {
+    let p = parser::parse_with_options(src, parse_options);
+    format_syntax(p.into_syntax(), options.with_dialect(parse_options.dialect))
+}
+
+impl Options {
+    /// Turn off the options that would produce output outside of the dialect.
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        match dialect {
+            Dialect::Jsona | Dialect::Json5 => {}
+            Dialect::Json | Dialect::Jsonc => {
+                self.format_key = false;
+                self.trailing_comma &= dialect.allows_trailing_commas();
+            }
+        }
+        self
+    }
+}
+
 /// Formats a parsed JSONA syntax tree.
 pub fn format_syntax(node: SyntaxNode, options: Options) -> String {
     let scope = Scope {
@@ -212,7 +231,7 @@ fn format_scalar(scope: Scope, syntax: SyntaxNode, ctx: &mut Context) {
         ctx.write(&scope, &text)
     }
     if is_multiline(&text) {
-        if let Some(offset) = text.split('\n').last().map(|v| v.len()) {
+        if let Some(offset) = text.split('\n').next_back().map(|v| v.len()) {
             ctx.col_offset = offset
         }
     }
@@ -511,10 +530,8 @@ fn can_compact(syntax: SyntaxNode) -> bool {
                     return false;
                 }
                 match t.kind() {
-                    BLOCK_COMMENT => {
-                        if is_multiline(t.text()) {
-                            return false;
-                        }
+                    BLOCK_COMMENT if is_multiline(t.text()) => {
+                        return false;
                     }
                     NEWLINE => {
                         exist_newline = true;
@@ -8,12 +8,17 @@
 //! It uses [Rowan](::rowan) for the syntax tree, and every character is preserved from the input,
 //! including all comments and white space.
 //!
+//! The crate requires `std`. Rowan, which the parser, DOM and formatter are built on,
+//! cannot be compiled with `#![no_std]`, so there is no `alloc`-only build.
+//!
 //! A [DOM](dom) can be constructed for data-oriented analysis where each node wraps a part of the
 //! syntax tree with additional information and functionality.
 //!
 //! # Features
 //!
 //! - **serde**: Support for [serde](https://serde.rs) serialization of the DOM nodes.
+//! - **arbitrary_precision**: Keep numbers of any size and precision in the DOM, see
+//!   [`dom::Number::value`].
 //!
 //! # Usage
 //!
@@ -54,15 +59,46 @@
 //!
 //! let root_node = parse_result.into_dom();
 //! ```
+//!
+//! Rust types implementing `Deserialize` can be read directly with [from_str] and [from_node],
+//! and types implementing `Serialize` written with [to_string] and [to_string_pretty].
+//!
+//! For inputs too large to be kept in memory, an [EventReader](events::EventReader)
+//! reports the document as a stream of events without building a syntax tree.
+//!
+//! Inputs with one document per line, such as logs, can be read and written with
+//! the [lines] module.
+//!
+//! Hand-written documents can be changed with an [edit::Document], which keeps comments and
+//! formatting outside of the edited nodes untouched, and [patch] applies JSON Patch and
+//! JSON Merge Patch documents to them.
+//!
+//! Annotations can be evaluated by [processors](processor) registered by name, which rewrite
+//! the annotated values. Values marked with `@anchor` are reused by `@alias`, which
+//! [anchors::resolve] expands.
+//!
+//! Nodes exported to plain JSON can carry a [source map](dom::source_map), so errors reported
+//! by other tools against the JSON are shown at their position in the JSONA source.
 
+pub mod anchors;
+pub mod de;
 pub mod dom;
+pub mod edit;
 pub mod error;
+pub mod events;
 pub mod formatter;
+pub mod lines;
 pub mod parser;
+pub mod patch;
+pub mod processor;
+pub mod ser;
 pub mod syntax;
 pub mod util;
 pub use rowan;
 
+pub use de::{from_node, from_str};
+pub use ser::{to_node, to_string, to_string_pretty, Annotated};
+
 mod private {
     pub trait Sealed {}
 }
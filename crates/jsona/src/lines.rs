@@ -0,0 +1,218 @@
+//! Reading and writing JSONA Lines, where every line of the input is a separate document.
+//!
+//! ```
+//! use jsona::lines::{Reader, Writer};
+//!
+//! let source = "{ level: \"info\" } @time(1)\n\n{ level: \"warn\" } @time(2)\n";
+//! let records: Vec<_> = Reader::new(source).collect();
+//!
+//! assert_eq!(records.len(), 2);
+//! assert_eq!(records[1].line, 2);
+//! assert_eq!(&source[records[1].range], r#"{ level: "warn" } @time(2)"#);
+//!
+//! let mut writer = Writer::new(vec![]);
+//! for record in &records {
+//!     writer.write(&record.node).unwrap();
+//! }
+//! assert_eq!(
+//!     String::from_utf8(writer.into_inner()).unwrap(),
+//!     "{@time(1) level:\"info\"}\n{@time(2) level:\"warn\"}\n"
+//! );
+//! ```
+
+use crate::dom::{DomOptions, Node};
+use crate::parser::{self, ParseOptions};
+use crate::ser::write_compact_line;
+
+use rowan::{TextRange, TextSize};
+use std::io;
+
+/// A single document of a JSONA Lines input.
+#[derive(Debug, Clone)]
+pub struct Record {
+    /// The zero-based line number of the record.
+    pub line: usize,
+
+    /// The byte range of the record in the input, without the line break.
+    pub range: TextRange,
+
+    /// The record, ranges of its syntax are relative to the start of the record.
+    pub node: Node,
+
+    /// Syntax errors of the record, ranges are relative to the start of the record.
+    pub errors: Vec<parser::Error>,
+}
+
+impl Record {
+    /// Translate a range relative to the record into a range in the input.
+    pub fn source_range(&self, range: TextRange) -> TextRange {
+        range + self.range.start()
+    }
+}
+
+/// Iterator over the records of a JSONA Lines input.
+///
+/// Blank lines are skipped, every other line is parsed as a document
+/// and reported with its own errors, so an invalid record does not affect the others.
+#[derive(Debug, Clone)]
+pub struct Reader<'a> {
+    source: &'a str,
+    offset: usize,
+    line: usize,
+    options: ParseOptions,
+    dom_options: DomOptions,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(source: &'a str) -> Self {
+        Self::with_options(source, ParseOptions::default())
+    }
+
+    pub fn with_options(source: &'a str, options: ParseOptions) -> Self {
+        Self {
+            source,
+            offset: 0,
+            line: 0,
+            options,
+            dom_options: DomOptions::default(),
+        }
+    }
+
+    /// Build the nodes of the records with the given options.
+    pub fn with_dom_options(mut self, dom_options: DomOptions) -> Self {
+        self.dom_options = dom_options;
+        self
+    }
+}
+
+impl<'a> Iterator for Reader<'a> {
+    type Item = Record;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.offset < self.source.len() {
+            let start = self.offset;
+            let rest = &self.source[start..];
+            let (text, next) = match rest.find('\n') {
+                Some(i) => (&rest[..i], start + i + 1),
+                None => (rest, self.source.len()),
+            };
+            let text = text.strip_suffix('\r').unwrap_or(text);
+            let line = self.line;
+            self.offset = next;
+            self.line += 1;
+            if text.trim().is_empty() {
+                continue;
+            }
+            let parse = parser::parse_with_options(text, &self.options);
+            let start = TextSize::from(start as u32);
+            return Some(Record {
+                line,
+                range: TextRange::at(start, TextSize::of(text)),
+                errors: parse.errors.clone(),
+                node: parse.into_dom_with_options(&self.dom_options),
+            });
+        }
+        None
+    }
+}
+
+/// Writes nodes as JSONA Lines.
+///
+/// Every node is written in compact form followed by a line break,
+/// strings spanning multiple lines are escaped to keep each node on its own line.
+#[derive(Debug)]
+pub struct Writer<W> {
+    inner: W,
+}
+
+impl<W: io::Write> Writer<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner }
+    }
+
+    /// Write the node as a single line.
+    pub fn write(&mut self, node: &Node) -> io::Result<()> {
+        let mut output = String::new();
+        write_compact_line(&mut output, node);
+        output.push('\n');
+        self.inner.write_all(output.as_bytes())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::DuplicateKeyPolicy;
+
+    #[test]
+    fn test_reader() {
+        let source = "1\r\n\n  { a: [1, 2] }  \n{ a: }\n\"x\" @foo";
+        let records: Vec<_> = Reader::new(source).collect();
+        assert_eq!(
+            records.iter().map(|v| v.line).collect::<Vec<_>>(),
+            vec![0, 2, 3, 4]
+        );
+        assert_eq!(&source[records[0].range], "1");
+        assert_eq!(&source[records[1].range], "  { a: [1, 2] }  ");
+        assert!(records[1].errors.is_empty());
+        assert_eq!(
+            records[1].node.to_plain_json(),
+            serde_json::json!({"a": [1, 2]})
+        );
+        assert_eq!(records[2].errors.len(), 1);
+        let error = records[2].source_range(records[2].errors[0].range);
+        assert_eq!(&source[error], "}");
+        assert!(records[3].errors.is_empty());
+        assert_eq!(&source[records[3].range], "\"x\" @foo");
+    }
+
+    #[test]
+    fn test_reader_dom_options() {
+        let source = "{ a: 1, a: 2 }\n{ b: 1 }";
+        let records: Vec<_> = Reader::new(source).collect();
+        assert!(records[0].node.validate().is_err());
+
+        let options = DomOptions {
+            properties: DuplicateKeyPolicy::LastWins,
+            ..Default::default()
+        };
+        let records: Vec<_> = Reader::new(source).with_dom_options(options).collect();
+        assert!(records[0].node.validate().is_ok());
+        assert_eq!(records[0].node.warnings().len(), 1);
+        assert_eq!(records[0].node.to_plain_json(), serde_json::json!({"a": 2}));
+        assert!(records[1].node.validate().is_ok());
+    }
+
+    #[test]
+    fn test_writer() {
+        let node: Node = "{ a: `x\ny`, @foo(`\r`) }".parse().unwrap();
+        let mut writer = Writer::new(vec![]);
+        writer.write(&node).unwrap();
+        writer.write(&node).unwrap();
+        let output = String::from_utf8(writer.into_inner()).unwrap();
+        assert_eq!(
+            output,
+            "{a:\"x\\ny\" @foo(\"\\r\")}\n{a:\"x\\ny\" @foo(\"\\r\")}\n"
+        );
+        let records: Vec<_> = Reader::new(&output).collect();
+        assert_eq!(records.len(), 2);
+        assert!(records[1].errors.is_empty());
+        assert_eq!(records[1].node.to_plain_json(), node.to_plain_json());
+    }
+}
@@ -5,7 +5,9 @@ use crate::syntax::{SyntaxKind, SyntaxKind::*, SyntaxNode};
 use crate::util::validate_quote;
 use logos::{Lexer, Logos};
 use rowan::{GreenNode, GreenNodeBuilder, TextRange, TextSize};
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
+use std::str::FromStr;
 
 macro_rules! with_node {
     ($builder:expr, $kind:ident, $($content:tt)*) => {
@@ -50,6 +52,193 @@ pub fn parse(source: &str) -> Parse {
     Parser::new(source).parse()
 }
 
+/// Parse a document with the given options, see [parse].
+///
+/// Everything outside of the chosen [Dialect] is still parsed into the tree,
+/// but reported as a syntax error.
+pub fn parse_with_options(source: &str, options: &ParseOptions) -> Parse {
+    let mut parser = Parser::new(source);
+    parser.dialect = options.dialect;
+    parser.parse()
+}
+
+/// Options for [parse_with_options].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParseOptions {
+    /// The grammar accepted by the parser.
+    #[serde(default)]
+    pub dialect: Dialect,
+}
+
+/// The grammar accepted by the parser.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Dialect {
+    /// The full JSONA grammar.
+    #[default]
+    Jsona,
+    /// Strict JSON as specified by RFC 8259.
+    Json,
+    /// JSON with comments and trailing commas.
+    Jsonc,
+    /// JSON5, which adds unquoted keys, single-quoted strings,
+    /// hexadecimal numbers, `Infinity`, `NaN`, leading `+`,
+    /// and `\` line continuations in strings.
+    ///
+    /// Strings accept the escapes of JSONA and `\/`, other characters can not be escaped.
+    Json5,
+}
+
+impl Dialect {
+    pub fn allows_comments(self) -> bool {
+        self != Dialect::Json
+    }
+
+    pub fn allows_trailing_commas(self) -> bool {
+        self != Dialect::Json
+    }
+
+    pub fn allows_annotations(self) -> bool {
+        self == Dialect::Jsona
+    }
+
+    fn is_json(self) -> bool {
+        matches!(self, Dialect::Json | Dialect::Jsonc)
+    }
+}
+
+impl FromStr for Dialect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "jsona" => Ok(Dialect::Jsona),
+            "json" => Ok(Dialect::Json),
+            "jsonc" => Ok(Dialect::Jsonc),
+            "json5" => Ok(Dialect::Json5),
+            _ => Err(format!("unknown dialect `{s}`")),
+        }
+    }
+}
+
+/// A change to a JSONA document, replacing the text in `range` with `text`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TextEdit {
+    /// The range of the old text that is replaced.
+    pub range: TextRange,
+
+    /// The new text.
+    pub text: String,
+}
+
+impl TextEdit {
+    pub fn new(range: TextRange, text: impl Into<String>) -> Self {
+        Self {
+            range,
+            text: text.into(),
+        }
+    }
+
+    pub fn insert(offset: TextSize, text: impl Into<String>) -> Self {
+        Self::new(TextRange::empty(offset), text)
+    }
+
+    pub fn delete(range: TextRange) -> Self {
+        Self::new(range, "")
+    }
+
+    /// Apply the edit to the given text.
+    pub fn apply(&self, text: &mut String) {
+        let start: usize = self.range.start().into();
+        let end: usize = self.range.end().into();
+        text.replace_range(start..end, &self.text);
+    }
+
+    fn delta(&self) -> i64 {
+        self.text.len() as i64 - u32::from(self.range.len()) as i64
+    }
+}
+
+/// Reparse a JSONA document after an edit.
+///
+/// Only the innermost object or array that contains the edit is
+/// parsed again, every other green subtree of `old` is reused as is.
+/// If the edited block can not be reparsed on its own,
+/// e.g. the edit touches its brackets or unbalances them,
+/// the whole document is parsed again.
+///
+/// The result is the same as calling [parse] on the edited text.
+pub fn reparse(old: &Parse, edit: &TextEdit) -> Parse {
+    reparse_with_options(old, edit, &ParseOptions::default())
+}
+
+/// Reparse a document after an edit with the options it was parsed with, see [reparse].
+///
+/// The result is the same as calling [parse_with_options] on the edited text.
+pub fn reparse_with_options(old: &Parse, edit: &TextEdit, options: &ParseOptions) -> Parse {
+    match reparse_block(old, edit, options) {
+        Some(parse) => parse,
+        None => {
+            let mut text = old.clone().into_syntax().to_string();
+            edit.apply(&mut text);
+            parse_with_options(&text, options)
+        }
+    }
+}
+
+fn reparse_block(old: &Parse, edit: &TextEdit, options: &ParseOptions) -> Option<Parse> {
+    let root = SyntaxNode::new_root(old.green_node.clone());
+    if !root.text_range().contains_range(edit.range) {
+        return None;
+    }
+    let node = root.covering_element(edit.range).ancestors().find(|v| {
+        let range = v.text_range();
+        v.kind().is_compose()
+            && range.start() < edit.range.start()
+            && edit.range.end() < range.end()
+    })?;
+    let range = node.text_range();
+
+    let mut text = node.text().to_string();
+    TextEdit::new(edit.range - range.start(), edit.text.as_str()).apply(&mut text);
+
+    let mut parser = Parser::new(&text);
+    parser.dialect = options.dialect;
+    parser.annotation_scope = node.ancestors().any(|v| v.kind() == ANNOTATION_VALUE);
+    let fragment = parser.parse_fragment(node.kind())?;
+
+    let delta = edit.delta();
+    let shift = |v: TextSize| TextSize::from((u32::from(v) as i64 + delta) as u32);
+    let mut errors = vec![];
+    for err in &old.errors {
+        if err.range.end() <= range.start() {
+            errors.push(err.clone());
+        }
+    }
+    errors.extend(fragment.errors.into_iter().map(|err| Error {
+        range: err.range + range.start(),
+        message: err.message,
+    }));
+    for err in &old.errors {
+        // Point errors right before the closing bracket are reported by the parent.
+        let is_parent_point =
+            err.range.is_empty() && err.range.start() + TextSize::from(1) == range.end();
+        if err.range.start() >= range.end() || is_parent_point {
+            errors.push(Error {
+                range: TextRange::new(shift(err.range.start()), shift(err.range.end())),
+                message: err.message.clone(),
+            });
+        }
+    }
+
+    Some(Parse {
+        green_node: node.replace_with(fragment.green_node),
+        errors,
+        dialect: options.dialect,
+    })
+}
+
 /// A hand-written parser that uses the Logos lexer
 /// to tokenize the source, then constructs
 /// a Rowan green tree from them.
@@ -60,6 +249,9 @@ pub(crate) struct Parser<'p> {
     errors: Vec<Error>,
     annotation_scope: bool,
     parse_keys_mode: ParseKeysMode,
+    dialect: Dialect,
+    /// The range of the last comma if no item followed it yet.
+    trailing_comma: Option<TextRange>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
@@ -83,6 +275,8 @@ impl<'p> Parser<'p> {
             errors: Default::default(),
             annotation_scope: false,
             parse_keys_mode: Default::default(),
+            dialect: Default::default(),
+            trailing_comma: None,
         }
     }
 
@@ -97,7 +291,37 @@ impl<'p> Parser<'p> {
         Parse {
             green_node: self.builder.finish(),
             errors: self.errors,
+            dialect: self.dialect,
+        }
+    }
+
+    /// Parse the text of a single object or array node.
+    ///
+    /// Fails if the text is not exactly one such node.
+    fn parse_fragment(mut self, kind: SyntaxKind) -> Option<Parse> {
+        let (ret, end) = match kind {
+            OBJECT => (
+                with_node!(self.builder, OBJECT, self.parse_object()),
+                BRACE_END,
+            ),
+            ARRAY => (
+                with_node!(self.builder, ARRAY, self.parse_array()),
+                BRACKET_END,
+            ),
+            _ => return None,
+        };
+        if ret.is_err() || !self.lexer.remainder().is_empty() {
+            return None;
         }
+        let green_node = self.builder.finish();
+        if green_node.children().last()?.kind() != end.into() {
+            return None;
+        }
+        Some(Parse {
+            green_node,
+            errors: self.errors,
+            dialect: self.dialect,
+        })
     }
 
     fn parse(mut self) -> Parse {
@@ -106,6 +330,7 @@ impl<'p> Parser<'p> {
         Parse {
             green_node: self.builder.finish(),
             errors: self.errors,
+            dialect: self.dialect,
         }
     }
 
@@ -119,7 +344,9 @@ impl<'p> Parser<'p> {
         if let Ok(ANNOTATION_KEY) = self.peek_token() {
             self.builder.start_node(ANNOTATIONS.into());
             while let Ok(ANNOTATION_KEY) = self.peek_token() {
-                if self.lexer.slice().len() == 1 {
+                if !self.dialect.allows_annotations() {
+                    self.report_error("annotations are not allowed");
+                } else if self.lexer.slice().len() == 1 {
                     self.report_error("invalid annotation key");
                 }
                 let _ = with_node!(self.builder, ANNOTATION_PROPERTY, self.parse_anno_entry());
@@ -180,6 +407,7 @@ impl<'p> Parser<'p> {
             Ok(t) => t,
             Err(_) => return Ok(()),
         };
+        let t = self.json5_non_finite(t);
         match t {
             BRACE_START => {
                 with_node!(self.builder, OBJECT, self.parse_object())
@@ -188,63 +416,26 @@ impl<'p> Parser<'p> {
                 with_node!(self.builder, ARRAY, self.parse_array())
             }
             NULL | BOOL => with_node!(self.builder, SCALAR, self.consume_current_token()),
-            INTEGER => {
-                // This could've been done more elegantly probably.
-                if (self.lexer.slice().starts_with('0') && self.lexer.slice() != "0")
-                    || (self.lexer.slice().starts_with("+0") && self.lexer.slice() != "+0")
-                    || (self.lexer.slice().starts_with("-0") && self.lexer.slice() != "-0")
-                {
-                    self.consume_error_token("zero-padded integers are not allowed")
-                } else if !validate_underscore_integer(self.lexer.slice(), 10) {
-                    self.consume_error_token("invalid underscores")
-                } else {
-                    with_node!(self.builder, SCALAR, self.consume_current_token())
-                }
-            }
-            INTEGER_BIN => {
-                if !validate_underscore_integer(self.lexer.slice(), 2) {
-                    self.consume_error_token("invalid underscores")
-                } else {
-                    with_node!(self.builder, SCALAR, self.consume_current_token())
-                }
-            }
-            INTEGER_HEX => {
-                if !validate_underscore_integer(self.lexer.slice(), 16) {
-                    self.consume_error_token("invalid underscores")
-                } else {
-                    with_node!(self.builder, SCALAR, self.consume_current_token())
-                }
-            }
-            INTEGER_OCT => {
-                if !validate_underscore_integer(self.lexer.slice(), 8) {
-                    self.consume_error_token("invalid underscores")
-                } else {
-                    with_node!(self.builder, SCALAR, self.consume_current_token())
-                }
-            }
-            FLOAT => {
-                let int_slice = if self.lexer.slice().contains('.') {
-                    self.lexer.slice().split('.').next().unwrap()
-                } else {
-                    self.lexer.slice().split('e').next().unwrap()
-                };
-
-                if (int_slice.starts_with('0') && int_slice != "0")
-                    || (int_slice.starts_with("+0") && int_slice != "+0")
-                    || (int_slice.starts_with("-0") && int_slice != "-0")
+            INTEGER | INTEGER_BIN | INTEGER_HEX | INTEGER_OCT | FLOAT => {
+                let s = self.lexer.slice();
+                match validates::number(t, s)
+                    .and_then(|_| validates::dialect_number(self.dialect, t, s))
                 {
-                    self.consume_error_token("zero-padded numbers are not allowed")
-                } else if !validate_underscore_integer(self.lexer.slice(), 10) {
-                    self.consume_error_token("invalid underscores")
-                } else {
-                    with_node!(self.builder, SCALAR, self.consume_current_token())
+                    Ok(_) => with_node!(self.builder, SCALAR, self.consume_current_token()),
+                    Err(message) => self.consume_error_token(message),
                 }
             }
             DOUBLE_QUOTE | SINGLE_QUOTE => {
+                if t == SINGLE_QUOTE && self.dialect.is_json() {
+                    self.report_error("single-quoted strings are not allowed");
+                }
                 self.validate_string();
                 with_node!(self.builder, SCALAR, self.consume_current_token())
             }
             BACKTICK_QUOTE => {
+                if self.dialect != Dialect::Jsona {
+                    self.report_error("backtick strings are not allowed");
+                }
                 self.validate_backtick();
                 with_node!(self.builder, SCALAR, self.consume_current_token())
             }
@@ -256,12 +447,37 @@ impl<'p> Parser<'p> {
         }
     }
 
+    /// `NaN` and `Infinity` are lexed as identifiers and a sign before them as a float,
+    /// only JSON5 reads them as numbers.
+    fn json5_non_finite(&mut self, t: SyntaxKind) -> SyntaxKind {
+        if self.dialect != Dialect::Json5 {
+            return t;
+        }
+        let is_non_finite = |s: &str| matches!(s, "NaN" | "Infinity");
+        match t {
+            IDENT if is_non_finite(self.lexer.slice()) => {}
+            FLOAT if matches!(self.lexer.slice(), "+" | "-") => {
+                let remainder = self.lexer.remainder();
+                let len = remainder
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .unwrap_or(remainder.len());
+                if !is_non_finite(&remainder[..len]) {
+                    return t;
+                }
+                self.lexer.bump(len);
+            }
+            _ => return t,
+        }
+        self.current_token = Some(FLOAT);
+        FLOAT
+    }
+
     fn parse_value_with_annotations(&mut self) -> ParserResult<bool> {
         self.parse_value()?;
         let mut has_comma = false;
         if let Ok(COMMA) = self.peek_token() {
             has_comma = true;
-            self.consume_current_token()?;
+            self.consume_comma()?;
         }
         self.parse_annotations()?;
         Ok(has_comma)
@@ -269,18 +485,20 @@ impl<'p> Parser<'p> {
 
     fn parse_object(&mut self) -> ParserResult<()> {
         self.must_token_or(BRACE_START, r#"expected "{""#)?;
+        self.trailing_comma = None;
         self.parse_annotations()?;
         let mut needs_comma = false;
 
         while let Ok(t) = self.must_peek_token() {
             match t {
                 BRACE_END => {
+                    self.check_trailing_comma();
                     return self.consume_current_token();
                 }
                 COMMA => {
                     if needs_comma {
                         needs_comma = false;
-                        self.consume_current_token()?;
+                        self.consume_comma()?;
                     } else {
                         let _ = self.consume_error_token(r#"unexpected ",""#);
                     }
@@ -289,6 +507,7 @@ impl<'p> Parser<'p> {
                     if needs_comma {
                         self.point_error(r#"expected ",""#);
                     }
+                    self.trailing_comma = None;
                     let ret = with_node!(self.builder, PROPERTY, self.parse_property());
                     if let Ok(has_comma) = ret {
                         needs_comma = !has_comma;
@@ -301,18 +520,20 @@ impl<'p> Parser<'p> {
 
     fn parse_array(&mut self) -> ParserResult<()> {
         self.must_token_or(BRACKET_START, r#"expected "[""#)?;
+        self.trailing_comma = None;
         let _ = self.parse_annotations();
         let mut needs_comma = false;
 
         while let Ok(t) = self.must_peek_token() {
             match t {
                 BRACKET_END => {
+                    self.check_trailing_comma();
                     return self.consume_current_token();
                 }
                 COMMA => {
                     if needs_comma {
                         needs_comma = false;
-                        self.consume_current_token()?;
+                        self.consume_comma()?;
                     } else {
                         let _ = self.consume_error_token(r#"unexpected ",""#);
                     }
@@ -321,6 +542,7 @@ impl<'p> Parser<'p> {
                     if needs_comma {
                         self.point_error(r#"expected ",""#);
                     }
+                    self.trailing_comma = None;
                     let ret = with_node!(self.builder, VALUE, self.parse_value_with_annotations());
                     needs_comma = !ret.ok().unwrap_or_default();
                 }
@@ -350,7 +572,7 @@ impl<'p> Parser<'p> {
                     if after_dot || exist_annotation_key {
                         return self.consume_error_token("unexpected annotation key");
                     } else {
-                        self.consume_current_token()?;
+                        self.parse_keys_annotation()?;
                         exist_annotation_key = true;
                         after_dot = false;
                         first = false;
@@ -421,8 +643,39 @@ impl<'p> Parser<'p> {
         }
     }
 
+    /// Namespaced annotation keys are quoted in keys, e.g. `a@"openapi.deprecated"`,
+    /// so `a@mock.foo` stays the property `foo` of the value of `@mock`.
+    fn parse_keys_annotation(&mut self) -> ParserResult<()> {
+        let value = self.lexer.slice();
+        if value == "@" {
+            let end = self.lexer.span().end;
+            let mut lexer = self.lexer.clone();
+            if let Some(Ok(DOUBLE_QUOTE | SINGLE_QUOTE)) = lexer.next() {
+                if lexer.span().start == end {
+                    let value = format!("@{}", lexer.slice());
+                    self.lexer = lexer;
+                    self.consume_token(ANNOTATION_KEY, &value);
+                    return Ok(());
+                }
+            }
+        }
+        let mut segments = value.split('.');
+        if let Some(key) = segments.next() {
+            self.builder.token(ANNOTATION_KEY.into(), key);
+        }
+        for segment in segments {
+            self.builder.token(PERIOD.into(), ".");
+            self.builder.token(IDENT.into(), segment);
+        }
+        self.current_token = None;
+        Ok(())
+    }
+
     fn parse_key(&mut self) -> ParserResult<()> {
         let t = self.must_peek_token()?;
+        if self.parse_keys_mode == ParseKeysMode::None {
+            self.validate_dialect_key(t);
+        }
 
         match t {
             IDENT => self.consume_current_token(),
@@ -533,6 +786,9 @@ impl<'p> Parser<'p> {
             match token {
                 Ok(token) => match token {
                     LINE_COMMENT | BLOCK_COMMENT => {
+                        if !self.dialect.allows_comments() {
+                            self.report_error("comments are not allowed");
+                        }
                         let multiline = token == BLOCK_COMMENT;
                         if let Err(err_indices) = validates::comment(self.lexer.slice(), multiline)
                         {
@@ -570,6 +826,40 @@ impl<'p> Parser<'p> {
         self.current_token = None;
     }
 
+    fn consume_comma(&mut self) -> ParserResult<()> {
+        let span = self.lexer.span();
+        self.trailing_comma = Some(TextRange::new(
+            TextSize::from(span.start as u32),
+            TextSize::from(span.end as u32),
+        ));
+        self.consume_current_token()
+    }
+
+    fn check_trailing_comma(&mut self) {
+        if let Some(range) = self.trailing_comma.take() {
+            if !self.dialect.allows_trailing_commas() {
+                self.add_error(&Error {
+                    range,
+                    message: "trailing commas are not allowed".into(),
+                });
+            }
+        }
+    }
+
+    fn validate_dialect_key(&mut self, kind: SyntaxKind) {
+        let valid = match self.dialect {
+            Dialect::Jsona => true,
+            Dialect::Json | Dialect::Jsonc => kind == DOUBLE_QUOTE,
+            Dialect::Json5 => matches!(kind, IDENT | NULL | BOOL | SINGLE_QUOTE | DOUBLE_QUOTE),
+        };
+        if !valid {
+            match self.dialect {
+                Dialect::Json5 => self.report_error("keys must be identifiers or strings"),
+                _ => self.report_error("keys must be double-quoted strings"),
+            }
+        }
+    }
+
     fn report_error(&mut self, message: &str) {
         let span = self.lexer.span();
 
@@ -605,7 +895,7 @@ impl<'p> Parser<'p> {
     fn validate_string(&mut self) {
         let mut indexes: HashSet<usize> = HashSet::default();
 
-        if let Err(err_indices) = validates::string(self.lexer.slice()) {
+        if let Err(err_indices) = validates::string(self.lexer.slice(), self.dialect) {
             indexes.extend(err_indices);
         };
         if let Err(err_indices) = validate_quote(self.lexer.slice()) {
@@ -638,26 +928,6 @@ impl<'p> Parser<'p> {
     }
 }
 
-fn validate_underscore_integer(s: &str, radix: u32) -> bool {
-    if s.starts_with('_') || s.ends_with('_') {
-        return false;
-    }
-
-    let mut prev_char = 0 as char;
-
-    for c in s.chars() {
-        if c == '_' && !prev_char.is_digit(radix) {
-            return false;
-        }
-        if !c.is_digit(radix) && prev_char == '_' {
-            return false;
-        }
-        prev_char = c;
-    }
-
-    true
-}
-
 /// The final results of a parsing.
 /// It contains the green tree, and
 /// the errors that ocurred during parsing.
@@ -665,6 +935,8 @@ fn validate_underscore_integer(s: &str, radix: u32) -> bool {
 pub struct Parse {
     pub green_node: GreenNode,
     pub errors: Vec<Error>,
+    /// The dialect the text was parsed with.
+    pub dialect: Dialect,
 }
 
 impl Parse {
@@ -677,11 +949,129 @@ impl Parse {
     /// Any semantic errors that occur will be collected
     /// in the returned DOM node.
     pub fn into_dom(self) -> dom::Node {
-        dom::from_syntax(self.into_syntax().into())
+        self.into_dom_with_options(&dom::DomOptions::default())
+    }
+    /// Turn the parse into a DOM tree with the given options.
+    pub fn into_dom_with_options(self, options: &dom::DomOptions) -> dom::Node {
+        let dialect = self.dialect;
+        dom::from_syntax_in(self.into_syntax().into(), options, dialect)
     }
 }
 
 pub(crate) mod validates {
+    use super::Dialect;
+    use crate::syntax::SyntaxKind::{self, *};
+
+    /// Validate the text of a number token, returns the error message if it's invalid.
+    pub(crate) fn number(kind: SyntaxKind, s: &str) -> Result<(), &'static str> {
+        match kind {
+            INTEGER => {
+                // This could've been done more elegantly probably.
+                if (s.starts_with('0') && s != "0")
+                    || (s.starts_with("+0") && s != "+0")
+                    || (s.starts_with("-0") && s != "-0")
+                {
+                    return Err("zero-padded integers are not allowed");
+                }
+                if !underscore_integer(s, 10) {
+                    return Err("invalid underscores");
+                }
+            }
+            INTEGER_BIN | INTEGER_HEX | INTEGER_OCT => {
+                let radix = match kind {
+                    INTEGER_BIN => 2,
+                    INTEGER_HEX => 16,
+                    _ => 8,
+                };
+                if !underscore_integer(s, radix) {
+                    return Err("invalid underscores");
+                }
+            }
+            FLOAT => {
+                let int_slice = if s.contains('.') {
+                    s.split('.').next().unwrap()
+                } else {
+                    s.split('e').next().unwrap()
+                };
+
+                if (int_slice.starts_with('0') && int_slice != "0")
+                    || (int_slice.starts_with("+0") && int_slice != "+0")
+                    || (int_slice.starts_with("-0") && int_slice != "-0")
+                {
+                    return Err("zero-padded numbers are not allowed");
+                }
+                if !underscore_integer(s, 10) {
+                    return Err("invalid underscores");
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Validate the text of a number token against the restrictions of a dialect.
+    pub(crate) fn dialect_number(
+        dialect: Dialect,
+        kind: SyntaxKind,
+        s: &str,
+    ) -> Result<(), &'static str> {
+        let unsigned = s.trim_start_matches(['+', '-']);
+        let non_finite = matches!(unsigned, "nan" | "inf" | "NaN" | "Infinity");
+        match dialect {
+            Dialect::Jsona => {}
+            Dialect::Json | Dialect::Jsonc => {
+                if kind != INTEGER && kind != FLOAT {
+                    return Err("only decimal numbers are allowed");
+                }
+                if non_finite {
+                    return Err("non-finite numbers are not allowed");
+                }
+                if s.starts_with('+') {
+                    return Err("leading plus signs are not allowed");
+                }
+                if s.contains('_') {
+                    return Err("underscores in numbers are not allowed");
+                }
+                let fraction = unsigned.split(['e', 'E']).next().unwrap_or_default();
+                if fraction.starts_with('.') || fraction.ends_with('.') {
+                    return Err("numbers must have digits around the decimal point");
+                }
+            }
+            Dialect::Json5 => {
+                if matches!(kind, INTEGER_BIN | INTEGER_OCT) {
+                    return Err("only decimal and hexadecimal numbers are allowed");
+                }
+                if matches!(unsigned, "nan" | "inf") {
+                    return Err("non-finite numbers must be `Infinity` or `NaN`");
+                }
+                if s.contains('_') {
+                    return Err("underscores in numbers are not allowed");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn underscore_integer(s: &str, radix: u32) -> bool {
+        if s.starts_with('_') || s.ends_with('_') {
+            return false;
+        }
+
+        let mut prev_char = 0 as char;
+
+        for c in s.chars() {
+            if c == '_' && !prev_char.is_digit(radix) {
+                return false;
+            }
+            if !c.is_digit(radix) && prev_char == '_' {
+                return false;
+            }
+            prev_char = c;
+        }
+
+        true
+    }
+
     pub(crate) fn comment(s: &str, multiline: bool) -> Result<(), Vec<usize>> {
         let mut err_indices = Vec::new();
 
@@ -702,12 +1092,37 @@ pub(crate) mod validates {
         }
     }
 
-    pub(crate) fn string(s: &str) -> Result<(), Vec<usize>> {
+    /// Validate the characters of a string against the restrictions of a dialect.
+    ///
+    /// Escapes are validated by [validate_quote](crate::util::validate_quote) in every dialect,
+    /// only the escapes a dialect does not have are reported here, at the same index.
+    pub(crate) fn string(s: &str, dialect: Dialect) -> Result<(), Vec<usize>> {
         let mut err_indices = Vec::new();
 
         let mut index = 0;
+        let mut escaped = false;
         for c in s.chars() {
-            if c != '\t' && c.is_ascii_control() {
+            if escaped {
+                let valid = match dialect {
+                    Dialect::Json | Dialect::Jsonc => "\"\\/bfnrtu".contains(c),
+                    // Line continuations
+                    Dialect::Json5 if c == '\r' => {
+                        index += c.len_utf8();
+                        continue;
+                    }
+                    // A line continuation must be followed by the line break right away.
+                    Dialect::Json5 => c != ' ' && c != '\t',
+                    // `\/` is only an escape in the JSON dialects.
+                    Dialect::Jsona => c != '/' && (c == '\t' || !c.is_ascii_control()),
+                };
+                if !valid {
+                    // The index of the backslash.
+                    err_indices.push(index - 1);
+                }
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c.is_ascii_control() && (c != '\t' || dialect.is_json()) {
                 err_indices.push(index);
             }
             index += c.len_utf8();
@@ -754,3 +1169,222 @@ pub(crate) mod validates {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"{ @foo
+  a: { @bar(1)
+    b: [1, 2, 3], @baz
+    c: "c", // comment
+  },
+  d: [{ e: 1 }, { f: 2 }],
+}
+"#;
+
+    macro_rules! assert_reparse {
+        ($source:expr, $start:literal..$end:literal, $text:literal) => {
+            let old = parse($source);
+            let edit = TextEdit::new(TextRange::new($start.into(), $end.into()), $text);
+            let mut text = $source.to_string();
+            edit.apply(&mut text);
+            let expect = parse(&text);
+            let actual = reparse(&old, &edit);
+            assert_eq!(actual.green_node, expect.green_node, "{}", text);
+            assert_eq!(actual.errors, expect.errors, "{}", text);
+        };
+    }
+
+    #[test]
+    fn test_reparse() {
+        assert_reparse!(SOURCE, 30..31, "10");
+        assert_reparse!(SOURCE, 31..31, ", 0");
+        assert_reparse!(SOURCE, 52..55, "'cc'");
+        assert_reparse!(SOURCE, 84..85, "");
+        assert_reparse!(SOURCE, 80..80, "x: true,");
+        assert_reparse!(SOURCE, 84..84, "x");
+        assert_reparse!(SOURCE, 30..30, "@nested(@x)");
+        assert_reparse!(SOURCE, 20..20, "@x(1)");
+        assert_reparse!(SOURCE, 30..30, "}");
+        assert_reparse!(SOURCE, 30..30, "\"");
+        assert_reparse!(SOURCE, 30..30, "/*");
+        assert_reparse!(SOURCE, 0..1, "[");
+        assert_reparse!(SOURCE, 0..0, "");
+        assert_reparse!("[{a:1}{b:2}]", 4..5, "2");
+        assert_reparse!("[{a:1}{b:2}]", 9..10, "x");
+        assert_reparse!("{ @a([1, 2]) }", 6..7, "@b");
+    }
+
+    #[test]
+    fn test_reparse_with_options() {
+        let source = "{\n  a: [1, +2, Infinity],\n  'b': \"x\\\n y\",\n}";
+        for dialect in [Dialect::Json5, Dialect::Json] {
+            let options = ParseOptions { dialect };
+            let old = parse_with_options(source, &options);
+            for (start, end, text) in [(8, 9, "NaN"), (11, 13, "-Infinity"), (23, 23, ", 0x1F")] {
+                let edit = TextEdit::new(TextRange::new(start.into(), end.into()), text);
+                let mut text = source.to_string();
+                edit.apply(&mut text);
+                let expect = parse_with_options(&text, &options);
+                let actual = reparse_with_options(&old, &edit, &options);
+                assert_eq!(actual.green_node, expect.green_node, "{text}");
+                assert_eq!(actual.errors, expect.errors, "{dialect:?} {text}");
+            }
+        }
+        let options = ParseOptions {
+            dialect: Dialect::Json5,
+        };
+        let old = parse_with_options("[1, 2]", &options);
+        let edit = TextEdit::new(TextRange::new(4.into(), 5.into()), "NaN");
+        assert!(reparse_with_options(&old, &edit, &options)
+            .errors
+            .is_empty());
+        assert!(!reparse(&old, &edit).errors.is_empty());
+    }
+
+    #[test]
+    fn test_reparse_reuse() {
+        let old = parse(SOURCE);
+        let new = reparse(
+            &old,
+            &TextEdit::new(TextRange::new(30.into(), 31.into()), "4"),
+        );
+        let old = SyntaxNode::new_root(old.green_node);
+        let new = SyntaxNode::new_root(new.green_node);
+        let untouched = |node: &SyntaxNode| {
+            node.descendants()
+                .find(|v| v.kind() == ARRAY && v.text() == "[{ e: 1 }, { f: 2 }]")
+                .unwrap()
+        };
+        assert!(std::ptr::eq(
+            &*untouched(&old).green(),
+            &*untouched(&new).green()
+        ));
+    }
+
+    fn dialect_errors(dialect: Dialect, source: &str) -> Vec<String> {
+        parse_with_options(source, &ParseOptions { dialect })
+            .errors
+            .into_iter()
+            .map(|v| v.message)
+            .collect()
+    }
+
+    #[test]
+    fn test_dialects() {
+        let json = r#"{"a": [1, -2.5e3, "x\/y"], "b": null}"#;
+        for dialect in [Dialect::Json, Dialect::Jsonc, Dialect::Json5] {
+            assert!(dialect_errors(dialect, json).is_empty(), "{dialect:?}");
+        }
+        assert_eq!(
+            dialect_errors(Dialect::Jsona, json),
+            ["invalid character in string"]
+        );
+
+        let jsonc = "{\n  // comment\n  \"a\": [1, 2,],\n}";
+        assert_eq!(
+            dialect_errors(Dialect::Json, jsonc),
+            [
+                "comments are not allowed",
+                "trailing commas are not allowed",
+                "trailing commas are not allowed"
+            ]
+        );
+        assert!(dialect_errors(Dialect::Jsonc, jsonc).is_empty());
+        assert_eq!(
+            dialect_errors(Dialect::Jsonc, "{a: 'x', \"b\": 0x1} @x"),
+            [
+                "keys must be double-quoted strings",
+                "single-quoted strings are not allowed",
+                "only decimal numbers are allowed",
+                "annotations are not allowed"
+            ]
+        );
+        assert_eq!(
+            dialect_errors(Dialect::Json, "[+1, .5, 1_0, nan, \"\t\", \"\\x41\"]"),
+            [
+                "leading plus signs are not allowed",
+                "numbers must have digits around the decimal point",
+                "underscores in numbers are not allowed",
+                "non-finite numbers are not allowed",
+                "invalid character in string",
+                "invalid character in string"
+            ]
+        );
+
+        let json5 =
+            "{\n  a: [Infinity, -Infinity, +NaN, +1, 0xFF, .5, 5.],\n  'b': \"x\\\n y\",\n}";
+        assert_eq!(dialect_errors(Dialect::Json5, json5), Vec::<String>::new());
+        assert_eq!(
+            dialect_errors(Dialect::Json5, "[inf, 0b1, `x`] @x"),
+            [
+                "non-finite numbers must be `Infinity` or `NaN`",
+                "only decimal and hexadecimal numbers are allowed",
+                "backtick strings are not allowed",
+                "annotations are not allowed"
+            ]
+        );
+        assert_eq!(
+            dialect_errors(Dialect::Jsona, json5),
+            [
+                "expected value",
+                r#"expected ",""#,
+                "expected value",
+                r#"expected ",""#,
+                "expected value",
+                "invalid character in string"
+            ]
+        );
+
+        for (source, dialects) in [
+            (r#""\x41""#, [Dialect::Jsona, Dialect::Json5].as_slice()),
+            (r#""\/""#, &[Dialect::Json, Dialect::Jsonc, Dialect::Json5]),
+            (r#""\q""#, &[]),
+            ("\"a\\ \n b\"", &[]),
+        ] {
+            for dialect in [
+                Dialect::Jsona,
+                Dialect::Json,
+                Dialect::Jsonc,
+                Dialect::Json5,
+            ] {
+                let errors = dialect_errors(dialect, source);
+                if dialects.contains(&dialect) {
+                    assert!(errors.is_empty(), "{dialect:?} {source}");
+                } else {
+                    assert!(!errors.is_empty(), "{dialect:?} {source}");
+                }
+            }
+        }
+        assert_eq!(
+            dialect_errors(Dialect::Json, r#""\q""#),
+            ["invalid character in string"]
+        );
+
+        let keys = "{NaN: 1, Infinity: NaN}";
+        assert_eq!(dialect_errors(Dialect::Json5, keys), Vec::<String>::new());
+        assert_eq!(dialect_errors(Dialect::Jsona, keys), ["expected value"]);
+        assert_eq!(
+            dialect_errors(Dialect::Json, "[NaN, Infinity]"),
+            ["expected value", "expected value"]
+        );
+
+        let node = parse_with_options(
+            json5,
+            &ParseOptions {
+                dialect: Dialect::Json5,
+            },
+        )
+        .into_dom();
+        assert!(node.is_valid());
+        assert_eq!(
+            node.to_plain_json(),
+            serde_json::json!({"a": [null, null, null, 1, 255, 0.5, 5.0], "b": "x y"})
+        );
+        assert_eq!(
+            parse("\"x\\\n y\"").into_dom().to_plain_json(),
+            serde_json::json!("xy")
+        );
+    }
+}
@@ -0,0 +1,536 @@
+//! Applying [RFC 6902](https://www.rfc-editor.org/rfc/rfc6902) JSON Patch and
+//! [RFC 7386](https://www.rfc-editor.org/rfc/rfc7386) JSON Merge Patch documents.
+//!
+//! Patches are applied through a [Document], so comments and formatting outside of the
+//! changed values are kept, and so are the annotations of values that are not replaced.
+//!
+//! Paths are [RFC 6901](https://www.rfc-editor.org/rfc/rfc6901) JSON Pointers. A patch
+//! with [Patch::annotations] set reads them as written by [Keys::to_pointer_ext] instead,
+//! where a segment starting with `@` addresses an annotation, e.g. `/version/@mock`, and a
+//! leading `@` of a property is escaped as `~2`. An operation on an annotation path adds,
+//! replaces or removes the annotation of the node at the parent path.
+//!
+//! ```
+//! use jsona::edit::Document;
+//! use jsona::patch::Patch;
+//! use std::str::FromStr;
+//!
+//! let mut doc = Document::from_str(r#"{
+//!   name: "foo", // the name
+//!   version: "1.0.0", @mock
+//! }"#).unwrap();
+//!
+//! let mut patch = Patch::from_str(r#"[
+//!   { "op": "replace", "path": "/version", "value": "1.1.0" },
+//!   { "op": "remove", "path": "/version/@mock" },
+//!   { "op": "add", "path": "/name/@deprecated", "value": null }
+//! ]"#).unwrap();
+//! patch.annotations = true;
+//! doc.apply_patch(&patch).unwrap();
+//!
+//! assert_eq!(doc.to_string(), r#"{
+//!   name: "foo", @deprecated // the name
+//!   version: "1.1.0",
+//! }"#);
+//! ```
+
+use crate::dom::{
+    diff, keys::parse_pointer_index, keys::pointer_segments, keys::PointerSegment, DiffOptions,
+    DomNode, Key, KeyOrIndex, Keys, Node, PointerError,
+};
+use crate::edit::{self, Document};
+use crate::parser;
+
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Error)]
+pub enum Error {
+    #[error("the patch has syntax errors")]
+    InvalidSyntax { errors: Vec<parser::Error> },
+    #[error("invalid operation at index {index}: {message}")]
+    InvalidOperation { index: usize, message: String },
+    #[error("invalid path `{path}`: {error}")]
+    InvalidPath { path: String, error: PointerError },
+    #[error("no value at `{0}`")]
+    NotFound(String),
+    #[error("the value at `{0}` cannot be the target of the operation")]
+    InvalidTarget(String),
+    #[error("cannot move `{from}` into itself at `{path}`")]
+    MoveIntoSelf { from: String, path: String },
+    #[error("the test at `{0}` failed")]
+    TestFailed(String),
+    #[error(transparent)]
+    Edit(#[from] edit::Error),
+}
+
+/// A single operation of a [Patch].
+#[derive(Debug, Clone)]
+pub enum Operation {
+    Add { path: String, value: Node },
+    Remove { path: String },
+    Replace { path: String, value: Node },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Node },
+}
+
+/// A JSON Patch, a list of operations applied in order.
+#[derive(Debug, Clone, Default)]
+pub struct Patch {
+    pub operations: Vec<Operation>,
+    /// Address annotations with `@` segments, see [Keys::to_pointer_ext].
+    pub annotations: bool,
+}
+
+impl Patch {
+    /// Read a patch from an array of operation objects.
+    ///
+    /// Values are kept as nodes, so a patch written in JSONA can add annotated values.
+    pub fn from_node(node: &Node) -> Result<Self, Error> {
+        let items = node
+            .as_array()
+            .ok_or_else(|| Error::InvalidOperation {
+                index: 0,
+                message: "the patch must be an array".into(),
+            })?
+            .value()
+            .read();
+        let operations = items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| operation_from_node(index, item))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            operations,
+            annotations: false,
+        })
+    }
+}
+
+impl FromStr for Patch {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = parser::parse(s);
+        if !parse.errors.is_empty() {
+            return Err(Error::InvalidSyntax {
+                errors: parse.errors,
+            });
+        }
+        Self::from_node(&parse.into_dom())
+    }
+}
+
+impl Document {
+    /// Apply a JSON Patch, the document is left unchanged if any operation fails.
+    pub fn apply_patch(&mut self, patch: &Patch) -> Result<(), Error> {
+        let mut doc = self.clone();
+        for operation in &patch.operations {
+            doc.apply_operation(operation, patch.annotations)?;
+        }
+        *self = doc;
+        Ok(())
+    }
+
+    /// Apply a JSON Merge Patch, the document is left unchanged if it fails.
+    ///
+    /// Properties set to `null` in the patch are removed, objects are merged recursively
+    /// and any other value replaces the target. Annotations of the patch are written
+    /// for new properties only, replaced values keep their annotations.
+    pub fn apply_merge_patch(&mut self, patch: &Node) -> Result<(), Error> {
+        let mut doc = self.clone();
+        doc.merge(&Keys::default(), patch)?;
+        *self = doc;
+        Ok(())
+    }
+
+    fn apply_operation(&mut self, operation: &Operation, ext: bool) -> Result<(), Error> {
+        match operation {
+            Operation::Add { path, value } => self.patch_add(path, value, ext),
+            Operation::Remove { path } => self.patch_remove(path, ext).map(|_| ()),
+            Operation::Replace { path, value } => self.patch_replace(path, value, ext),
+            Operation::Move { from, path } => {
+                if path.starts_with(&format!("{from}/")) {
+                    return Err(Error::MoveIntoSelf {
+                        from: from.clone(),
+                        path: path.clone(),
+                    });
+                }
+                if from == path {
+                    return self.patch_get(from, ext).map(|_| ());
+                }
+                let value = self.patch_remove(from, ext)?;
+                self.patch_add(path, &value, ext)
+            }
+            Operation::Copy { from, path } => {
+                let value = self.patch_get(from, ext)?;
+                self.patch_add(path, &value, ext)
+            }
+            Operation::Test { path, value } => {
+                let node = self.patch_get(path, ext)?;
+                let equal = diff(&node, value, &DiffOptions::default())
+                    .iter()
+                    .all(|v| v.keys().iter().any(|k| k.is_annotation_key()));
+                if equal {
+                    Ok(())
+                } else {
+                    Err(Error::TestFailed(path.clone()))
+                }
+            }
+        }
+    }
+
+    fn patch_get(&self, path: &str, ext: bool) -> Result<Node, Error> {
+        let target = self.resolve(path, ext)?;
+        match target.last {
+            None => Ok(self.root()),
+            Some(last) => self
+                .root()
+                .path(&target.parent)
+                .and_then(|v| v.get(&last))
+                .ok_or_else(|| Error::NotFound(path.to_string())),
+        }
+    }
+
+    fn patch_add(&mut self, path: &str, value: &Node, ext: bool) -> Result<(), Error> {
+        let target = self.resolve(path, ext)?;
+        let parent = &target.parent;
+        match target.last {
+            None => self.set_value(parent, value)?,
+            Some(KeyOrIndex::Index(index)) => self.insert_item(parent, index, value)?,
+            Some(KeyOrIndex::Key(key)) if key.is_annotation() => {
+                let node = self.root().path(parent);
+                if node.and_then(|v| v.get(&key.clone().into())).is_some() {
+                    self.remove_annotation(parent, key.value())?;
+                }
+                self.add_annotation(parent, key.value(), value)?;
+            }
+            Some(KeyOrIndex::Key(key)) => {
+                let keys = parent.join(key.clone());
+                if self.root().path(&keys).is_some() {
+                    self.set_value(&keys, value)?;
+                } else {
+                    self.insert_property(parent, key.value(), value)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace the value in place, keeping its annotations and position.
+    fn patch_replace(&mut self, path: &str, value: &Node, ext: bool) -> Result<(), Error> {
+        let old = self.patch_get(path, ext)?;
+        let target = self.resolve(path, ext)?;
+        let keys = match target.last {
+            Some(last) => target.parent.join(last),
+            None => target.parent,
+        };
+        let is_annotation = keys
+            .last()
+            .map(|v| v.is_annotation_key())
+            .unwrap_or_default();
+        if is_annotation && (old.syntax().is_none() || value.is_null()) {
+            // An annotation without value has nothing to replace in place.
+            self.patch_remove(path, ext)?;
+            return self.patch_add(path, value, ext);
+        }
+        Ok(self.set_value(&keys, value)?)
+    }
+
+    fn patch_remove(&mut self, path: &str, ext: bool) -> Result<Node, Error> {
+        let value = self.patch_get(path, ext)?;
+        let target = self.resolve(path, ext)?;
+        let parent = &target.parent;
+        match target.last {
+            None => return Err(Error::InvalidTarget(path.to_string())),
+            Some(KeyOrIndex::Index(index)) => self.remove_item(parent, index)?,
+            Some(KeyOrIndex::Key(key)) if key.is_annotation() => {
+                self.remove_annotation(parent, key.value())?
+            }
+            Some(KeyOrIndex::Key(key)) => self.remove_property(parent, key.value())?,
+        };
+        Ok(value)
+    }
+
+    /// Resolve a pointer into keys against the current document.
+    ///
+    /// The last segment is resolved as an insertion point, so it may not exist yet,
+    /// and `-` refers to the end of an array.
+    fn resolve(&self, path: &str, ext: bool) -> Result<Target, Error> {
+        let segments = pointer_segments(path, ext)
+            .map_err(|error| Error::InvalidPath {
+                path: path.to_string(),
+                error,
+            })?
+            .unwrap_or_default();
+        let not_found = || Error::NotFound(path.to_string());
+        let mut node = self.root();
+        let mut parent = Keys::default();
+        let mut last = None;
+        let len = segments.len();
+        for (i, segment) in segments.into_iter().enumerate() {
+            let key = match segment {
+                PointerSegment::Annotation(v) => KeyOrIndex::Key(Key::annotation(v)),
+                PointerSegment::Property(v) => match &node {
+                    Node::Array(array) => {
+                        let len = array.value().read().len();
+                        let index = match v.as_str() {
+                            "-" => len,
+                            _ => parse_pointer_index(&v).ok_or_else(not_found)?,
+                        };
+                        if index > len {
+                            return Err(not_found());
+                        }
+                        KeyOrIndex::Index(index)
+                    }
+                    Node::Object(_) => KeyOrIndex::property(v),
+                    _ => return Err(not_found()),
+                },
+            };
+            if i + 1 == len {
+                last = Some(key);
+            } else {
+                node = node.get(&key).ok_or_else(not_found)?;
+                parent = parent.join(key);
+            }
+        }
+        Ok(Target { parent, last })
+    }
+
+    fn merge(&mut self, keys: &Keys, patch: &Node) -> Result<(), Error> {
+        let target = self.root().path(keys);
+        let (Some(Node::Object(target)), Node::Object(patch)) = (&target, patch) else {
+            return Ok(self.set_value(keys, &without_nulls(patch))?);
+        };
+        for (key, value) in patch.value().read().iter() {
+            let exists = target.get(key).is_some();
+            if value.is_null() {
+                if exists {
+                    self.remove_property(keys, key.value())?;
+                }
+            } else if exists {
+                self.merge(&keys.join(key.clone()), value)?;
+            } else {
+                self.insert_property(keys, key.value(), &without_nulls(value))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Target {
+    parent: Keys,
+    /// `None` refers to the whole document.
+    last: Option<KeyOrIndex>,
+}
+
+fn operation_from_node(index: usize, node: &Node) -> Result<Operation, Error> {
+    let invalid = |message: &str| Error::InvalidOperation {
+        index,
+        message: message.to_string(),
+    };
+    let object = node
+        .as_object()
+        .ok_or_else(|| invalid("the operation must be an object"))?;
+    let string = |name: &str| {
+        let value = object
+            .get(&Key::property(name))
+            .ok_or_else(|| invalid(&format!("missing `{name}`")))?;
+        value
+            .as_string()
+            .map(|v| v.value().to_string())
+            .ok_or_else(|| invalid(&format!("`{name}` must be a string")))
+    };
+    let value = || {
+        object
+            .get(&Key::property("value"))
+            .ok_or_else(|| invalid("missing `value`"))
+    };
+    let operation = match string("op")?.as_str() {
+        "add" => Operation::Add {
+            path: string("path")?,
+            value: value()?,
+        },
+        "remove" => Operation::Remove {
+            path: string("path")?,
+        },
+        "replace" => Operation::Replace {
+            path: string("path")?,
+            value: value()?,
+        },
+        "move" => Operation::Move {
+            from: string("from")?,
+            path: string("path")?,
+        },
+        "copy" => Operation::Copy {
+            from: string("from")?,
+            path: string("path")?,
+        },
+        "test" => Operation::Test {
+            path: string("path")?,
+            value: value()?,
+        },
+        op => return Err(invalid(&format!("unknown op `{op}`"))),
+    };
+    Ok(operation)
+}
+
+/// Remove properties with `null` values from the objects of a merge patch.
+fn without_nulls(node: &Node) -> Node {
+    match node {
+        Node::Object(object) => {
+            let mut map = crate::dom::Map::default();
+            for (key, value) in object.value().read().iter() {
+                if !value.is_null() {
+                    map.add(key.clone(), without_nulls(value), None);
+                }
+            }
+            crate::dom::Object::new(map, object.annotations().cloned()).into()
+        }
+        _ => node.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"{ @describe("package")
+  name: 'foo', // the name
+  version: 0x10, @mock
+  tags: [
+    "a", // first
+    "b"
+  ],
+  nested: { a: 1, b: [1, 2] },
+}
+"#;
+
+    fn apply(patch: &str) -> Result<String, Error> {
+        let mut doc = Document::from_str(SOURCE).unwrap();
+        doc.apply_patch(&patch.parse()?)?;
+        Ok(doc.to_string())
+    }
+
+    fn apply_ext(patch: &str) -> Result<String, Error> {
+        let mut doc = Document::from_str(SOURCE).unwrap();
+        let mut patch: Patch = patch.parse()?;
+        patch.annotations = true;
+        doc.apply_patch(&patch)?;
+        Ok(doc.to_string())
+    }
+
+    fn merge(patch: &str) -> String {
+        let mut doc = Document::from_str(SOURCE).unwrap();
+        doc.apply_merge_patch(&patch.parse().unwrap()).unwrap();
+        doc.to_string()
+    }
+
+    #[test]
+    fn test_patch() {
+        assert_eq!(
+            apply(r#"[{ "op": "replace", "path": "/version", "value": 17 }]"#).unwrap(),
+            SOURCE.replace("0x10", "17")
+        );
+        assert_eq!(
+            apply(r#"[{ "op": "add", "path": "/tags/-", "value": "c" }]"#).unwrap(),
+            SOURCE.replace("    \"b\"\n", "    \"b\",\n    \"c\"\n")
+        );
+        assert_eq!(
+            apply(r#"[{ "op": "remove", "path": "/tags/0" }]"#).unwrap(),
+            SOURCE.replace("    \"a\", // first\n", "")
+        );
+        assert_eq!(
+            apply(
+                r#"[
+                  { "op": "move", "from": "/nested/a", "path": "/nested/c" },
+                  { "op": "copy", "from": "/nested/b/1", "path": "/nested/b/0" },
+                  { "op": "test", "path": "/nested", "value": { "b": [2, 1, 2], "c": 1 } }
+                ]"#
+            )
+            .unwrap(),
+            SOURCE.replace("{ a: 1, b: [1, 2] }", "{ b: [2, 1, 2], c: 1 }")
+        );
+        assert!(matches!(
+            apply(r#"[{ "op": "test", "path": "/version", "value": 17 }]"#),
+            Err(Error::TestFailed(_))
+        ));
+        assert!(matches!(
+            apply(r#"[{ "op": "remove", "path": "/missing" }]"#),
+            Err(Error::NotFound(_))
+        ));
+        assert!(matches!(
+            apply(r#"[{ "op": "add", "path": "/tags/3", "value": 1 }]"#),
+            Err(Error::NotFound(_))
+        ));
+        assert!(matches!(
+            apply(r#"[{ "op": "move", "from": "/nested", "path": "/nested/x" }]"#),
+            Err(Error::MoveIntoSelf { .. })
+        ));
+        assert!(matches!(
+            apply(r#"[{ "op": "jump", "path": "/" }]"#),
+            Err(Error::InvalidOperation { index: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn test_patch_annotations() {
+        assert_eq!(
+            apply_ext(
+                r#"[
+                  { "op": "remove", "path": "/version/@mock" },
+                  { "op": "replace", "path": "/@describe", "value": "pkg" },
+                  { "op": "add", "path": "/tags/0/@x.y", "value": { "k": true } }
+                ]"#
+            )
+            .unwrap(),
+            SOURCE
+                .replace("0x10, @mock", "0x10,")
+                .replace(r#"@describe("package")"#, r#"@describe("pkg")"#)
+                .replace(r#""a", // first"#, r#""a", @x.y({k:true}) // first"#)
+        );
+        assert_eq!(
+            apply(r#"[{ "op": "test", "path": "/version", "value": 16 }]"#).unwrap(),
+            SOURCE
+        );
+        assert_eq!(
+            apply(r#"[{ "op": "add", "path": "/@context", "value": 1 }]"#).unwrap(),
+            SOURCE.replace("[1, 2] },\n", "[1, 2] },\n  \"@context\": 1,\n")
+        );
+        assert!(matches!(
+            apply(r#"[{ "op": "remove", "path": "/version/@mock" }]"#),
+            Err(Error::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_patch_atomic() {
+        let mut doc = Document::from_str(SOURCE).unwrap();
+        let patch: Patch = r#"[
+          { "op": "remove", "path": "/name" },
+          { "op": "remove", "path": "/name" }
+        ]"#
+        .parse()
+        .unwrap();
+        assert!(doc.apply_patch(&patch).is_err());
+        assert_eq!(doc.to_string(), SOURCE);
+    }
+
+    #[test]
+    fn test_merge_patch() {
+        assert_eq!(
+            merge(
+                r#"{ "name": null, "version": 17, "nested": { "a": null, "c": { "d": 1, "e": null } } }"#
+            ),
+            SOURCE
+                .replace("  name: 'foo', // the name\n", "")
+                .replace("0x10", "17")
+                .replace(
+                    "{ a: 1, b: [1, 2] }",
+                    "{ b: [1, 2], c: {\n      d: 1\n    } }"
+                )
+        );
+        assert_eq!(merge("[1]"), "[\n  1\n]\n");
+    }
+}
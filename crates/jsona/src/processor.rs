@@ -0,0 +1,485 @@
+//! Evaluating annotations with processors registered by name.
+//!
+//! A [Pipeline] walks a tree and hands every annotation with a registered
+//! [AnnotationProcessor] to it. The processor can replace or remove the annotated value,
+//! add properties or items next to it and report diagnostics. Annotations that were
+//! processed are dropped from the output, the others are kept.
+//!
+//! ```
+//! use jsona::dom::{Node, String};
+//! use jsona::processor::{Context, Pipeline};
+//!
+//! let mut pipeline = Pipeline::new();
+//! pipeline.register("upper", |cx: &mut Context| {
+//!     match cx.node().as_string() {
+//!         Some(v) => cx.replace(String::new(v.value().to_uppercase(), None).into()),
+//!         None => cx.error("expected a string"),
+//!     }
+//! });
+//!
+//! let node: Node = "{ a: 'x' @upper @mock, b: 1 @upper }".parse().unwrap();
+//! let output = pipeline.run(&node);
+//! assert_eq!(output.node.unwrap().to_canonical_string(), r#"{"a":"X" @mock,"b":1}"#);
+//! assert_eq!(output.diagnostics[0].to_string(), "@upper at .b: expected a string");
+//! ```
+
+use crate::dom::visitor::{is_same, rebuild, with_annotations};
+use crate::dom::{Annotations, DomNode, Key, KeyOrIndex, Keys, Map, Node};
+
+use rowan::TextRange;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// Evaluates one kind of annotation.
+pub trait AnnotationProcessor {
+    /// Process an annotation of a node, the changes are recorded in the context.
+    fn process(&self, cx: &mut Context<'_>);
+}
+
+impl<F> AnnotationProcessor for F
+where
+    F: Fn(&mut Context<'_>),
+{
+    fn process(&self, cx: &mut Context<'_>) {
+        self(cx)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem reported by a processor.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    /// Path of the annotated node.
+    pub keys: Keys,
+    /// The annotation being processed.
+    pub annotation: Key,
+    pub severity: Severity,
+    pub message: String,
+    /// Defaults to the range of the annotation.
+    pub range: Option<TextRange>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let keys = if self.keys.is_empty() {
+            "the root".to_string()
+        } else {
+            self.keys.to_string()
+        };
+        write!(f, "{} at {}: {}", self.annotation, keys, self.message)
+    }
+}
+
+/// The annotation handed to a processor and the changes it makes.
+pub struct Context<'a> {
+    keys: &'a Keys,
+    key: &'a Key,
+    value: &'a Node,
+    node: &'a Node,
+    range: Option<TextRange>,
+    replacement: Option<Option<Node>>,
+    siblings: Vec<(Option<Key>, Node)>,
+    diagnostics: &'a mut Vec<Diagnostic>,
+}
+
+impl<'a> Context<'a> {
+    /// Path of the annotated node.
+    pub fn keys(&self) -> &Keys {
+        self.keys
+    }
+
+    /// Key of the annotation, like `@env`.
+    pub fn key(&self) -> &Key {
+        self.key
+    }
+
+    /// Value of the annotation, `null` for an annotation without parentheses.
+    pub fn value(&self) -> &Node {
+        self.value
+    }
+
+    /// The annotated node, after its children and the preceding annotations are processed.
+    ///
+    /// It still carries all of its annotations, including the one being processed.
+    pub fn node(&self) -> &Node {
+        self.node
+    }
+
+    /// Range of the annotation in the source.
+    pub fn range(&self) -> Option<TextRange> {
+        self.range
+    }
+
+    /// Replace the annotated node.
+    ///
+    /// The replacement is not processed again, the annotations of the replaced node that
+    /// have no processor are added to it.
+    pub fn replace(&mut self, node: Node) {
+        self.replacement = Some(Some(node));
+    }
+
+    /// Remove the annotated node, or the property or item holding it.
+    pub fn remove(&mut self) {
+        self.replacement = Some(None);
+    }
+
+    /// Add a property after the annotated property, replacing any property with the same key,
+    /// whether it comes before or after the annotated property.
+    pub fn add_property(&mut self, key: impl Into<String>, node: Node) {
+        match self.keys.last() {
+            Some(KeyOrIndex::Key(k)) if k.is_property() => {
+                self.siblings.push((Some(Key::property(key.into())), node))
+            }
+            _ => self.error("properties can only be added next to a property"),
+        }
+    }
+
+    /// Add an item after the annotated array item.
+    pub fn add_item(&mut self, node: Node) {
+        match self.keys.last() {
+            Some(KeyOrIndex::Index(_)) => self.siblings.push((None, node)),
+            _ => self.error("items can only be added next to an array item"),
+        }
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.report(Severity::Error, message.into(), self.range)
+    }
+
+    pub fn warning(&mut self, message: impl Into<String>) {
+        self.report(Severity::Warning, message.into(), self.range)
+    }
+
+    /// Report a problem at the given range, like the range of a part of the annotation value.
+    pub fn report(&mut self, severity: Severity, message: String, range: Option<TextRange>) {
+        self.diagnostics.push(Diagnostic {
+            keys: self.keys.clone(),
+            annotation: self.key.clone(),
+            severity,
+            message,
+            range,
+        })
+    }
+}
+
+/// The output of a [Pipeline].
+#[derive(Debug, Clone)]
+pub struct Processed {
+    /// The new tree, `None` if the root was removed.
+    pub node: Option<Node>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Processors registered by annotation name.
+#[derive(Default)]
+pub struct Pipeline {
+    processors: HashMap<String, Box<dyn AnnotationProcessor>>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a processor for the annotation with the given name, like `env`
+    /// or `openapi.deprecated`. A leading `@` is ignored.
+    pub fn register(
+        &mut self,
+        name: &str,
+        processor: impl AnnotationProcessor + 'static,
+    ) -> &mut Self {
+        self.processors.insert(
+            name.trim_start_matches('@').to_string(),
+            Box::new(processor),
+        );
+        self
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.processors.contains_key(name.trim_start_matches('@'))
+    }
+
+    /// Build a new tree with the annotations processed.
+    ///
+    /// Nodes are processed after their children and the values of their annotations,
+    /// annotations of a node in the order they are written. Unchanged subtrees are shared
+    /// with the original tree, rebuilt nodes have no syntax.
+    pub fn run(&self, node: &Node) -> Processed {
+        let mut diagnostics = vec![];
+        let (node, _) = self.process_node(&Keys::default(), node, &mut diagnostics);
+        Processed { node, diagnostics }
+    }
+
+    fn process_node(
+        &self,
+        keys: &Keys,
+        node: &Node,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> (Option<Node>, Vec<(Option<Key>, Node)>) {
+        let mut changed = false;
+        let properties = node.as_object().map(|v| {
+            let mut output = Map::default();
+            // Properties added by processors, which replace those that come later.
+            let mut added = HashSet::new();
+            for (key, (value, syntax)) in v.value().read().value.iter() {
+                if added.contains(key.value()) {
+                    changed = true;
+                    continue;
+                }
+                let (new_value, siblings) =
+                    self.process_node(&keys.join(key.clone()), value, diagnostics);
+                if let Some(new_value) = new_value {
+                    changed |= !is_same(value, &new_value);
+                    output.add(key.clone(), new_value, syntax.clone());
+                } else {
+                    changed = true;
+                }
+                for (key, sibling) in siblings {
+                    changed = true;
+                    let key = key.unwrap();
+                    output.value.shift_remove(&key);
+                    output.add(key.clone(), sibling, None);
+                    added.insert(key.value().to_string());
+                }
+            }
+            output
+        });
+        let items = node.as_array().map(|v| {
+            let mut output = vec![];
+            for (idx, value) in v.value().read().iter().enumerate() {
+                let (new_value, siblings) = self.process_node(&keys.join(idx), value, diagnostics);
+                if let Some(new_value) = new_value {
+                    changed |= !is_same(value, &new_value);
+                    output.push(new_value);
+                } else {
+                    changed = true;
+                }
+                changed |= !siblings.is_empty();
+                output.extend(siblings.into_iter().map(|(_, v)| v));
+            }
+            output
+        });
+
+        let mut current = if changed {
+            rebuild(node, node.annotations().cloned(), properties, items)
+        } else {
+            node.clone()
+        };
+        let annotations = match node.annotations() {
+            Some(v) => (**v.value().read()).clone(),
+            None => return (Some(current), vec![]),
+        };
+
+        let mut kept = Map::default();
+        let mut replaced = false;
+        let mut siblings = vec![];
+        for (key, (value, syntax)) in annotations.value.iter() {
+            let value_keys = keys.join(key.clone());
+            let (value, value_siblings) = self.process_node(&value_keys, value, diagnostics);
+            if !value_siblings.is_empty() {
+                diagnostics.push(Diagnostic {
+                    keys: value_keys,
+                    annotation: key.clone(),
+                    severity: Severity::Error,
+                    message: "nothing can be added next to an annotation value".into(),
+                    range: syntax.as_ref().map(|v| v.text_range()),
+                });
+            }
+            let value = match value {
+                Some(v) => v,
+                None => {
+                    changed = true;
+                    continue;
+                }
+            };
+            changed |= !is_same(&annotations.value[key].0, &value);
+            let processor = match self.processors.get(&key.value()[1..]) {
+                Some(v) => v,
+                None => {
+                    kept.add(key.clone(), value, syntax.clone());
+                    continue;
+                }
+            };
+            changed = true;
+            let mut cx = Context {
+                keys,
+                key,
+                value: &value,
+                node: &current,
+                range: syntax
+                    .as_ref()
+                    .map(|v| v.text_range())
+                    .or_else(|| key.text_range()),
+                replacement: None,
+                siblings: vec![],
+                diagnostics,
+            };
+            processor.process(&mut cx);
+            let Context {
+                replacement,
+                siblings: new_siblings,
+                ..
+            } = cx;
+            siblings.extend(new_siblings);
+            match replacement {
+                Some(Some(v)) => {
+                    current = v;
+                    replaced = true;
+                }
+                Some(None) => return (None, siblings),
+                None => {}
+            }
+        }
+
+        if !changed {
+            return (Some(current), siblings);
+        }
+        let node = if replaced {
+            if kept.is_empty() {
+                current
+            } else {
+                let mut map = current
+                    .annotations()
+                    .map(|v| (**v.value().read()).clone())
+                    .unwrap_or_default();
+                for (key, (value, syntax)) in kept.value {
+                    if !map.value.contains_key(&key) {
+                        map.add(key, value, syntax);
+                    }
+                }
+                with_annotations(&current, Some(Annotations::new(map)))
+            }
+        } else {
+            with_annotations(&current, (!kept.is_empty()).then(|| Annotations::new(kept)))
+        };
+        (Some(node), siblings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::{Number, Object};
+
+    fn pipeline() -> Pipeline {
+        let mut pipeline = Pipeline::new();
+        pipeline
+            .register("@double", |cx: &mut Context| match cx.node().as_number() {
+                Some(v) => cx.replace(Number::from_f64(v.as_f64() * 2.0, None).into()),
+                None => cx.warning("not a number"),
+            })
+            .register("drop", |cx: &mut Context| cx.remove())
+            .register("spread", |cx: &mut Context| {
+                let value = cx.value().clone();
+                match cx.keys().last_property_key().map(|k| format!("{k}2")) {
+                    Some(key) => cx.add_property(key, value),
+                    None => cx.add_item(value),
+                }
+            });
+        pipeline
+    }
+
+    #[test]
+    fn test_pipeline() {
+        let node: Node = r#"{
+  a: 1 @double,
+  b: [1, 2 @spread(5), 3 @drop] @mock,
+  c: 'x' @spread('y'),
+  d: { e: 2 @double } @x,
+  f: true @y,
+}"#
+        .parse()
+        .unwrap();
+        let output = pipeline().run(&node);
+        assert!(output.diagnostics.is_empty());
+        let output = output.node.unwrap();
+        assert_eq!(
+            output.to_canonical_string(),
+            r#"{"a":2,"b":[@mock 1,2,5],"c":"x","c2":"y","d":{@x "e":4},"f":true @y}"#
+        );
+        let f = output.path(&"f".parse().unwrap()).unwrap();
+        assert!(is_same(&f, &node.path(&"f".parse().unwrap()).unwrap()));
+    }
+
+    #[test]
+    fn test_pipeline_replace_keeps_annotations() {
+        let node: Node = "[1 @x @double(2) @y]".parse().unwrap();
+        let output = pipeline().run(&node).node.unwrap();
+        assert_eq!(output.to_canonical_string(), "[2 @x @y]");
+    }
+
+    #[test]
+    fn test_pipeline_diagnostics() {
+        let source = "{ a: 'x' @double, b: 1 @spread(1) } @spread";
+        let node: Node = source.parse().unwrap();
+        let output = pipeline().run(&node);
+        let diagnostics: Vec<(String, Severity, &str)> = output
+            .diagnostics
+            .iter()
+            .map(|v| (v.to_string(), v.severity, &source[v.range.unwrap()]))
+            .collect();
+        assert_eq!(
+            diagnostics,
+            [
+                (
+                    "@double at .a: not a number".into(),
+                    Severity::Warning,
+                    "@double"
+                ),
+                (
+                    "@spread at the root: items can only be added next to an array item".into(),
+                    Severity::Error,
+                    "@spread"
+                ),
+            ]
+        );
+        assert_eq!(
+            output.node.unwrap().to_canonical_string(),
+            r#"{"a":"x","b":1,"b2":1}"#
+        );
+        assert!(pipeline().run(&"1 @drop".parse().unwrap()).node.is_none());
+
+        // Annotation values can only have annotations when built with the API.
+        let mut annotations = Map::default();
+        annotations.add(Key::annotation("@x"), "2 @spread(3)".parse().unwrap(), None);
+        let value = with_annotations(&"1".parse().unwrap(), Some(Annotations::new(annotations)));
+        let mut properties = Map::default();
+        properties.add(Key::property("a"), value, None);
+        let output = pipeline().run(&Object::new(properties, None).into());
+        let diagnostics: Vec<String> = output.diagnostics.iter().map(|v| v.to_string()).collect();
+        assert_eq!(
+            diagnostics,
+            ["@spread at .a@x: items can only be added next to an array item"]
+        );
+        assert_eq!(
+            output.node.unwrap().to_canonical_string(),
+            r#"{"a":1 @x(2)}"#
+        );
+    }
+
+    #[test]
+    fn test_pipeline_add_property_replaces() {
+        let properties = |source: &str| -> Vec<String> {
+            let node: Node = source.parse().unwrap();
+            let output = pipeline().run(&node).node.unwrap();
+            let object = output.as_object().unwrap().value().read();
+            object
+                .iter()
+                .map(|(k, v)| format!("{}={}", k.value(), v.to_canonical_string()))
+                .collect()
+        };
+        assert_eq!(
+            properties("{ a2: 0, a: 1 @spread(5), b: 2 }"),
+            ["a=1", "a2=5", "b=2"]
+        );
+        assert_eq!(
+            properties("{ a: 1 @spread, a2: 7, b: 2 }"),
+            ["a=1", "a2=null", "b=2"]
+        );
+    }
+}
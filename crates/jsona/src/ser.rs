@@ -0,0 +1,915 @@
+//! Serialize Rust types to JSONA.
+//!
+//! The [Serializer] builds a [DOM](crate::dom) node, which is then written
+//! as compact text by [to_string] or formatted by [to_string_pretty].
+//!
+//! Annotations can be attached to a value with [Annotated].
+//!
+//! ```
+//! use jsona::{formatter::Options, Annotated};
+//! use serde::Serialize;
+//!
+//! #[derive(Serialize)]
+//! struct Describe {
+//!     describe: &'static str,
+//!     mock: (),
+//! }
+//!
+//! #[derive(Serialize)]
+//! struct User {
+//!     id: Annotated<u64, Describe>,
+//!     name: String,
+//! }
+//!
+//! let user = User {
+//!     id: Annotated::new(1, Describe { describe: "user id", mock: () }),
+//!     name: "foo".into(),
+//! };
+//! assert_eq!(
+//!     jsona::to_string(&user).unwrap(),
+//!     r#"{id:1 @describe("user id") @mock,name:"foo"}"#
+//! );
+//! assert_eq!(
+//!     jsona::to_string_pretty(&user, Options::default()).unwrap(),
+//!     r#"{
+//!   id: 1, @describe("user id") @mock
+//!   name: "foo"
+//! }"#
+//! );
+//! ```
+
+use crate::dom::{self, DomNode, Key, Map, Node};
+use crate::formatter::{self, Options};
+use serde::ser::{self, Impossible, Serialize};
+use serde_json::Number as JsonNumber;
+use std::fmt::Write;
+use thiserror::Error;
+
+/// Serialize the value as a DOM node.
+pub fn to_node<T: ?Sized + Serialize>(value: &T) -> Result<Node, Error> {
+    value.serialize(Serializer)
+}
+
+/// Serialize the value as compact JSONA text.
+pub fn to_string<T: ?Sized + Serialize>(value: &T) -> Result<String, Error> {
+    let node = to_node(value)?;
+    let mut output = String::new();
+    write_compact(&mut output, &node);
+    Ok(output)
+}
+
+/// Serialize the value as JSONA text formatted with the options.
+pub fn to_string_pretty<T: ?Sized + Serialize>(
+    value: &T,
+    options: Options,
+) -> Result<String, Error> {
+    let node = to_node(value)?;
+    Ok(formatter::format(&node.to_string(), options))
+}
+
+#[derive(Debug, Clone, Error)]
+pub enum Error {
+    #[error("{0}")]
+    Custom(String),
+    #[error("key must be a string")]
+    KeyMustBeAString,
+    #[error("number {0} can not be represented")]
+    InvalidNumber(String),
+    #[error("annotations must be a map")]
+    AnnotationsMustBeAMap,
+    #[error("invalid annotation name `{0}`")]
+    InvalidAnnotationName(String),
+}
+
+impl ser::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Error::Custom(msg.to_string())
+    }
+}
+
+pub(crate) const ANNOTATED_TOKEN: &str = "$jsona::Annotated";
+
+/// A value with annotations.
+///
+/// `annotations` must serialize to a map, each entry becomes an annotation
+/// of the value. Entries with unit or `None` values become annotations without value.
+///
+/// Only the JSONA [Serializer] knows about annotations, other serializers
+/// see a struct with the `value` and `annotations` fields.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Annotated<T, A> {
+    pub value: T,
+    pub annotations: A,
+}
+
+impl<T, A> Annotated<T, A> {
+    pub fn new(value: T, annotations: A) -> Self {
+        Self { value, annotations }
+    }
+}
+
+impl<T: Serialize, A: Serialize> Serialize for Annotated<T, A> {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use ser::SerializeStruct;
+        let mut s = serializer.serialize_struct(ANNOTATED_TOKEN, 2)?;
+        s.serialize_field("value", &self.value)?;
+        s.serialize_field("annotations", &self.annotations)?;
+        s.end()
+    }
+}
+
+/// A serializer building a DOM node.
+#[derive(Debug, Clone, Copy)]
+pub struct Serializer;
+
+macro_rules! serialize_number {
+    ($($method:ident: $ty:ty,)*) => {
+        $(
+            fn $method(self, v: $ty) -> Result<Node, Error> {
+                Ok(dom::Number::new(JsonNumber::from(v), None).into())
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = Node;
+    type Error = Error;
+
+    type SerializeSeq = SerializeVec;
+    type SerializeTuple = SerializeVec;
+    type SerializeTupleStruct = SerializeVec;
+    type SerializeTupleVariant = SerializeVariant<SerializeVec>;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeStruct;
+    type SerializeStructVariant = SerializeVariant<SerializeMap>;
+
+    fn serialize_bool(self, v: bool) -> Result<Node, Error> {
+        Ok(dom::Bool::new(v, None).into())
+    }
+
+    serialize_number! {
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Node, Error> {
+        match i64::try_from(v) {
+            Ok(v) => self.serialize_i64(v),
+            Err(_) => large_integer(v.to_string()),
+        }
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Node, Error> {
+        match u64::try_from(v) {
+            Ok(v) => self.serialize_u64(v),
+            Err(_) => large_integer(v.to_string()),
+        }
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Node, Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Node, Error> {
+        Ok(dom::Number::from_f64(v, None).into())
+    }
+
+    fn serialize_char(self, v: char) -> Result<Node, Error> {
+        self.serialize_str(&v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Node, Error> {
+        Ok(dom::String::new(v.to_string(), None).into())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Node, Error> {
+        let items = v
+            .iter()
+            .map(|v| self.serialize_u8(*v))
+            .collect::<Result<_, _>>()?;
+        Ok(dom::Array::new(items, None).into())
+    }
+
+    fn serialize_none(self) -> Result<Node, Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Node, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Node, Error> {
+        Ok(dom::Null::new(None).into())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Node, Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Node, Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Node, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Node, Error> {
+        let mut map = Map::default();
+        map.add(Key::property(variant), value.serialize(self)?, None);
+        Ok(dom::Object::new(map, None).into())
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeVec, Error> {
+        Ok(SerializeVec {
+            items: Vec::with_capacity(len.unwrap_or_default()),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeVec, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeVec, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeVariant<SerializeVec>, Error> {
+        Ok(SerializeVariant {
+            variant,
+            inner: self.serialize_seq(Some(len))?,
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeMap, Error> {
+        Ok(SerializeMap {
+            map: Map::default(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, name: &'static str, _len: usize) -> Result<SerializeStruct, Error> {
+        if name == ANNOTATED_TOKEN {
+            Ok(SerializeStruct::Annotated {
+                value: None,
+                annotations: None,
+            })
+        } else {
+            Ok(SerializeStruct::Map(self.serialize_map(None)?))
+        }
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<SerializeVariant<SerializeMap>, Error> {
+        Ok(SerializeVariant {
+            variant,
+            inner: self.serialize_map(None)?,
+        })
+    }
+}
+
+pub struct SerializeVec {
+    items: Vec<Node>,
+}
+
+impl SerializeVec {
+    fn push<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.items.push(value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Node {
+        dom::Array::new(self.items, None).into()
+    }
+}
+
+impl ser::SerializeSeq for SerializeVec {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Node, Error> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTuple for SerializeVec {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Node, Error> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeVec {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Node, Error> {
+        Ok(self.finish())
+    }
+}
+
+pub struct SerializeMap {
+    map: Map,
+    key: Option<Key>,
+}
+
+impl SerializeMap {
+    fn finish(self) -> Node {
+        dom::Object::new(self.map, None).into()
+    }
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Error> {
+        self.key = Some(Key::property(key.serialize(MapKeySerializer)?));
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| Error::Custom("serialize_value called before serialize_key".into()))?;
+        self.map.add(key, value.serialize(Serializer)?, None);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Node, Error> {
+        Ok(self.finish())
+    }
+}
+
+pub enum SerializeStruct {
+    Map(SerializeMap),
+    Annotated {
+        value: Option<Node>,
+        annotations: Option<Node>,
+    },
+}
+
+impl ser::SerializeStruct for SerializeStruct {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        match self {
+            SerializeStruct::Map(map) => {
+                map.map
+                    .add(Key::property(key), value.serialize(Serializer)?, None);
+            }
+            SerializeStruct::Annotated {
+                value: node,
+                annotations,
+            } => {
+                let value = Some(value.serialize(Serializer)?);
+                match key {
+                    "value" => *node = value,
+                    _ => *annotations = value,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<Node, Error> {
+        match self {
+            SerializeStruct::Map(map) => Ok(map.finish()),
+            SerializeStruct::Annotated { value, annotations } => {
+                let value = value.unwrap_or_else(|| dom::Null::new(None).into());
+                match annotations {
+                    Some(Node::Object(annotations)) => annotate(value, &annotations),
+                    Some(Node::Null(_)) | None => Ok(value),
+                    Some(_) => Err(Error::AnnotationsMustBeAMap),
+                }
+            }
+        }
+    }
+}
+
+pub struct SerializeVariant<T> {
+    variant: &'static str,
+    inner: T,
+}
+
+impl<T> SerializeVariant<T> {
+    fn finish(self, value: Node) -> Node {
+        let mut map = Map::default();
+        map.add(Key::property(self.variant), value, None);
+        dom::Object::new(map, None).into()
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeVariant<SerializeVec> {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.inner.push(value)
+    }
+
+    fn end(mut self) -> Result<Node, Error> {
+        let items = std::mem::take(&mut self.inner.items);
+        Ok(self.finish(dom::Array::new(items, None).into()))
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeVariant<SerializeMap> {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.inner
+            .map
+            .add(Key::property(key), value.serialize(Serializer)?, None);
+        Ok(())
+    }
+
+    fn end(mut self) -> Result<Node, Error> {
+        let map = std::mem::take(&mut self.inner.map);
+        Ok(self.finish(dom::Object::new(map, None).into()))
+    }
+}
+
+/// An integer beyond `i64`/`u64`, written in decimal notation.
+///
+/// Without the `arbitrary_precision` feature it can not be held by a number.
+fn large_integer(text: String) -> Result<Node, Error> {
+    if cfg!(feature = "arbitrary_precision") {
+        if let Ok(value) = text.parse::<JsonNumber>() {
+            return Ok(dom::Number::new(value, None).into());
+        }
+    }
+    Err(Error::InvalidNumber(text))
+}
+
+/// Attach the entries of the object as annotations of the node,
+/// keeping the annotations it already has.
+fn annotate(node: Node, object: &dom::Object) -> Result<Node, Error> {
+    let mut map = node
+        .annotations()
+        .map(|v| v.value().read().as_ref().clone())
+        .unwrap_or_default();
+    for (key, value) in object.value().read().iter() {
+        if !dom::is_annotation_name(key.value()) {
+            return Err(Error::InvalidAnnotationName(key.value().to_string()));
+        }
+        map.add(
+            Key::annotation(format!("@{}", key.value())),
+            value.clone(),
+            None,
+        );
+    }
+    let annotations = Some(dom::Annotations::new(map));
+    Ok(match node {
+        Node::Null(_) => dom::Null::new(annotations).into(),
+        Node::Bool(v) => dom::Bool::new(v.value(), annotations).into(),
+        Node::Number(v) => dom::Number::with_repr(v.value().clone(), v.repr(), annotations).into(),
+        Node::String(v) => dom::String::new(v.value().to_string(), annotations).into(),
+        Node::Array(v) => dom::Array::new(v.value().read().to_vec(), annotations).into(),
+        Node::Object(v) => dom::Object::new(v.value().read().as_ref().clone(), annotations).into(),
+    })
+}
+
+struct MapKeySerializer;
+
+macro_rules! serialize_key_display {
+    ($($method:ident: $ty:ty,)*) => {
+        $(
+            fn $method(self, v: $ty) -> Result<String, Error> {
+                Ok(v.to_string())
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for MapKeySerializer {
+    type Ok = String;
+    type Error = Error;
+
+    type SerializeSeq = Impossible<String, Error>;
+    type SerializeTuple = Impossible<String, Error>;
+    type SerializeTupleStruct = Impossible<String, Error>;
+    type SerializeTupleVariant = Impossible<String, Error>;
+    type SerializeMap = Impossible<String, Error>;
+    type SerializeStruct = Impossible<String, Error>;
+    type SerializeStructVariant = Impossible<String, Error>;
+
+    serialize_key_display! {
+        serialize_bool: bool,
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_i128: i128,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_u128: u128,
+        serialize_char: char,
+        serialize_str: &str,
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<String, Error> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<String, Error> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<String, Error> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_none(self) -> Result<String, Error> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<String, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<String, Error> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String, Error> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<String, Error> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String, Error> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(Error::KeyMustBeAString)
+    }
+}
+
+/// Write the node without any whitespace except around annotations.
+pub(crate) fn write_compact(output: &mut String, node: &Node) {
+    write_compact_impl(output, node, false)
+}
+
+/// Write the node like [write_compact], but never break the line,
+/// strings and keys spanning multiple lines are written with escapes instead.
+pub(crate) fn write_compact_line(output: &mut String, node: &Node) {
+    write_compact_impl(output, node, true)
+}
+
+/// Write the annotations of the node, returns the separator for the first item.
+pub(crate) fn write_compact_annotations(
+    output: &mut String,
+    node: &Node,
+    inner: bool,
+) -> &'static str {
+    write_compact_annotations_impl(output, node, inner, false)
+}
+
+fn write_compact_impl(output: &mut String, node: &Node, single_line: bool) {
+    match node {
+        Node::Array(v) => {
+            output.push('[');
+            let mut sep = write_compact_annotations_impl(output, node, true, single_line);
+            for item in v.value().read().iter() {
+                output.push_str(sep);
+                write_compact_impl(output, item, single_line);
+                sep = ",";
+            }
+            output.push(']');
+        }
+        Node::Object(v) => {
+            output.push('{');
+            let mut sep = write_compact_annotations_impl(output, node, true, single_line);
+            for (key, value) in v.value().read().iter() {
+                output.push_str(sep);
+                if single_line && is_multiline(key.value()) {
+                    output.push_str(&escape_line(key.value()));
+                } else {
+                    let _ = write!(output, "{key}");
+                }
+                output.push(':');
+                write_compact_impl(output, value, single_line);
+                sep = ",";
+            }
+            output.push('}');
+        }
+        _ => {
+            let text = node.scalar_text().unwrap_or_default();
+            match node {
+                Node::String(v) if single_line && is_multiline(&text) => {
+                    output.push_str(&escape_line(v.value()))
+                }
+                _ => output.push_str(&text),
+            }
+            write_compact_annotations_impl(output, node, false, single_line);
+        }
+    }
+}
+
+fn write_compact_annotations_impl(
+    output: &mut String,
+    node: &Node,
+    inner: bool,
+    single_line: bool,
+) -> &'static str {
+    let annotations = match node.annotations() {
+        Some(v) => v.value().read(),
+        None => return "",
+    };
+    if annotations.is_empty() {
+        return "";
+    }
+    for (i, (key, value)) in annotations.iter().enumerate() {
+        if i > 0 || !inner {
+            output.push(' ');
+        }
+        let _ = write!(output, "{key}");
+        if !value.is_null() {
+            output.push('(');
+            write_compact_impl(output, value, single_line);
+            output.push(')');
+        }
+    }
+    " "
+}
+
+fn is_multiline(s: &str) -> bool {
+    s.contains(['\n', '\r'])
+}
+
+/// Double-quote the string, escaping line breaks.
+fn escape_line(s: &str) -> String {
+    serde_json::to_string(s).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    struct Spec {
+        name: &'static str,
+        version: Option<u32>,
+        tags: Annotated<Vec<&'static str>, BTreeMap<&'static str, ()>>,
+        ports: BTreeMap<u16, Protocol>,
+        level: Annotated<Level, Mock>,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Protocol {
+        Tcp,
+        Udp { buffer: usize },
+    }
+
+    #[derive(Serialize)]
+    enum Level {
+        Debug(bool),
+    }
+
+    #[derive(Serialize)]
+    struct Mock {
+        mock: Option<&'static str>,
+        example: Annotated<u8, ()>,
+    }
+
+    fn spec() -> Spec {
+        Spec {
+            name: "foo bar",
+            version: None,
+            tags: Annotated::new(vec!["a", "b"], BTreeMap::from([("unique", ())])),
+            ports: BTreeMap::from([(53, Protocol::Udp { buffer: 512 }), (80, Protocol::Tcp)]),
+            level: Annotated::new(
+                Level::Debug(true),
+                Mock {
+                    mock: None,
+                    example: Annotated::new(1, ()),
+                },
+            ),
+        }
+    }
+
+    #[test]
+    fn test_to_string() {
+        let output = to_string(&spec()).unwrap();
+        assert_eq!(
+            output,
+            r#"{name:"foo bar",version:null,tags:[@unique "a","b"],ports:{53:{udp:{buffer:512}},80:"tcp"},level:{@mock @example(1) Debug:true}}"#
+        );
+        let parse = crate::parser::parse(&output);
+        assert!(parse.errors.is_empty());
+        assert_eq!(
+            parse.into_dom().to_plain_json(),
+            to_node(&spec()).unwrap().to_plain_json()
+        );
+    }
+
+    #[test]
+    fn test_to_string_pretty() {
+        let options = Options {
+            indent_string: "    ".into(),
+            trailing_comma: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            to_string_pretty(&spec(), options).unwrap(),
+            r#"{
+    name: "foo bar",
+    version: null,
+    tags: [ @unique
+        "a",
+        "b",
+    ],
+    ports: {
+        53: {
+            udp: {
+                buffer: 512,
+            },
+        },
+        80: "tcp",
+    },
+    level: { @mock @example(1)
+        Debug: true,
+    },
+}"#
+        );
+    }
+
+    #[test]
+    fn test_to_string_non_finite() {
+        assert_eq!(
+            to_string(&[f64::NAN, f64::INFINITY, f64::NEG_INFINITY]).unwrap(),
+            "[nan,inf,-inf]"
+        );
+    }
+
+    #[test]
+    fn test_to_string_error() {
+        assert!(matches!(
+            to_string(&BTreeMap::from([(vec![1], 1)])),
+            Err(Error::KeyMustBeAString)
+        ));
+        assert!(matches!(
+            to_string(&Annotated::new(1, 2)),
+            Err(Error::AnnotationsMustBeAMap)
+        ));
+        assert!(matches!(
+            to_string(&Annotated::new(1, BTreeMap::from([("my note", "x")]))),
+            Err(Error::InvalidAnnotationName(name)) if name == "my note"
+        ));
+        assert!(matches!(
+            to_string(&Annotated::new(1, BTreeMap::from([("x.", "x")]))),
+            Err(Error::InvalidAnnotationName(_))
+        ));
+        assert_eq!(
+            to_string(&Annotated::new(1, BTreeMap::from([("openapi.example", 2)]))).unwrap(),
+            "1 @openapi.example(2)"
+        );
+    }
+
+    #[test]
+    fn test_to_string_large_integer() {
+        let value = (i128::from(i64::MIN) - 1, u128::from(u64::MAX) + 1);
+        if cfg!(feature = "arbitrary_precision") {
+            assert_eq!(
+                to_string(&value).unwrap(),
+                "[-9223372036854775809,18446744073709551616]"
+            );
+        } else {
+            assert!(matches!(to_string(&value), Err(Error::InvalidNumber(_))));
+        }
+    }
+}
@@ -23,7 +23,8 @@ pub enum SyntaxKind {
     #[regex(r"[A-Za-z0-9_]+", priority = 2)]
     IDENT,
 
-    #[regex(r"@[A-Za-z0-9_]*")]
+    /// An annotation key, optionally namespaced with dots, e.g. `@openapi.deprecated`.
+    #[regex(r"@[A-Za-z0-9_]*(\.[A-Za-z0-9_]+)*")]
     ANNOTATION_KEY,
 
     /// Not part of the regular JSONA syntax, only used to allow
@@ -218,13 +219,13 @@ fn lex_comment_block(lex: &mut Lexer<SyntaxKind>) -> bool {
         }
 
         if c == '/' && asterisk_found {
-            lex.bump(remainder[0..total_len].as_bytes().len());
+            lex.bump(remainder[0..total_len].len());
             return true;
         }
 
         asterisk_found = false;
     }
-    lex.bump(remainder[0..total_len].as_bytes().len());
+    lex.bump(remainder[0..total_len].len());
     false
 }
 
@@ -254,14 +255,19 @@ fn lex_string(lex: &mut Lexer<SyntaxKind>, quote: char, multiline: bool) -> bool
             continue;
         }
 
-        if (c == quote && !escaped) || (c == '\n' && !multiline) {
-            lex.bump(remainder[0..total_len].as_bytes().len());
+        // Keep escaping a line continuation that ends with `\r\n`.
+        if c == '\r' && escaped {
+            continue;
+        }
+
+        if (c == quote || (c == '\n' && !multiline)) && !escaped {
+            lex.bump(remainder[0..total_len].len());
             return true;
         }
 
         escaped = false;
     }
-    lex.bump(remainder[0..total_len].as_bytes().len());
+    lex.bump(remainder[0..total_len].len());
     false
 }
 
@@ -301,5 +307,13 @@ mod tests {
         assert_lex!("a?i*", SyntaxKind::IDENT_WITH_GLOB);
         assert_lex!("*", SyntaxKind::IDENT_WITH_GLOB);
         assert_lex!("**", SyntaxKind::IDENT_WITH_GLOB);
+        assert_lex!("@mock", SyntaxKind::ANNOTATION_KEY);
+
+        let mut lex = SyntaxKind::lexer("@openapi.v3.deprecated(true)");
+        assert_eq!(lex.next(), Some(Ok(SyntaxKind::ANNOTATION_KEY)));
+        assert_eq!(lex.slice(), "@openapi.v3.deprecated");
+        let mut lex = SyntaxKind::lexer("@x.");
+        assert_eq!(lex.next(), Some(Ok(SyntaxKind::ANNOTATION_KEY)));
+        assert_eq!(lex.slice(), "@x");
     }
 }
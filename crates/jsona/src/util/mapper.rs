@@ -1,8 +1,11 @@
 //! Utilities for mapping between offset:length bytes and col:row character positions.
 
+use once_cell::sync::OnceCell;
 use rowan::{TextRange, TextSize};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
 
 #[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Default, Serialize, Deserialize)]
 pub struct Position {
@@ -42,26 +45,121 @@ impl Range {
 }
 
 /// Inclusive offset range in characters instead of bytes.
+#[deprecated(note = "not used by the mapper, ranges are byte offsets or line and column positions")]
+#[allow(dead_code)]
 #[derive(Debug, Clone, Copy)]
 pub struct CharacterRange(u64, u64);
 
+/// The unit that columns are counted in.
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone, Default, Serialize, Deserialize)]
+pub enum PositionEncoding {
+    /// UTF-8 code units, which are bytes.
+    #[serde(rename = "utf-8")]
+    Utf8,
+    /// UTF-16 code units.
+    #[default]
+    #[serde(rename = "utf-16")]
+    Utf16,
+    /// UTF-32 code units, which are characters.
+    #[serde(rename = "utf-32")]
+    Utf32,
+}
+
+impl PositionEncoding {
+    /// The name used by the language server protocol.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PositionEncoding::Utf8 => "utf-8",
+            PositionEncoding::Utf16 => "utf-16",
+            PositionEncoding::Utf32 => "utf-32",
+        }
+    }
+
+    fn char_len(&self, utf8_len: u32) -> u32 {
+        match self {
+            PositionEncoding::Utf8 => utf8_len,
+            PositionEncoding::Utf16 if utf8_len == 4 => 2,
+            PositionEncoding::Utf16 | PositionEncoding::Utf32 => 1,
+        }
+    }
+}
+
+impl fmt::Display for PositionEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for PositionEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "utf-8" => Ok(PositionEncoding::Utf8),
+            "utf-16" => Ok(PositionEncoding::Utf16),
+            "utf-32" => Ok(PositionEncoding::Utf32),
+            _ => Err(format!("unknown position encoding `{s}`")),
+        }
+    }
+}
+
+/// A multi-byte character, relative to the start of its line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct WideChar {
+    start: u32,
+    len: u32,
+}
+
+impl WideChar {
+    fn end(&self) -> u32 {
+        self.start + self.len
+    }
+}
+
 /// A mapper that translates offset:length bytes to
-/// 1-based line:row characters.
+/// line:column positions.
+///
+/// Only the start of each line and the multi-byte characters are remembered,
+/// columns are computed on demand in any [PositionEncoding].
 #[derive(Debug, Clone)]
 pub struct Mapper {
-    /// Mapping offsets to positions.
-    offset_to_position: BTreeMap<TextSize, Position>,
+    /// Byte offset of the start of each line.
+    line_starts: Vec<TextSize>,
+
+    /// Character index of the start of each line.
+    line_chars: Vec<u64>,
+
+    /// Multi-byte characters of the lines that have any.
+    wide_chars: BTreeMap<usize, Vec<WideChar>>,
+
+    /// Length of the source.
+    len: TextSize,
 
-    /// Mapping positions to offsets.
-    position_to_offset: BTreeMap<Position, TextSize>,
+    /// The encoding of columns used by default.
+    encoding: PositionEncoding,
 
-    /// Line count.
-    lines: usize,
+    /// Lines and columns start at 1 instead of 0.
+    base: u64,
 
-    /// Ending position.
-    end: Position,
+    /// Maps of every offset and position, built by [mappings](Self::mappings).
+    mappings: OnceCell<Mappings>,
 }
 
+type Mappings = (BTreeMap<TextSize, Position>, BTreeMap<Position, TextSize>);
+
+impl PartialEq for Mapper {
+    fn eq(&self, other: &Self) -> bool {
+        self.line_starts == other.line_starts
+            && self.line_chars == other.line_chars
+            && self.wide_chars == other.wide_chars
+            && self.len == other.len
+            && self.encoding == other.encoding
+            && self.base == other.base
+    }
+}
+
+impl Eq for Mapper {}
+
 impl Mapper {
     /// Creates a new Mapper that remembers where
     /// each line starts and ends.
@@ -69,18 +167,203 @@ impl Mapper {
     /// Uses UTF-16 character sizes for positions.
     #[must_use]
     pub fn new_utf16(source: &str, one_based: bool) -> Self {
-        Self::new_impl(source, true, if one_based { 1 } else { 0 })
+        Self::new(source, PositionEncoding::Utf16, one_based)
     }
 
-    /// Uses UTF-8 character sizes for positions.
+    /// Uses one column per character for positions, same as [new_utf32](Self::new_utf32).
     #[must_use]
     pub fn new_utf8(source: &str, one_based: bool) -> Self {
-        Self::new_impl(source, false, if one_based { 1 } else { 0 })
+        Self::new(source, PositionEncoding::Utf32, one_based)
+    }
+
+    /// Uses UTF-8 code units, that is bytes, for positions.
+    #[must_use]
+    pub fn new_utf8_bytes(source: &str, one_based: bool) -> Self {
+        Self::new(source, PositionEncoding::Utf8, one_based)
+    }
+
+    /// Uses UTF-32 character sizes, that is one per character, for positions.
+    #[must_use]
+    pub fn new_utf32(source: &str, one_based: bool) -> Self {
+        Self::new(source, PositionEncoding::Utf32, one_based)
+    }
+
+    #[must_use]
+    pub fn new(source: &str, encoding: PositionEncoding, one_based: bool) -> Self {
+        let mut line_starts = vec![TextSize::from(0)];
+        let mut line_chars = vec![0];
+        let mut wide_chars: BTreeMap<usize, Vec<WideChar>> = BTreeMap::new();
+        let mut line_start = 0;
+        let mut chars = 0;
+
+        for (offset, c) in source.char_indices() {
+            chars += 1;
+            let len = c.len_utf8();
+            if len > 1 {
+                wide_chars
+                    .entry(line_starts.len() - 1)
+                    .or_default()
+                    .push(WideChar {
+                        start: (offset - line_start) as u32,
+                        len: len as u32,
+                    });
+            }
+            if c == '\n' {
+                line_start = offset + 1;
+                line_starts.push(TextSize::from(line_start as u32));
+                line_chars.push(chars);
+            }
+        }
+
+        Self {
+            line_starts,
+            line_chars,
+            wide_chars,
+            len: TextSize::from(source.len() as u32),
+            encoding,
+            base: if one_based { 1 } else { 0 },
+            mappings: OnceCell::new(),
+        }
+    }
+
+    /// Update the mapper after the text in `range` was replaced with `text`.
+    ///
+    /// Only the lines touched by the edit are scanned, the following lines are shifted.
+    /// Returns `None` if the range is not in the source.
+    pub fn apply_edit(&mut self, range: TextRange, text: &str) -> Option<()> {
+        if range.end() > self.len {
+            return None;
+        }
+        let start_line = self.line_starts.partition_point(|v| *v <= range.start()) - 1;
+        let end_line = self.line_starts.partition_point(|v| *v <= range.end()) - 1;
+        let start_index = self.locate(range.start(), PositionEncoding::Utf32)?.index;
+        let end_index = self.locate(range.end(), PositionEncoding::Utf32)?.index;
+        let prefix = u32::from(range.start() - self.line_starts[start_line]);
+        let suffix = u32::from(range.end() - self.line_starts[end_line]);
+
+        let mut new_starts = vec![];
+        let mut new_chars = vec![];
+        let mut new_wide_chars: BTreeMap<usize, Vec<WideChar>> = BTreeMap::new();
+        let head: Vec<WideChar> = self
+            .line_wide_chars(start_line)
+            .iter()
+            .filter(|c| c.start < prefix)
+            .copied()
+            .collect();
+        if !head.is_empty() {
+            new_wide_chars.insert(start_line, head);
+        }
+        let mut line_start = 0;
+        let mut column = prefix;
+        let mut chars = start_index;
+        for (offset, c) in text.char_indices() {
+            chars += 1;
+            let len = c.len_utf8();
+            if len > 1 {
+                new_wide_chars
+                    .entry(start_line + new_starts.len())
+                    .or_default()
+                    .push(WideChar {
+                        start: column + (offset - line_start) as u32,
+                        len: len as u32,
+                    });
+            }
+            if c == '\n' {
+                line_start = offset + 1;
+                column = 0;
+                new_starts.push(range.start() + TextSize::from(line_start as u32));
+                new_chars.push(chars);
+            }
+        }
+        let last_line = start_line + new_starts.len();
+        let last_column = column + (text.len() - line_start) as u32;
+        for c in self
+            .line_wide_chars(end_line)
+            .iter()
+            .filter(|c| c.start >= suffix)
+        {
+            new_wide_chars.entry(last_line).or_default().push(WideChar {
+                start: c.start - suffix + last_column,
+                len: c.len,
+            });
+        }
+
+        let delta_bytes =
+            i64::from(u32::from(TextSize::of(text))) - i64::from(u32::from(range.len()));
+        let delta_chars = chars as i64 - end_index as i64;
+        let delta_lines = new_starts.len() as i64 - (end_line - start_line) as i64;
+        let shift = |v: u64, delta: i64| (v as i64 + delta) as u64;
+
+        let tail_starts = self.line_starts.split_off(end_line + 1);
+        let tail_chars = self.line_chars.split_off(end_line + 1);
+        self.line_starts.truncate(start_line + 1);
+        self.line_chars.truncate(start_line + 1);
+        self.line_starts.extend(new_starts);
+        self.line_chars.extend(new_chars);
+        self.line_starts.extend(
+            tail_starts
+                .into_iter()
+                .map(|v| TextSize::from(shift(u32::from(v).into(), delta_bytes) as u32)),
+        );
+        self.line_chars
+            .extend(tail_chars.into_iter().map(|v| shift(v, delta_chars)));
+
+        let wide_chars = std::mem::take(&mut self.wide_chars);
+        for (line, chars) in wide_chars {
+            if line < start_line {
+                new_wide_chars.insert(line, chars);
+            } else if line > end_line {
+                new_wide_chars.insert(shift(line as u64, delta_lines) as usize, chars);
+            }
+        }
+        self.wide_chars = new_wide_chars;
+        self.len = TextSize::from(shift(u32::from(self.len).into(), delta_bytes) as u32);
+        self.mappings = OnceCell::new();
+        Some(())
+    }
+
+    /// The encoding of columns used by [offset](Self::offset) and [position](Self::position).
+    #[must_use]
+    pub fn encoding(&self) -> PositionEncoding {
+        self.encoding
     }
 
     #[must_use]
     pub fn offset(&self, position: Position) -> Option<TextSize> {
-        self.position_to_offset.get(&position).copied()
+        self.offset_in(position, self.encoding)
+    }
+
+    /// Same as [offset](Self::offset), but with the column in the given encoding.
+    ///
+    /// The index of the position is ignored. A column past the end of its line is
+    /// the end of the line, as positions of the language server protocol are.
+    #[must_use]
+    pub fn offset_in(&self, position: Position, encoding: PositionEncoding) -> Option<TextSize> {
+        let line = position.line.checked_sub(self.base)? as usize;
+        let column = position.column.checked_sub(self.base)?;
+        let line_start = *self.line_starts.get(line)?;
+        // The last column is the line feed, except for the last line.
+        let line_end = match self.line_starts.get(line + 1) {
+            Some(next) => *next - TextSize::from(1),
+            None => self.len,
+        };
+        let mut offset = u32::try_from(column).unwrap_or(u32::MAX);
+        let wide_chars = self.line_wide_chars(line);
+        for c in wide_chars {
+            if c.start >= offset {
+                break;
+            }
+            offset = offset.saturating_add(c.len - encoding.char_len(c.len));
+        }
+        // Inside a character.
+        if wide_chars
+            .iter()
+            .any(|c| c.start < offset && offset < c.end())
+        {
+            return None;
+        }
+        let offset = u32::from(line_start).saturating_add(offset);
+        Some(TextSize::from(offset).min(line_end))
     }
 
     #[must_use]
@@ -91,7 +374,48 @@ impl Mapper {
 
     #[must_use]
     pub fn position(&self, offset: TextSize) -> Option<Position> {
-        self.offset_to_position.get(&offset).copied()
+        self.position_in(offset, self.encoding)
+    }
+
+    /// Same as [position](Self::position), but with the column in the given encoding.
+    ///
+    /// An offset inside of a character is the position of that character, the end
+    /// of the source has the index of the last character.
+    #[must_use]
+    pub fn position_in(&self, offset: TextSize, encoding: PositionEncoding) -> Option<Position> {
+        let mut position = self.locate(offset, encoding)?;
+        if offset == self.len && position.index > 0 {
+            position.index -= 1;
+        }
+        Some(position)
+    }
+
+    /// The position of an offset, where the index counts the characters before it.
+    fn locate(&self, offset: TextSize, encoding: PositionEncoding) -> Option<Position> {
+        if offset > self.len {
+            return None;
+        }
+        let line = self.line_starts.partition_point(|v| *v <= offset) - 1;
+        let wide_chars = self.line_wide_chars(line);
+        let mut offset = u32::from(offset - self.line_starts[line]);
+        if let Some(c) = wide_chars
+            .iter()
+            .find(|c| c.start < offset && offset < c.end())
+        {
+            offset = c.start;
+        }
+        let mut column = offset;
+        let mut chars = offset;
+        for c in wide_chars.iter().take_while(|c| c.start < offset) {
+            column -= c.len - encoding.char_len(c.len);
+            chars -= c.len - 1;
+        }
+        let chars = self.line_chars[line] + u64::from(chars);
+        Some(Position {
+            index: chars,
+            line: line as u64 + self.base,
+            column: u64::from(column) + self.base,
+        })
     }
 
     #[must_use]
@@ -100,14 +424,29 @@ impl Mapper {
             .and_then(|start| self.position(range.end()).map(|end| Range { start, end }))
     }
 
+    /// Maps of every byte offset to its position and back.
+    ///
+    /// The maps are built on first use and take memory for every byte of the source.
+    #[deprecated(note = "use `position` and `offset`, which look up the line index instead")]
     #[must_use]
     pub fn mappings(&self) -> (&BTreeMap<TextSize, Position>, &BTreeMap<Position, TextSize>) {
-        (&self.offset_to_position, &self.position_to_offset)
+        let (offset_to_position, position_to_offset) = self.mappings.get_or_init(|| {
+            let mut offset_to_position = BTreeMap::new();
+            let mut position_to_offset = BTreeMap::new();
+            for offset in 0..=u32::from(self.len) {
+                if let Some(position) = self.position(offset.into()) {
+                    offset_to_position.insert(offset.into(), position);
+                    position_to_offset.insert(position, offset.into());
+                }
+            }
+            (offset_to_position, position_to_offset)
+        });
+        (offset_to_position, position_to_offset)
     }
 
     #[must_use]
     pub fn line_count(&self) -> usize {
-        self.lines
+        self.line_starts.len() - 1 + self.base as usize
     }
 
     #[must_use]
@@ -118,84 +457,135 @@ impl Mapper {
                 line: 0,
                 column: 0,
             },
-            end: self.end,
-        }
-    }
-
-    fn new_impl(source: &str, utf16: bool, base: u64) -> Self {
-        let mut offset_to_position = BTreeMap::new();
-        let mut position_to_offset = BTreeMap::new();
-
-        let mut line: u64 = base;
-        let mut column: u64 = base;
-        let mut last_offset = 0;
-        let mut index: u64 = 0;
-
-        for (i, c) in source.chars().enumerate() {
-            index = i as u64;
-            let new_offset = last_offset + c.len_utf8();
-
-            let character_size = if utf16 { c.len_utf16() } else { 1 };
-
-            offset_to_position.extend((last_offset..new_offset).map(|b| {
-                (
-                    TextSize::from(b as u32),
-                    Position {
-                        index,
-                        line,
-                        column,
-                    },
-                )
-            }));
-
-            position_to_offset.extend((last_offset..new_offset).map(|b| {
-                (
-                    Position {
-                        index,
-                        line,
-                        column,
-                    },
-                    TextSize::from(b as u32),
-                )
-            }));
-
-            last_offset = new_offset;
-
-            column += character_size as u64;
-            if c == '\n' {
-                // LF is at the start of each line.
-                line += 1;
-                column = base;
-            }
+            end: self.position(self.len).unwrap_or_default(),
         }
+    }
 
-        // Last imaginary character.
-        offset_to_position.insert(
-            TextSize::from(last_offset as u32),
-            Position {
-                index,
-                line,
-                column,
-            },
+    fn line_wide_chars(&self, line: usize) -> &[WideChar] {
+        self.wide_chars
+            .get(&line)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "a\u{e9}b\n\u{1F600}x\r\n\n\u{4e2d}";
+
+    #[test]
+    fn test_position() {
+        let mapper = Mapper::new_utf16(SOURCE, false);
+        let position = |offset: u32| mapper.position(offset.into()).unwrap();
+        assert_eq!(position(0), Position::new(0, 0, 0));
+        assert_eq!(position(1), Position::new(1, 0, 1));
+        assert_eq!(position(2), Position::new(1, 0, 1));
+        assert_eq!(position(3), Position::new(2, 0, 2));
+        assert_eq!(position(4), Position::new(3, 0, 3));
+        assert_eq!(position(5), Position::new(4, 1, 0));
+        assert_eq!(position(9), Position::new(5, 1, 2));
+        assert_eq!(position(12), Position::new(8, 2, 0));
+        assert_eq!(position(16), Position::new(9, 3, 1));
+        assert_eq!(mapper.position(17.into()), None);
+
+        assert_eq!(
+            mapper.position_in(9.into(), PositionEncoding::Utf8),
+            Some(Position::new(5, 1, 4))
         );
-        position_to_offset.insert(
-            Position {
-                index,
-                line,
-                column,
-            },
-            TextSize::from(last_offset as u32),
+        assert_eq!(
+            mapper.position_in(9.into(), PositionEncoding::Utf32),
+            Some(Position::new(5, 1, 1))
         );
+        assert_eq!(mapper.line_count(), 3);
+        assert_eq!(mapper.all_range().end, Position::new(9, 3, 1));
+        assert_eq!(
+            Mapper::new_utf8(SOURCE, false).position(9.into()),
+            Some(Position::new(5, 1, 1))
+        );
+        assert_eq!(
+            Mapper::new_utf8_bytes(SOURCE, false).position(9.into()),
+            Some(Position::new(5, 1, 4))
+        );
+        assert_eq!(
+            Mapper::new_utf16("", false).all_range().end,
+            Position::new(0, 0, 0)
+        );
+    }
 
-        Self {
-            offset_to_position,
-            position_to_offset,
-            lines: line as usize,
-            end: Position {
-                index,
-                line,
-                column,
-            },
+    #[test]
+    #[allow(deprecated)]
+    fn test_mappings() {
+        let mapper = Mapper::new_utf16(SOURCE, false);
+        let (offset_to_position, position_to_offset) = mapper.mappings();
+        assert_eq!(offset_to_position.len(), SOURCE.len() + 1);
+        assert_eq!(offset_to_position[&2.into()], Position::new(1, 0, 1));
+        assert_eq!(position_to_offset[&Position::new(1, 0, 1)], 2.into());
+        assert_eq!(position_to_offset[&Position::new(9, 3, 1)], 16.into());
+    }
+
+    #[test]
+    fn test_apply_edit() {
+        let edits: &[(u32, u32, &str)] = &[
+            (0, 0, ""),
+            (1, 3, "x"),
+            (3, 3, "\u{e9}\n\u{1F600}"),
+            (5, 12, ""),
+            (4, 13, "\r\n"),
+            (16, 16, "\n\u{4e2d}\u{4e2d}"),
+            (0, 16, ""),
+        ];
+        for (start, end, text) in edits {
+            let range = TextRange::new((*start).into(), (*end).into());
+            let mut source = SOURCE.to_string();
+            source.replace_range(std::ops::Range::<usize>::from(range), text);
+            let mut mapper = Mapper::new_utf16(SOURCE, false);
+            mapper.apply_edit(range, text).unwrap();
+            assert_eq!(mapper, Mapper::new_utf16(&source, false), "{source:?}");
+        }
+        let mut mapper = Mapper::new_utf16(SOURCE, false);
+        assert!(mapper
+            .apply_edit(TextRange::new(0.into(), 17.into()), "")
+            .is_none());
+    }
+
+    #[test]
+    fn test_offset() {
+        let mapper = Mapper::new_utf16(SOURCE, true);
+        let offset = |line: u64, column: u64, encoding: PositionEncoding| {
+            mapper
+                .offset_in(Position::new(0, line, column), encoding)
+                .map(u32::from)
+        };
+        assert_eq!(offset(1, 1, PositionEncoding::Utf16), Some(0));
+        assert_eq!(offset(1, 3, PositionEncoding::Utf16), Some(3));
+        assert_eq!(offset(1, 4, PositionEncoding::Utf16), Some(4));
+        assert_eq!(offset(1, 5, PositionEncoding::Utf16), Some(4));
+        assert_eq!(offset(1, 6, PositionEncoding::Utf16), Some(4));
+        assert_eq!(offset(1, u64::MAX, PositionEncoding::Utf8), Some(4));
+        assert_eq!(offset(2, 2, PositionEncoding::Utf16), None);
+        assert_eq!(offset(2, 3, PositionEncoding::Utf16), Some(9));
+        assert_eq!(offset(2, 2, PositionEncoding::Utf32), Some(9));
+        assert_eq!(offset(2, 5, PositionEncoding::Utf8), Some(9));
+        assert_eq!(offset(2, 3, PositionEncoding::Utf8), None);
+        assert_eq!(offset(4, 2, PositionEncoding::Utf16), Some(16));
+        assert_eq!(offset(4, 3, PositionEncoding::Utf16), Some(16));
+        assert_eq!(offset(5, 1, PositionEncoding::Utf16), None);
+        assert_eq!(offset(0, 1, PositionEncoding::Utf16), None);
+
+        for encoding in [
+            PositionEncoding::Utf8,
+            PositionEncoding::Utf16,
+            PositionEncoding::Utf32,
+        ] {
+            for (i, _) in SOURCE.char_indices() {
+                let position = mapper.position_in((i as u32).into(), encoding).unwrap();
+                assert_eq!(
+                    mapper.offset_in(position, encoding),
+                    Some((i as u32).into())
+                );
+            }
         }
     }
 }
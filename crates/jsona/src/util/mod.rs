@@ -4,4 +4,5 @@ pub(crate) mod shared;
 
 mod glob;
 pub use glob::glob;
+pub(crate) use quote::unquote_json5;
 pub use quote::{check_quote, quote, unquote, validate_quote};
@@ -9,6 +9,7 @@ use logos::{Lexer, Logos};
 /// \r         - carriage return (U+000D)
 /// \"         - quote           (U+0022)
 /// \\         - backslash       (U+005C)
+/// \/         - slash           (U+002F), not in the JSONA dialect
 /// \uXXXX     - unicode         (U+XXXX)
 /// \UXXXXXXXX - unicode         (U+XXXXXXXX)
 #[derive(Logos, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -46,6 +47,9 @@ enum Escape {
     #[token(r#"\\"#)]
     Backslash,
 
+    #[token(r#"\/"#)]
+    Slash,
+
     #[regex(r#"\\x[0-9A-Fa-f_][0-9A-Fa-f_]"#)]
     Hex,
 
@@ -67,6 +71,16 @@ use Escape::*;
 ///
 /// If it fails, the index of failure is returned.
 pub fn unquote(s: &str) -> Result<String, usize> {
+    unescape(s, false)
+}
+
+/// Same as [unquote], but a line continuation only removes the backslash and the line break,
+/// as in JSON5, instead of the whitespace around the line break too.
+pub(crate) fn unquote_json5(s: &str) -> Result<String, usize> {
+    unescape(s, true)
+}
+
+fn unescape(s: &str, keep_indent: bool) -> Result<String, usize> {
     let mut new_s = String::with_capacity(s.len());
     let mut lexer: Lexer<Escape> = Lexer::new(s);
 
@@ -83,7 +97,13 @@ pub fn unquote(s: &str) -> Result<String, usize> {
                 SingleQuote => new_s += "\u{0027}",
                 BacktickQuote => new_s += "\u{0060}",
                 Backslash => new_s += "\u{005C}",
-                Newline => {}
+                Slash => new_s += "/",
+                Newline => {
+                    if keep_indent {
+                        let slice = lexer.slice();
+                        new_s += &slice[slice.find('\n').map_or(slice.len(), |i| i + 1)..];
+                    }
+                }
                 Hex | Unicode => {
                     new_s += &std::char::from_u32(
                         u32::from_str_radix(&lexer.slice()[2..], 16)
@@ -151,6 +171,7 @@ pub fn validate_quote(s: &str) -> Result<(), Vec<usize>> {
                 DoubleQuote => {}
                 BacktickQuote => {}
                 Backslash => {}
+                Slash => {}
                 Newline => {}
                 Hex | Unicode => {
                     let char_val = match u32::from_str_radix(&lexer.slice()[2..], 16) {
@@ -247,26 +268,14 @@ pub fn quote(s: &str, force: bool) -> String {
                     output.push_str("\\r");
                 }
             }
-            '\'' => {
-                if quote_char == Some('\'') {
-                    output.push_str("\\'");
-                } else {
-                    output.push(c);
-                }
+            '\'' if quote_char == Some('\'') => {
+                output.push_str("\\'");
             }
-            '"' => {
-                if quote_char == Some('"') {
-                    output.push_str("\\\"");
-                } else {
-                    output.push(c);
-                }
+            '"' if quote_char == Some('"') => {
+                output.push_str("\\\"");
             }
-            '`' => {
-                if quote_char == Some('`') {
-                    output.push_str("\\`");
-                } else {
-                    output.push(c);
-                }
+            '`' if quote_char == Some('`') => {
+                output.push_str("\\`");
             }
             _ => {
                 output.push(c);
@@ -345,6 +354,12 @@ mod tests {
         assert_eq!(unquote(r"\w"), Err(0));
         assert_eq!(unquote("'\\w'"), Err(1));
         assert_eq!(unquote(r"'\w'"), Err(1));
+
+        assert_eq!(&unquote("'a\\ \n  b'").unwrap(), "ab");
+        assert_eq!(&unquote("'a\\\r\n  b'").unwrap(), "ab");
+        assert_eq!(&unquote_json5("'a\\\n  b'").unwrap(), "a  b");
+        assert_eq!(&unquote_json5("'a\\\r\n  b'").unwrap(), "a  b");
+        assert_eq!(&unquote_json5("'a\\\nb'").unwrap(), "ab");
     }
 
     #[test]